@@ -0,0 +1,336 @@
+use crate::objs::Matrix4x4;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Interpolation {
+    // Holds each key's value until the next key
+    Step,
+    // Straight blends, with rotations slerped
+    Linear,
+    // Hermite curves through the keys, see Track::tangents
+    Cubic,
+}
+
+// Keyed values of N components, like a translation (3) or a rotation quaternion (4)
+#[derive(Clone)]
+pub struct Track<const N: usize> {
+    // Key times in seconds, increasing
+    pub times: Vec<f64>,
+    pub values: Vec<[f64; N]>,
+    // Cubic only: (incoming, outgoing) slope per second at each key. Left empty, slopes are made
+    // Catmull-Rom style from the neighbouring keys.
+    pub tangents: Vec<([f64; N], [f64; N])>,
+    pub interpolation: Interpolation,
+}
+
+impl<const N: usize> Track<N> {
+    pub fn new(times: Vec<f64>, values: Vec<[f64; N]>, interpolation: Interpolation) -> Self {
+        Self { times, values, tangents: Vec::new(), interpolation }
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.times.last().cloned().unwrap_or(0.0)
+    }
+
+    // Value at time t, holding the first and last values outside the keyed range
+    pub fn sample(&self, t: f64) -> [f64; N] {
+        let (i, u) = match self.locate(t) {
+            Some(found) => found,
+            None => return self.values.first().cloned().unwrap_or([0.0; N]),
+        };
+        let (a, b) = (self.values[i], self.values[i + 1]);
+        match self.interpolation {
+            Interpolation::Step => if u >= 1.0 { b } else { a },
+            Interpolation::Linear => std::array::from_fn(|k| a[k] + (b[k] - a[k])*u),
+            Interpolation::Cubic => {
+                let dt = self.times[i + 1] - self.times[i];
+                let (m0, m1) = (self.tangent(i, true), self.tangent(i + 1, false));
+                let (u2, u3) = (u*u, u*u*u);
+                let (h00, h10, h01, h11) = (2.0*u3 - 3.0*u2 + 1.0, u3 - 2.0*u2 + u, -2.0*u3 + 3.0*u2, u3 - u2);
+                std::array::from_fn(|k| h00*a[k] + h10*dt*m0[k] + h01*b[k] + h11*dt*m1[k])
+            }
+        }
+    }
+
+    // Index of the key at or before t and how far t is towards the next one, or None when t is
+    // outside the keys (or there is only one)
+    fn locate(&self, t: f64) -> Option<(usize, f64)> {
+        let n = self.times.len().min(self.values.len());
+        if n < 2 || t <= self.times[0] {
+            return None;
+        }
+        if t >= self.times[n - 1] {
+            return Some((n - 2, 1.0));
+        }
+        let i = self.times[..n].partition_point(|k| *k <= t) - 1;
+        let span = self.times[i + 1] - self.times[i];
+        Some((i, if span > 0.0 { (t - self.times[i])/span } else { 0.0 }))
+    }
+
+    fn tangent(&self, i: usize, outgoing: bool) -> [f64; N] {
+        if let Some((incoming, out)) = self.tangents.get(i) {
+            return if outgoing { *out } else { *incoming };
+        }
+        let (lo, hi) = (i.saturating_sub(1), (i + 1).min(self.values.len() - 1));
+        let dt = self.times[hi] - self.times[lo];
+        if dt <= 0.0 {
+            return [0.0; N];
+        }
+        std::array::from_fn(|k| (self.values[hi][k] - self.values[lo][k])/dt)
+    }
+}
+
+impl Track<4> {
+    // Quaternion (x, y, z, w) at time t. Linear keys are slerped; cubic ones are blended as plain
+    // components and renormalised, which is what glTF asks for.
+    pub fn sample_rotation(&self, t: f64) -> [f64; 4] {
+        let q = match (self.interpolation, self.locate(t)) {
+            (Interpolation::Linear, Some((i, u))) => slerp(self.values[i], self.values[i + 1], u),
+            _ => self.sample(t),
+        };
+        normalize(q)
+    }
+}
+
+fn normalize(q: [f64; 4]) -> [f64; 4] {
+    let length = q.iter().map(|c| c*c).sum::<f64>().sqrt();
+    if length > 0.0 { q.map(|c| c/length) } else { [0.0, 0.0, 0.0, 1.0] }
+}
+
+// Spherical blend along the shorter arc between two unit quaternions
+pub fn slerp(a: [f64; 4], b: [f64; 4], u: f64) -> [f64; 4] {
+    let mut dot: f64 = (0..4).map(|k| a[k]*b[k]).sum();
+    let b = if dot < 0.0 { dot = -dot; b.map(|c| -c) } else { b };
+    // Nearly equal rotations blend linearly, where the sine below would lose precision
+    if dot > 0.9995 {
+        return normalize(std::array::from_fn(|k| a[k] + (b[k] - a[k])*u));
+    }
+    let theta = dot.min(1.0).acos();
+    let (wa, wb) = (((1.0 - u)*theta).sin()/theta.sin(), (u*theta).sin()/theta.sin());
+    std::array::from_fn(|k| a[k]*wa + b[k]*wb)
+}
+
+// Quaternion turning by angle radians about a unit axis
+pub fn axis_angle(axis: [f64; 3], angle: f64) -> [f64; 4] {
+    let s = (angle*0.5).sin();
+    [axis[0]*s, axis[1]*s, axis[2]*s, (angle*0.5).cos()]
+}
+
+// Translation, rotation and scale applied scale first, as glTF composes them
+#[derive(Copy, Clone)]
+pub struct Trs {
+    pub translation: [f64; 3],
+    pub rotation: [f64; 4],
+    pub scale: [f64; 3],
+}
+
+impl Trs {
+    pub fn identity() -> Self {
+        Self { translation: [0.0; 3], rotation: [0.0, 0.0, 0.0, 1.0], scale: [1.0; 3] }
+    }
+
+    // Splits a matrix without shear into its parts. Mirroring is put into the x scale.
+    pub fn from_matrix(mat: &Matrix4x4) -> Self {
+        let m = &mat.m;
+        let translation = [m[3][0], m[3][1], m[3][2]];
+        let mut scale: [f64; 3] = std::array::from_fn(|r| (m[r][0]*m[r][0] + m[r][1]*m[r][1] + m[r][2]*m[r][2]).sqrt());
+        if mat.determinant3() < 0.0 {
+            scale[0] = -scale[0];
+        }
+        let r: [[f64; 3]; 3] = std::array::from_fn(|i| std::array::from_fn(|j| if scale[i] != 0.0 { m[i][j]/scale[i] } else { 0.0 }));
+
+        // Inverse of make_rotation_quat, starting from its largest component for stability
+        let trace = r[0][0] + r[1][1] + r[2][2];
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt()*2.0;
+            [(r[1][2] - r[2][1])/s, (r[2][0] - r[0][2])/s, (r[0][1] - r[1][0])/s, 0.25*s]
+        }
+        else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+            let s = (1.0 + r[0][0] - r[1][1] - r[2][2]).sqrt()*2.0;
+            [0.25*s, (r[0][1] + r[1][0])/s, (r[2][0] + r[0][2])/s, (r[1][2] - r[2][1])/s]
+        }
+        else if r[1][1] > r[2][2] {
+            let s = (1.0 + r[1][1] - r[0][0] - r[2][2]).sqrt()*2.0;
+            [(r[0][1] + r[1][0])/s, 0.25*s, (r[1][2] + r[2][1])/s, (r[2][0] - r[0][2])/s]
+        }
+        else {
+            let s = (1.0 + r[2][2] - r[0][0] - r[1][1]).sqrt()*2.0;
+            [(r[2][0] + r[0][2])/s, (r[1][2] + r[2][1])/s, 0.25*s, (r[0][1] - r[1][0])/s]
+        };
+        Self { translation, rotation: normalize(q), scale }
+    }
+
+    pub fn to_matrix(&self) -> Matrix4x4 {
+        let (t, s) = (self.translation, self.scale);
+        let mut mat_scale = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        mat_scale.make_scale(s[0], s[1], s[2]);
+        let mut mat_rot = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        mat_rot.make_rotation_quat(self.rotation);
+        let mut mat_trans = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        mat_trans.make_translation(t[0], t[1], t[2]);
+        mat_scale.mul_mat_mat(&mat_rot).mul_mat_mat(&mat_trans)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Target {
+    Node(usize),
+    // The whole framed scene, turning about the centre it was framed on
+    Scene,
+}
+
+#[derive(Clone)]
+pub enum Property {
+    Translation(Track<3>),
+    Rotation(Track<4>),
+    Scale(Track<3>),
+    // Weight of one of the morph targets of the node's meshes
+    Weight(usize, Track<1>),
+}
+
+#[derive(Clone)]
+pub struct Channel {
+    pub target: Target,
+    pub property: Property,
+}
+
+#[derive(Clone)]
+pub struct Animation {
+    pub name: String,
+    pub channels: Vec<Channel>,
+}
+
+impl Animation {
+    pub fn new(name: String) -> Self {
+        Self { name, channels: Vec::new() }
+    }
+
+    // Time of the last key on any channel
+    pub fn duration(&self) -> f64 {
+        self.channels.iter().map(|c| match &c.property {
+            Property::Translation(track) | Property::Scale(track) => track.duration(),
+            Property::Rotation(track) => track.duration(),
+            Property::Weight(_, track) => track.duration(),
+        }).fold(0.0, f64::max)
+    }
+
+    // Pose of target at time t: rest with whichever parts this animation keys replaced
+    pub fn pose(&self, target: Target, rest: &Trs, t: f64) -> Trs {
+        let mut pose = *rest;
+        for channel in self.channels.iter().filter(|c| c.target == target) {
+            match &channel.property {
+                Property::Translation(track) => pose.translation = track.sample(t),
+                Property::Rotation(track) => pose.rotation = track.sample_rotation(t),
+                Property::Scale(track) => pose.scale = track.sample(t),
+                Property::Weight(..) => {}
+            }
+        }
+        pose
+    }
+
+    // Sets the morph target weights this animation keys for target at time t, leaving the others
+    pub fn morph_weights(&self, target: Target, weights: &mut [f64], t: f64) {
+        for channel in self.channels.iter().filter(|c| c.target == target) {
+            if let Property::Weight(i, track) = &channel.property {
+                if let Some(weight) = weights.get_mut(*i) {
+                    *weight = track.sample(t)[0];
+                }
+            }
+        }
+    }
+
+    // One turn of the scene about the vertical axis every period seconds
+    pub fn turntable(period: f64) -> Self {
+        let times: Vec<f64> = (0..=4).map(|k| k as f64*period/4.0).collect();
+        let values = (0..=4).map(|k| axis_angle([0.0, 1.0, 0.0], k as f64*std::f64::consts::FRAC_PI_2)).collect();
+        let mut animation = Self::new("Turntable".to_string());
+        animation.channels.push(Channel { target: Target::Scene, property: Property::Rotation(Track::new(times, values, Interpolation::Linear)) });
+        animation
+    }
+}
+
+// Animation time, moved on by the wall clock while playing
+#[derive(Copy, Clone)]
+pub struct Clock {
+    pub time: f64,
+    // Animation seconds per wall clock second; negative plays backwards
+    pub speed: f64,
+    pub playing: bool,
+    // Wrap around at the ends instead of stopping there
+    pub looping: bool,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self { time: 0.0, speed: 1.0, playing: true, looping: true }
+    }
+
+    // Playing again after a one-shot animation of length duration has run off its end starts it over
+    pub fn play(&mut self, duration: f64) {
+        if !self.playing && !self.looping {
+            if self.speed < 0.0 && self.time <= 0.0 {
+                self.time = duration;
+            }
+            else if self.speed >= 0.0 && self.time >= duration {
+                self.time = 0.0;
+            }
+        }
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn seek(&mut self, time: f64) {
+        self.time = time;
+    }
+
+    // Moves on by dt wall clock seconds through an animation lasting duration seconds
+    pub fn advance(&mut self, dt: f64, duration: f64) {
+        if !self.playing {
+            return;
+        }
+        self.time += dt*self.speed;
+        if duration <= 0.0 {
+            self.time = 0.0;
+        }
+        else if self.looping {
+            self.time = self.time.rem_euclid(duration);
+        }
+        else {
+            // Only the end being played towards stops the clock, so a paused speed of 0 doesn't
+            let ran_off = if self.speed < 0.0 { self.time <= 0.0 } else { self.speed > 0.0 && self.time >= duration };
+            self.time = self.time.clamp(0.0, duration);
+            if ran_off {
+                self.playing = false;
+            }
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Animation controls shared with the UI. The UI sets the first group and the engine reports the rest.
+#[derive(Clone)]
+pub struct Playback {
+    pub playing: bool,
+    pub speed: f64,
+    pub looping: bool,
+    pub seek: Option<f64>,
+    // Index into animations
+    pub active: usize,
+    pub time: f64,
+    pub duration: f64,
+    pub animations: Vec<String>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        let clock = Clock::new();
+        Self { playing: clock.playing, speed: clock.speed, looping: clock.looping, seek: None, active: 0, time: 0.0, duration: 0.0, animations: Vec::new() }
+    }
+}
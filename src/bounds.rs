@@ -0,0 +1,181 @@
+use crate::objs::{ Matrix4x4, Mesh, Vec3d };
+
+// Axis aligned bounding box; an empty box has min > max so the first point sets both
+#[derive(Copy, Clone)]
+pub struct Aabb {
+    pub min: Vec3d,
+    pub max: Vec3d,
+}
+
+impl Aabb {
+    pub fn empty() -> Self {
+        Self { min: Vec3d::new(f64::MAX, f64::MAX, f64::MAX), max: Vec3d::new(f64::MIN, f64::MIN, f64::MIN) }
+    }
+
+    pub fn from_points(points: &[Vec3d]) -> Self {
+        let mut aabb = Self::empty();
+        points.iter().for_each(|p| aabb.include(p));
+        aabb
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn include(&mut self, p: &Vec3d) {
+        self.min = Vec3d::new(self.min.x.min(p.x), self.min.y.min(p.y), self.min.z.min(p.z));
+        self.max = Vec3d::new(self.max.x.max(p.x), self.max.y.max(p.y), self.max.z.max(p.z));
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut out = *self;
+        if !other.is_empty() {
+            out.include(&other.min);
+            out.include(&other.max);
+        }
+        out
+    }
+
+    pub fn center(&self) -> Vec3d {
+        (self.min + self.max)*0.5
+    }
+
+    pub fn size(&self) -> Vec3d {
+        self.max - self.min
+    }
+
+    pub fn corners(&self) -> [Vec3d; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3d::new(a.x, a.y, a.z), Vec3d::new(b.x, a.y, a.z), Vec3d::new(a.x, b.y, a.z), Vec3d::new(b.x, b.y, a.z),
+            Vec3d::new(a.x, a.y, b.z), Vec3d::new(b.x, a.y, b.z), Vec3d::new(a.x, b.y, b.z), Vec3d::new(b.x, b.y, b.z),
+        ]
+    }
+
+    // Box around the transformed corners, which still contains everything the original box did
+    pub fn transformed(&self, mat: &Matrix4x4) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+        let mut out = Aabb::empty();
+        for c in self.corners().iter() {
+            out.include(&mat.mul_mat_vec(c));
+        }
+        out
+    }
+
+    // Sphere through the corners, loose but cheap and enough for framing
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        let size = self.size();
+        BoundingSphere { center: self.center(), radius: size.dot(&size).sqrt()*0.5 }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct BoundingSphere {
+    pub center: Vec3d,
+    pub radius: f64,
+}
+
+impl BoundingSphere {
+    // Ritter's approximation: start from two far apart points, then grow to take in any stragglers
+    pub fn from_points(points: &[Vec3d]) -> Self {
+        let first = match points.first() {
+            Some(p) => *p,
+            None => return Self { center: Vec3d::new(0.0, 0.0, 0.0), radius: 0.0 },
+        };
+        let farthest = |from: Vec3d| *points.iter().max_by(|a, b| dist2(**a, from).total_cmp(&dist2(**b, from))).unwrap();
+        let a = farthest(first);
+        let b = farthest(a);
+
+        let mut center = (a + b)*0.5;
+        let mut radius = dist2(a, b).sqrt()*0.5;
+        for p in points.iter() {
+            let d = dist2(*p, center).sqrt();
+            if d > radius {
+                let grown = (radius + d)*0.5;
+                center = center + (*p - center)*((grown - radius)/d);
+                radius = grown;
+            }
+        }
+        Self { center, radius }
+    }
+
+    // How far in front of a camera with projection mat_proj the centre has to be for the whole
+    // sphere to fit the narrower of the two fields of view
+    pub fn fit_distance(&self, mat_proj: &Matrix4x4) -> f64 {
+        let tan_half_fov = 1.0/mat_proj.m[0][0].max(mat_proj.m[1][1]);
+        let sin_half_fov = tan_half_fov/(1.0 + tan_half_fov*tan_half_fov).sqrt();
+        self.radius/sin_half_fov
+    }
+
+    // Scales the radius by the largest axis scale so non-uniform transforms stay covered
+    pub fn transformed(&self, mat: &Matrix4x4) -> Self {
+        let scale = (0..3).map(|r| (mat.m[r][0].powi(2) + mat.m[r][1].powi(2) + mat.m[r][2].powi(2)).sqrt()).fold(0.0, f64::max);
+        Self { center: mat.mul_mat_vec(&self.center), radius: self.radius*scale }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Containment {
+    Outside,
+    Intersecting,
+    Inside,
+}
+
+// View space frustum as planes (point, inward normal) in the form the clipper uses
+pub struct Frustum {
+    pub planes: Vec<(Vec3d, Vec3d)>,
+}
+
+impl Frustum {
+    // Side planes come from the projection's x and y scale, the near plane is the one triangles are
+    // clipped against. Nothing is clipped at the far plane, so it isn't tested either.
+    pub fn from_projection(mat_proj: &Matrix4x4, near_z: f64) -> Self {
+        let (sx, sy) = (mat_proj.m[0][0], mat_proj.m[1][1]);
+        let origin = Vec3d::new(0.0, 0.0, 0.0);
+        let side = |x: f64, y: f64| {
+            let mut n = Vec3d::new(x, y, 1.0);
+            n.normalize();
+            (origin, n)
+        };
+        Self { planes: vec![
+            (Vec3d::new(0.0, 0.0, near_z), Vec3d::new(0.0, 0.0, 1.0)),
+            side(sx, 0.0), side(-sx, 0.0), side(0.0, sy), side(0.0, -sy),
+        ] }
+    }
+
+    // Classifies a box given in another space by its corners after mat takes them to view space
+    pub fn classify_aabb(&self, aabb: &Aabb, mat: &Matrix4x4) -> Containment {
+        if aabb.is_empty() {
+            return Containment::Outside;
+        }
+        let corners = aabb.corners().map(|c| mat.mul_mat_vec(&c));
+        let mut result = Containment::Inside;
+        for (p, n) in self.planes.iter() {
+            let inside = corners.iter().filter(|c| (**c - *p).dot(n) >= 0.0).count();
+            if inside == 0 {
+                return Containment::Outside;
+            }
+            if inside < corners.len() {
+                result = Containment::Intersecting;
+            }
+        }
+        result
+    }
+}
+
+fn dist2(a: Vec3d, b: Vec3d) -> f64 {
+    let d = a - b;
+    d.dot(&d)
+}
+
+impl Mesh {
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_points(&self.positions)
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::from_points(&self.positions)
+    }
+}
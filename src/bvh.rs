@@ -0,0 +1,108 @@
+use crate::bounds::{ Aabb, Containment, Frustum };
+use crate::objs::{ Matrix4x4, Mesh, Vec3d };
+use crate::picking::Ray;
+
+// Leaves stop splitting once they hold this many triangles
+const LEAF_SIZE: usize = 8;
+
+pub struct BvhNode {
+    pub bounds: Aabb,
+    // Range of Bvh::tris covered by this node; children split it between them
+    pub first: usize,
+    pub count: usize,
+    pub children: Option<(usize, usize)>,
+}
+
+// Bounding volume hierarchy over a mesh's triangles in the mesh's own space. It is built from the
+// positions at the time and has to be rebuilt if they change.
+pub struct Bvh {
+    pub nodes: Vec<BvhNode>,
+    // Triangle indices ordered so every node covers a contiguous run
+    pub tris: Vec<usize>,
+}
+
+impl Bvh {
+    // Top down build splitting at the median centroid along the widest axis
+    pub fn build(mesh: &Mesh) -> Self {
+        let centroids: Vec<Vec3d> = mesh.indices.iter()
+            .map(|[a, b, c]| (mesh.positions[*a] + mesh.positions[*b] + mesh.positions[*c])*(1.0/3.0))
+            .collect();
+        let mut bvh = Self { nodes: Vec::new(), tris: (0..mesh.indices.len()).collect() };
+        bvh.nodes.push(BvhNode { bounds: Aabb::empty(), first: 0, count: bvh.tris.len(), children: None });
+
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let (first, count) = (bvh.nodes[node_index].first, bvh.nodes[node_index].count);
+            let tris = &mut bvh.tris[first..first + count];
+
+            let mut bounds = Aabb::empty();
+            let mut centroid_bounds = Aabb::empty();
+            for t in tris.iter() {
+                mesh.indices[*t].iter().for_each(|v| bounds.include(&mesh.positions[*v]));
+                centroid_bounds.include(&centroids[*t]);
+            }
+            bvh.nodes[node_index].bounds = bounds;
+
+            let extent = centroid_bounds.size();
+            let axis_value = |p: &Vec3d, axis: usize| [p.x, p.y, p.z][axis];
+            let axis = (0..3).max_by(|a, b| axis_value(&extent, *a).total_cmp(&axis_value(&extent, *b))).unwrap_or(0);
+            if count <= LEAF_SIZE || axis_value(&extent, axis) <= 0.0 {
+                continue;
+            }
+
+            let mid = count/2;
+            tris.select_nth_unstable_by(mid, |a, b| axis_value(&centroids[*a], axis).total_cmp(&axis_value(&centroids[*b], axis)));
+            let left = bvh.nodes.len();
+            bvh.nodes.push(BvhNode { bounds: Aabb::empty(), first, count: mid, children: None });
+            bvh.nodes.push(BvhNode { bounds: Aabb::empty(), first: first + mid, count: count - mid, children: None });
+            bvh.nodes[node_index].children = Some((left, left + 1));
+            stack.extend([left, left + 1]);
+        }
+        bvh
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.nodes[0].bounds
+    }
+
+    // Appends the triangles whose node boxes are not entirely outside the frustum once mat
+    // takes them to view space. Subtrees found fully inside are taken without further tests.
+    pub fn visible_tris(&self, frustum: &Frustum, mat: &Matrix4x4, out: &mut Vec<usize>) {
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            match (frustum.classify_aabb(&node.bounds, mat), node.children) {
+                (Containment::Outside, _) => {}
+                (Containment::Intersecting, Some((left, right))) => stack.extend([left, right]),
+                _ => out.extend_from_slice(&self.tris[node.first..node.first + node.count]),
+            }
+        }
+    }
+
+    // Closest triangle along the ray, where test gives the hit parameter for a triangle or None
+    // to reject it. Nodes the ray misses, or only reaches past the best hit so far, are skipped.
+    pub fn raycast<F: FnMut(usize) -> Option<f64>>(&self, ray: &Ray, mut test: F) -> Option<(usize, f64)> {
+        let mut best: Option<(usize, f64)> = None;
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            match ray.intersect_aabb(&node.bounds) {
+                Some((t_enter, t_exit)) if t_exit >= 0.0 && best.is_none_or(|(_, t)| t_enter <= t) => {}
+                _ => continue,
+            }
+            match node.children {
+                Some((left, right)) => stack.extend([left, right]),
+                None => {
+                    for tri in self.tris[node.first..node.first + node.count].iter() {
+                        if let Some(t) = test(*tri) {
+                            if best.is_none_or(|(_, b)| t < b) {
+                                best = Some((*tri, t));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        best
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::{ HashMap, HashSet, VecDeque };
+
+use crate::objs::{ Mesh, Vec3d };
+use crate::scene::Scene;
+
+// Welding distance as a fraction of the mesh's bounding box diagonal, used by clean_up
+const WELD_TOLERANCE: f64 = 1e-6;
+// Edges sharper than this get split normals when clean_up generates them
+pub const DEFAULT_CREASE_ANGLE: f64 = 60.0;
+
+impl Mesh {
+    // The usual fixes for loaded meshes: welds duplicate vertices, drops degenerate and repeated
+    // faces, makes the winding consistent, and gives meshes without normals smooth ones that keep
+    // sharp edges sharp. Posed meshes are cleaned up in their rest pose.
+    pub fn clean_up(&mut self) {
+        if !self.rest_positions.is_empty() {
+            self.positions = self.rest_positions.clone();
+            self.normals = self.rest_normals.clone();
+        }
+        let diagonal = self.aabb().size();
+        self.weld(diagonal.dot(&diagonal).sqrt()*WELD_TOLERANCE);
+        self.remove_degenerate_faces();
+        self.remove_duplicate_faces();
+        self.orient_consistently();
+        if self.normals.is_empty() {
+            self.compute_smooth_normals(DEFAULT_CREASE_ANGLE);
+        }
+    }
+
+    // Merges vertices less than epsilon apart whose other attributes also match, so seams in
+    // texture coordinates or hard edges in the normals survive. Returns how many were removed.
+    pub fn weld(&mut self, epsilon: f64) -> usize {
+        let epsilon = epsilon.max(0.0);
+        let cell_size = if epsilon > 0.0 { epsilon } else { 1.0 };
+        let cell = |p: &Vec3d| ((p.x/cell_size).floor() as i64, (p.y/cell_size).floor() as i64, (p.z/cell_size).floor() as i64);
+        let close = |a: &Vec3d, b: &Vec3d| { let d = *a - *b; d.dot(&d) <= epsilon*epsilon };
+
+        let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+        let mut remap = vec![0; self.positions.len()];
+        let mut keep = Vec::new();
+        for (v, slot) in remap.iter_mut().enumerate() {
+            let (cx, cy, cz) = cell(&self.positions[v]);
+            // Anything within epsilon is in this cell or one next to it
+            let found = (-1..=1).flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (cx + dx, cy + dy, cz + dz))))
+                .filter_map(|c| grid.get(&c))
+                .flat_map(|kept| kept.iter())
+                .find(|k| close(&self.positions[keep[**k]], &self.positions[v]) && self.same_attributes(keep[**k], v, epsilon))
+                .cloned();
+            *slot = match found {
+                Some(k) => k,
+                None => {
+                    grid.entry((cx, cy, cz)).or_default().push(keep.len());
+                    keep.push(v);
+                    keep.len() - 1
+                }
+            };
+        }
+        let removed = self.positions.len() - keep.len();
+        if removed > 0 {
+            self.keep_vertices(&keep);
+            self.indices.iter_mut().for_each(|f| *f = f.map(|v| remap[v]));
+            self.polygons.iter_mut().flatten().for_each(|v| *v = remap[*v]);
+        }
+        removed
+    }
+
+    fn same_attributes(&self, a: usize, b: usize, epsilon: f64) -> bool {
+        let near = |p: &Vec3d, q: &Vec3d, e: f64| { let d = *p - *q; d.dot(&d) <= e*e };
+        // Normals are unit length, so they get a fixed tolerance rather than the distance one
+        (self.normals.is_empty() || near(&self.normals[a], &self.normals[b], 1e-6))
+            && (self.uvs.is_empty() || ((self.uvs[a].u - self.uvs[b].u).abs() <= 1e-9 && (self.uvs[a].v - self.uvs[b].v).abs() <= 1e-9))
+            && (self.colors.is_empty() || self.colors[a] == self.colors[b])
+            && (self.joints.is_empty() || (self.joints[a] == self.joints[b] && self.weights[a] == self.weights[b]))
+            && self.morph_targets.iter().all(|t| t.positions.len() != self.positions.len() || near(&t.positions[a], &t.positions[b], epsilon))
+    }
+
+    // Drops faces that use a vertex twice or have no area, which have no normal to light them by.
+    // Returns how many were removed.
+    pub fn remove_degenerate_faces(&mut self) -> usize {
+        let before = self.indices.len();
+        let positions = &self.positions;
+        self.indices.retain(|[a, b, c]| {
+            if a == b || b == c || a == c {
+                return false;
+            }
+            let (e1, e2) = (positions[*b] - positions[*a], positions[*c] - positions[*a]);
+            let n = e1.cross(&e2);
+            // Area small next to the longest edge squared means the corners are in a line
+            let longest = e1.dot(&e1).max(e2.dot(&e2)).max((positions[*c] - positions[*b]).dot(&(positions[*c] - positions[*b])));
+            n.dot(&n) > (1e-12*longest).powi(2) && n.dot(&n).is_finite()
+        });
+        let removed = before - self.indices.len();
+        if removed > 0 {
+            self.polygons.clear();
+            self.bvh = None;
+        }
+        removed
+    }
+
+    // Drops faces with the same corners in the same winding as an earlier one. Faces facing the
+    // other way are kept, since they can be the back of a two sided sheet. Returns how many were removed.
+    pub fn remove_duplicate_faces(&mut self) -> usize {
+        let before = self.indices.len();
+        let mut seen = HashSet::new();
+        self.indices.retain(|f| {
+            // Rotated so the smallest index is first, which is the same for every rotation of the face
+            let first = (0..3).min_by_key(|k| f[*k]).unwrap();
+            seen.insert([f[first], f[(first + 1) % 3], f[(first + 2) % 3]])
+        });
+        let removed = before - self.indices.len();
+        if removed > 0 {
+            self.polygons.clear();
+            self.bvh = None;
+        }
+        removed
+    }
+
+    // Flips faces so that neighbours across every shared edge agree on which side is the front,
+    // then turns each closed piece outwards. Edges shared by more than two faces are not crossed.
+    // Returns how many faces were flipped.
+    pub fn orient_consistently(&mut self) -> usize {
+        // Faces by undirected edge, which is what links neighbours whichever way they wind
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (f, face) in self.indices.iter().enumerate() {
+            for k in 0..3 {
+                let (a, b) = (face[k], face[(k + 1) % 3]);
+                edge_faces.entry((a.min(b), a.max(b))).or_default().push(f);
+            }
+        }
+        let runs_forward = |face: &[usize; 3], a: usize, b: usize| (0..3).any(|k| face[k] == a && face[(k + 1) % 3] == b);
+
+        let mut flipped = vec![false; self.indices.len()];
+        let mut visited = vec![false; self.indices.len()];
+        let mut flips = 0;
+        for start in 0..self.indices.len() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let mut piece = vec![start];
+            let mut closed = true;
+            let mut queue = VecDeque::from([start]);
+            while let Some(f) = queue.pop_front() {
+                let face = self.indices[f];
+                for k in 0..3 {
+                    let (a, b) = (face[k], face[(k + 1) % 3]);
+                    let neighbours = &edge_faces[&(a.min(b), a.max(b))];
+                    if neighbours.len() != 2 {
+                        closed = false;
+                        continue;
+                    }
+                    let g = if neighbours[0] == f { neighbours[1] } else { neighbours[0] };
+                    if visited[g] {
+                        continue;
+                    }
+                    visited[g] = true;
+                    // Consistent neighbours run the shared edge in opposite directions
+                    if runs_forward(&self.indices[g], a, b) {
+                        self.indices[g].swap(1, 2);
+                        flipped[g] = !flipped[g];
+                    }
+                    piece.push(g);
+                    queue.push_back(g);
+                }
+            }
+
+            // A closed piece whose signed volume is negative is inside out
+            if closed {
+                let volume: f64 = piece.iter().map(|f| {
+                    let [a, b, c] = self.indices[*f].map(|v| self.positions[v]);
+                    a.dot(&b.cross(&c))
+                }).sum();
+                if volume < 0.0 {
+                    for f in piece.iter() {
+                        self.indices[*f].swap(1, 2);
+                        flipped[*f] = !flipped[*f];
+                    }
+                }
+            }
+            flips += piece.iter().filter(|f| flipped[**f]).count();
+        }
+        if flips > 0 {
+            self.polygons.clear();
+            self.bvh = None;
+        }
+        flips
+    }
+
+    // Replaces the normals with ones averaged from the faces around each vertex, weighted by the
+    // angle each face has there. Faces meeting at more than crease_angle degrees don't smooth
+    // into each other, and vertices on such creases are split so each side keeps its own normal.
+    pub fn compute_smooth_normals(&mut self, crease_angle: f64) {
+        let cos_crease = crease_angle.to_radians().cos();
+        let face_normals: Vec<Vec3d> = self.indices.iter().map(|f| {
+            let [a, b, c] = f.map(|v| self.positions[v]);
+            let mut n = (b - a).cross(&(c - a));
+            n.normalize();
+            n
+        }).collect();
+        let corner_angle = |f: usize, k: usize| {
+            let face = self.indices[f];
+            let p = self.positions[face[k]];
+            let (mut e1, mut e2) = (self.positions[face[(k + 1) % 3]] - p, self.positions[face[(k + 2) % 3]] - p);
+            e1.normalize();
+            e2.normalize();
+            e1.dot(&e2).clamp(-1.0, 1.0).acos()
+        };
+
+        let mut vertex_faces: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.positions.len()];
+        for (f, face) in self.indices.iter().enumerate() {
+            for (k, v) in face.iter().enumerate() {
+                vertex_faces[*v].push((f, k));
+            }
+        }
+
+        // Each corner's normal from the faces around its vertex on the same side of any crease
+        let mut corner_normals = vec![[Vec3d::new(0.0, 0.0, 0.0); 3]; self.indices.len()];
+        for around in vertex_faces.iter() {
+            for (f, k) in around.iter() {
+                let mut n = Vec3d::new(0.0, 0.0, 0.0);
+                for (g, j) in around.iter().filter(|(g, _)| face_normals[*f].dot(&face_normals[*g]) >= cos_crease) {
+                    let weight = corner_angle(*g, *j);
+                    if weight.is_finite() {
+                        n = n + face_normals[*g]*weight;
+                    }
+                }
+                n.normalize();
+                corner_normals[*f][*k] = n;
+            }
+        }
+
+        // Corners of a vertex that ended up with different normals get a vertex each. Faces with
+        // no area have no normal of their own and just use whatever their vertices get.
+        let mut normals = vec![Vec3d::new(0.0, 0.0, 0.0); self.positions.len()];
+        let mut assigned = vec![false; self.positions.len()];
+        let mut keep: Vec<usize> = (0..self.positions.len()).collect();
+        let mut split: HashMap<(usize, [u64; 3]), usize> = HashMap::new();
+        for f in (0..self.indices.len()).filter(|f| face_normals[*f].dot(&face_normals[*f]) > 0.0) {
+            for (k, n) in corner_normals[f].iter().enumerate() {
+                let (v, n) = (self.indices[f][k], *n);
+                let key = (v, [n.x.to_bits(), n.y.to_bits(), n.z.to_bits()]);
+                if !assigned[v] {
+                    assigned[v] = true;
+                    normals[v] = n;
+                    split.insert(key, v);
+                    continue;
+                }
+                self.indices[f][k] = *split.entry(key).or_insert_with(|| {
+                    keep.push(v);
+                    normals.push(n);
+                    keep.len() - 1
+                });
+            }
+        }
+        self.keep_vertices(&keep);
+        self.normals = normals;
+    }
+
+    // Rebuilds every per-vertex list from the vertices listed in keep, in that order. Indices are
+    // left for the caller to remap.
+    pub(crate) fn keep_vertices(&mut self, keep: &[usize]) {
+        fn pick<T: Copy>(list: &mut Vec<T>, keep: &[usize]) {
+            if !list.is_empty() {
+                *list = keep.iter().map(|v| list[*v]).collect();
+            }
+        }
+        pick(&mut self.positions, keep);
+        pick(&mut self.normals, keep);
+        pick(&mut self.colors, keep);
+        pick(&mut self.uvs, keep);
+        pick(&mut self.joints, keep);
+        pick(&mut self.weights, keep);
+        for target in self.morph_targets.iter_mut() {
+            pick(&mut target.positions, keep);
+            pick(&mut target.normals, keep);
+        }
+        // Any saved rest pose is for the old vertices; the current shape becomes the new one
+        self.rest_positions.clear();
+        self.rest_normals.clear();
+        self.bvh = None;
+    }
+}
+
+impl Scene {
+    // Cleans up every mesh a node draws. Their simplified levels of detail no longer match, so
+    // they are dropped.
+    pub fn clean_up(&mut self) {
+        let drawn = self.drawn_meshes();
+        for m in drawn.iter() {
+            self.meshes[*m].clean_up();
+        }
+        self.lods.retain(|l| !drawn.contains(&l.mesh));
+    }
+}
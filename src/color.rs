@@ -0,0 +1,82 @@
+use std::sync::OnceLock;
+
+use eframe::egui::Color32;
+
+// Red, green and blue, 1 being full intensity. Lighting is added up in linear light, where sums
+// can go past 1 until tone mapping brings them back into range.
+pub type Rgb = [f64; 3];
+
+// How an HDR color is brought into the 0 to 1 range a display can show
+#[derive(Copy, Clone, PartialEq, Default)]
+pub enum ToneMapping {
+    // Anything brighter than 1 is cut off
+    Clamp,
+    // c/(1 + c) on each channel, which never quite reaches white
+    Reinhard,
+    // Narkowicz's fit of the ACES filmic curve, with a toe in the shadows and a soft shoulder
+    #[default]
+    Aces,
+}
+
+impl ToneMapping {
+    pub const ALL: [ToneMapping; 3] = [ToneMapping::Clamp, ToneMapping::Reinhard, ToneMapping::Aces];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ToneMapping::Clamp => "None (clamp)",
+            ToneMapping::Reinhard => "Reinhard",
+            ToneMapping::Aces => "ACES filmic",
+        }
+    }
+
+    pub fn apply(&self, c: Rgb) -> Rgb {
+        c.map(|v| {
+            let v = v.max(0.0);
+            match self {
+                ToneMapping::Clamp => v.min(1.0),
+                ToneMapping::Reinhard => v/(1.0 + v),
+                ToneMapping::Aces => (v*(2.51*v + 0.03)/(v*(2.43*v + 0.59) + 0.14)).min(1.0),
+            }
+        })
+    }
+
+    // Tone maps an HDR color and sRGB encodes it, giving the 0 to 1 value a display shows
+    pub fn display(&self, c: Rgb) -> Rgb {
+        self.apply(c).map(|v| linear_to_srgb(v.clamp(0.0, 1.0)))
+    }
+
+    // Tone maps an HDR image and encodes it as sRGB into out
+    pub fn encode(&self, image: &[Rgb], out: &mut [Color32]) {
+        for (pixel, c) in out.iter_mut().zip(image.iter()) {
+            *pixel = to_srgb(self.apply(*c));
+        }
+    }
+}
+
+// The sRGB transfer function, for 0 to 1 values
+pub fn srgb_to_linear(v: f64) -> f64 {
+    if v <= 0.04045 { v/12.92 } else { ((v + 0.055)/1.055).powf(2.4) }
+}
+
+pub fn linear_to_srgb(v: f64) -> f64 {
+    if v <= 0.0031308 { v*12.92 } else { 1.055*v.powf(1.0/2.4) - 0.055 }
+}
+
+// Color32 holds sRGB encoded bytes, as egui and image files do. Decoding is looked up since
+// textures are sampled for every pixel.
+pub fn to_linear(c: Color32) -> Rgb {
+    static TABLE: OnceLock<[f64; 256]> = OnceLock::new();
+    let table = TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(i as f64/255.0)));
+    [table[c.r() as usize], table[c.g() as usize], table[c.b() as usize]]
+}
+
+// Encodes a linear color, clamped to 0 to 1, as opaque sRGB bytes
+pub fn to_srgb(c: Rgb) -> Color32 {
+    to_bytes(c.map(|v| linear_to_srgb(v.clamp(0.0, 1.0))))
+}
+
+// Rounds an already encoded color to opaque bytes
+pub fn to_bytes(c: Rgb) -> Color32 {
+    let [r, g, b] = c.map(|v| (v.clamp(0.0, 1.0)*255.0).round() as u8);
+    Color32::from_rgb(r, g, b)
+}
@@ -2,19 +2,96 @@ use eframe::egui;
 use std::sync::{ Arc, Mutex };
 use std::sync::atomic::Ordering;
 use std::time::Instant;
-use std::cmp::Ordering as cmpOrdering;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::path::Path;
 
-use crate::objs::{ GUIState, DisplayBuffers, Matrix4x4, Mesh, Tri, Vec3d };
+use crate::animation::{ Animation, Clock, Playback, Property, Target, Trs };
+use crate::bounds::{ Aabb, BoundingSphere, Containment, Frustum };
+use crate::color::{ self, Rgb };
+use crate::framebuffer::{ AaMode, Framebuffer, TransparencyMode };
+use crate::loaders::{ self, LoadError };
+use crate::lod;
+use crate::objs::{ GUIState, DisplayBuffers, Matrix4x4, Mesh, RenderMode, RenderSettings, Tri, Vec3d };
+use crate::picking::{ PickHit, Ray };
+use crate::post::PostChain;
+use crate::raster;
+use crate::scene::{ Light, LightKind, Material, Scene, Texture };
+use crate::shadow::ShadowMap;
+use crate::stats::RenderStats;
+
+// View space depth of the plane triangles are clipped against
+const NEAR_Z: f64 = 1.0;
+// Radius framed models are scaled to, large enough that they stay clear of the near clip plane
+const FRAME_RADIUS: f64 = 2.0;
+// Extra distance so framed models don't touch the edge of the view
+const FRAME_MARGIN: f64 = 1.1;
+// Light every surface gets, so faces turned away from the lights or in shadow aren't black
+const AMBIENT: f64 = 0.2;
+// Side of the ground plane, in framed units
+const GROUND_SIZE: f64 = 5.0*FRAME_RADIUS;
+// Relative depth slack for lines and points tested against the surfaces they lie on
+const DEPTH_TEST_BIAS: f64 = 0.01;
+// View space length of normal overlay lines
+const NORMAL_LENGTH: f64 = 0.08*FRAME_RADIUS;
+// Axes gizmo arm length and distance of its centre from the bottom left corner, in output pixels
+const AXES_SIZE: f64 = 30.0;
+// Seconds per turn of the turntable animation scenes without their own animations get
+const TURNTABLE_PERIOD: f64 = 12.0;
+// Meshes with at least this many triangles get simplified levels of detail when asked, this many of them
+const LOD_MIN_TRIANGLES: usize = 20000;
+const LOD_LEVELS: usize = 3;
+
+// A screen space triangle ready to fill, with what per-pixel lighting needs. tri carries the
+// unlit vertex colors; view and normals are its corners in view space.
+struct RasterTri {
+    tri: Tri,
+    view: Vec<Vec3d>,
+    normals: Vec<Vec3d>,
+    material: Option<usize>,
+    // Opacity of see-through triangles, None for opaque ones
+    opacity: Option<f64>,
+    picked: bool,
+    // Its mesh's render mode, and the color its edges are drawn in when the mode has any
+    mode: RenderMode,
+    edge_color: egui::Color32,
+}
 
 pub struct Engine {
     state: Arc<Mutex<GUIState>>,
     buffers: DisplayBuffers,
-    meshes: Vec<Mesh>,
+    // Drawn into at the anti-aliasing mode's resolution, then resolved into the display buffer
+    target: Framebuffer,
+    pub scene: Scene,
     mat_proj: Matrix4x4,
-    begin_time: Instant,
+    // Time of the last clock update, so the clock moves on by wall clock time between frames
+    last_tick: Instant,
+    clock: Clock,
+    // Index into the scene's animations of the one playing
+    active_animation: usize,
+    // Transform and its parts each node had before any animation moved it
+    rest_transforms: Vec<Matrix4x4>,
+    rest_poses: Vec<Trs>,
+    // (mesh index, morph target index) of the weights the active animation set this frame
+    animated_weights: Vec<(usize, usize)>,
     v_camera: Vec3d,
+    // Moves the framed model to the origin at a standard size, see frame_sphere
+    mat_frame: Matrix4x4,
+    view_distance: f64,
+    // Local space bounds of each scene mesh, rebuilt when meshes are added
+    mesh_bounds: Vec<Aabb>,
+    // Copied from the UI at the start of each frame by lo, and set directly when driving render by hand
+    pub settings: RenderSettings,
+    pub post: PostChain,
+    // (mesh index, node transform, model to view transform) of everything drawn in the last frame
+    instances: Vec<(usize, Matrix4x4, Matrix4x4)>,
+    picked: Option<PickHit>,
+    // Unit floor, placed below the framed model in view space when the ground plane is on
+    ground: Mesh,
+    // Screen space edge being drawn, so line pixels can find their depth along it, and whether they
+    // are only drawn where it is not behind the depth buffer and whether they fade into the fog
+    drawn_edge: Option<(Vec3d, Vec3d)>,
+    edge_depth_tested: bool,
+    edge_fogged: bool,
+    stats: RenderStats,
 }
 
 impl Engine{
@@ -22,8 +99,6 @@ impl Engine{
     pub fn new(state: Arc<Mutex<GUIState>>, buffers_copy: &DisplayBuffers) -> Self{
         let buffers = DisplayBuffers::from(buffers_copy);
 
-        let meshes = Vec::with_capacity(1);
-
         // Projection Matrix
 		let f_near = 0.1;
 		let f_far = 1000.0;
@@ -33,52 +108,174 @@ impl Engine{
         let mut mat_proj = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
         mat_proj.make_projection(f_fov, f_aspect_ratio, f_near, f_far);
 
+        let target = Framebuffer::new(buffers.buf_size, AaMode::Off);
+        let mut ground = Mesh::plane(1.0, 1.0, 1, 1);
+        ground.render_mode = RenderMode::Shaded;
         Self { 
             state,
             buffers,
-            meshes,
+            target,
+            scene: Scene::new(),
             mat_proj,
-            begin_time: Instant::now(),
+            last_tick: Instant::now(),
+            clock: Clock::new(),
+            active_animation: 0,
+            rest_transforms: Vec::new(),
+            rest_poses: Vec::new(),
+            animated_weights: Vec::new(),
             v_camera: Vec3d::new(0.0, 0.0, 0.0),
+            mat_frame: Matrix4x4::identity(),
+            view_distance: 8.0,
+            mesh_bounds: Vec::new(),
+            settings: RenderSettings::default(),
+            post: PostChain::new(),
+            instances: Vec::new(),
+            picked: None,
+            ground,
+            drawn_edge: None,
+            edge_depth_tested: false,
+            edge_fogged: false,
+            stats: RenderStats::new(),
         }
     }
 
-    fn put_pixel(&self, x: i64, y: i64, color: &egui::Color32, pixels: &mut Vec<egui::Color32>){
-        let hlen = self.buffers.buf_size[0];
-        let vlen = self.buffers.buf_size[1];
-        if x<hlen as i64 && y<vlen as i64 {
-            pixels[(y as usize)*hlen+(x as usize)] = *color;
+    fn put_pixel(&mut self, x: i64, y: i64, color: &Rgb){
+        let mask = self.target.full_mask();
+        if self.edge_visible(x, y) && self.target.put(x, y, mask, self.edge_fog(x, y, color)) {
+            self.stats.pixels_written += 1;
         }
     }
 
-    fn load_from_object_file(&mut self, fpath: String){
-        let file = File::open(fpath).expect("fopen error");
-        let mut reader = BufReader::new(file);
+    fn blend_pixel(&mut self, x: i64, y: i64, color: &Rgb, alpha: f64){
+        let mask = self.target.full_mask();
+        if self.edge_visible(x, y) && self.target.blend(x, y, mask, self.edge_fog(x, y, color), alpha) {
+            self.stats.pixels_written += 1;
+        }
+    }
 
-        let mut vec_cache = Vec::with_capacity(0);
-        let mut sample = Vec::with_capacity(0);
-        
-        let mut line = String::new();
-        loop {
-            let bytes_read = reader.read_line(&mut line).expect("read error");
-            if bytes_read == 0 {
-                break;
+    // View depth of the edge being drawn, if any, where it passes pixel (x, y)
+    fn edge_depth(&self, x: i64, y: i64) -> Option<f64> {
+        let (a, b) = self.drawn_edge?;
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let length2 = dx*dx + dy*dy;
+        let t = if length2 > 0.0 { (((x as f64 + 0.5 - a.x)*dx + (y as f64 + 0.5 - a.y)*dy)/length2).clamp(0.0, 1.0) } else { 0.0 };
+        // 1/w is linear in screen space, so view depth along the edge is the reciprocal of its lerp
+        Some(1.0/((1.0 - t)/a.w + t/b.w))
+    }
+
+    // Whether the edge being drawn, if depth tested, is in front of what has been filled at pixel (x, y)
+    fn edge_visible(&self, x: i64, y: i64) -> bool {
+        match self.edge_depth(x, y).filter(|_| self.edge_depth_tested) {
+            Some(z) => z <= self.target.depth_at(x, y)*(1.0 + DEPTH_TEST_BIAS),
+            None => true,
+        }
+    }
+
+    // color as the edge being drawn shows it at pixel (x, y), faded into the fog if it is fogged
+    fn edge_fog(&self, x: i64, y: i64, color: &Rgb) -> Rgb {
+        let fog = self.settings.fog;
+        match self.edge_depth(x, y).filter(|_| self.edge_fogged) {
+            Some(z) => fog.apply(*color, fog.visibility(z)),
+            None => *color,
+        }
+    }
+
+    // Loads a model and adds it to the scene, picking the loader from the file extension
+    pub fn load_model(&mut self, fpath: &str) -> Result<(), LoadError> {
+        let path = Path::new(fpath);
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+        let name = path.file_stem().and_then(|n| n.to_str()).unwrap_or("").to_string();
+        let mut mesh = match ext.as_str() {
+            "obj" => loaders::obj::load_obj(fpath)?,
+            "stl" => loaders::stl::load_stl(fpath)?,
+            "ply" => loaders::ply::load_ply(fpath)?,
+            "gltf" | "glb" => {
+                self.scene.append(loaders::gltf::load_gltf(fpath)?);
+                return Ok(());
             }
-            let chars_as_vec = line.chars().collect::<Vec<char>>();
-            if chars_as_vec[0]=='v' {
-                let trimmed: Vec<&str> = line.trim().split_whitespace().collect();
-                vec_cache.push(Vec3d::new(trimmed[1].parse::<f64>().unwrap(), trimmed[2].parse::<f64>().unwrap(), trimmed[3].parse::<f64>().unwrap()));    
+            other => return Err(LoadError::Unsupported(format!("model extension '{}'", other))),
+        };
+        // These formats carry no materials, so each model gets a plain one to set its opacity with
+        mesh.material = Some(self.scene.materials.len());
+        self.scene.materials.push(Material::new(name.clone()));
+        self.scene.add_mesh(name, mesh);
+        Ok(())
+    }
+
+    // Gives every drawn mesh big enough to gain from it simplified levels of detail, returning how
+    // many meshes got them
+    pub fn generate_lods(&mut self) -> usize {
+        let mut count = 0;
+        for m in self.scene.drawn_meshes() {
+            if self.scene.meshes[m].indices.len() >= LOD_MIN_TRIANGLES && self.scene.add_lods(m, LOD_LEVELS) > 0 {
+                count += 1;
             }
-            else if chars_as_vec[0]=='f'{
-                let trimmed: Vec<&str> = line.trim().split_whitespace().collect();
-                sample.push(Tri::new(vec![vec_cache[trimmed[1].parse::<usize>().unwrap()-1], vec_cache[trimmed[2].parse::<usize>().unwrap()-1], vec_cache[trimmed[3].parse::<usize>().unwrap()-1]]));
+        }
+        count
+    }
+
+    // Finds what is under the buffer pixel (x, y) in the last rendered frame. The view ray is
+    // taken into each instance's own space so meshes with a BVH only test the triangles near it.
+    pub fn pick(&self, x: f64, y: f64) -> Option<PickHit> {
+        let ndc_x = x/(0.5*self.buffers.buf_size[0] as f64) - 1.0;
+        let ndc_y = y/(0.5*self.buffers.buf_size[1] as f64) - 1.0;
+        // With the camera at the origin and dir.z = 1, the hit parameter is the view depth
+        let view_ray = Ray::new(Vec3d::new(0.0, 0.0, 0.0), Vec3d::new(ndc_x/self.mat_proj.m[0][0], ndc_y/self.mat_proj.m[1][1], 1.0));
+
+        let mut best: Option<PickHit> = None;
+        for (instance, (mesh_index, mat_node, mat_world)) in self.instances.iter().enumerate() {
+            let m = &self.scene.meshes[*mesh_index];
+            let inverse = match mat_world.inverse() {
+                Some(inverse) => inverse,
+                None => continue,
+            };
+            let ray = view_ray.transformed(&inverse);
+            // Only faces the renderer draws can be hit, and mirroring transforms flip which those are
+            let front = if mat_world.determinant3() < 0.0 { -1.0 } else { 1.0 };
+            let test = |tri: usize| {
+                let [a, b, c] = m.indices[tri].map(|v| m.positions[v]);
+                let (t, _, _) = ray.intersect_triangle(&a, &b, &c)?;
+                let facing = (b - a).cross(&(c - a)).dot(&ray.dir)*front < 0.0;
+                (facing && t >= NEAR_Z).then_some(t)
+            };
+            let hit = match &m.bvh {
+                Some(bvh) => bvh.raycast(&ray, test),
+                None => (0..m.indices.len()).filter_map(|tri| test(tri).map(|t| (tri, t))).min_by(|a, b| a.1.total_cmp(&b.1)),
+            };
+            if let Some((triangle, depth)) = hit {
+                if best.is_none_or(|b| depth < b.depth) {
+                    let point = mat_node.mul_mat_vec(&ray.at(depth));
+                    best = Some(PickHit { instance, mesh: *mesh_index, triangle, point, depth });
+                }
             }
-            line.clear();
         }
-        self.meshes.push(Mesh::new(sample));
+        best
+    }
+
+    // Centres the sphere on the origin and scales it to FRAME_RADIUS, then backs the model off far
+    // enough that it fits the narrower of the two fields of view whichever way it spins
+    pub fn frame_sphere(&mut self, sphere: &BoundingSphere) {
+        if sphere.radius <= 0.0 || !sphere.radius.is_finite() {
+            return;
+        }
+        let mut mat_center = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        mat_center.make_translation(-sphere.center.x, -sphere.center.y, -sphere.center.z);
+        let scale = FRAME_RADIUS/sphere.radius;
+        let mut mat_scale = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        mat_scale.make_scale(scale, scale, scale);
+        self.mat_frame = mat_center.mul_mat_mat(&mat_scale);
+
+        let framed = BoundingSphere { center: Vec3d::new(0.0, 0.0, 0.0), radius: FRAME_RADIUS };
+        self.view_distance = framed.fit_distance(&self.mat_proj)*FRAME_MARGIN;
+    }
+
+    pub fn frame_scene(&mut self) {
+        let sphere = self.scene.bounding_sphere();
+        self.frame_sphere(&sphere);
     }
 
-    fn draw_line(&self, x1: i64, y1: i64, x2: i64, y2: i64, color: &egui::Color32, pixels: &mut Vec<egui::Color32>){
+    #[allow(clippy::too_many_arguments)]
+    fn draw_line(&mut self, x1: i64, y1: i64, x2: i64, y2: i64, color: &Rgb){
         let (mut x, mut y, dx, dy, dx1, dy1, mut px, mut py, xe, ye);
         dx = x2 - x1; dy = y2 - y1;
         dx1 = dx.abs(); dy1 = dy.abs();
@@ -91,28 +288,28 @@ impl Engine{
                 { x = x2; y = y2; xe = x1;}
     
             // Draw(x, y, c, col);
-            self.put_pixel(x, y, color, pixels);
+            self.put_pixel(x, y, color);
             loop
             {
                 if x>=xe {
                     break;
                 }
-                x = x + 1;
+                x += 1;
                 if px<0 {
-                    px = px + 2 * dy1;
+                    px += 2 * dy1;
                 }
                 else
                 {
                     if (dx<0 && dy<0) || (dx>0 && dy>0){
-                        y = y + 1;
+                        y += 1;
                     }
                     else {
-                        y = y - 1;
+                        y -= 1;
                     }
-                    px = px + 2 * (dy1 - dx1);
+                    px += 2 * (dy1 - dx1);
                 }
                 // Draw(x, y, c, col);
-                self.put_pixel(x, y, color, pixels);
+                self.put_pixel(x, y, color);
             }
         }
         else
@@ -123,193 +320,734 @@ impl Engine{
                 { x = x2; y = y2; ye = y1; }
     
             // Draw(x, y, c, col);
-            self.put_pixel(x, y, color, pixels);
+            self.put_pixel(x, y, color);
             loop
             {
                 if y>=ye {
                     break;
                 }
-                y = y + 1;
+                y += 1;
                 if py <= 0 {
-                    py = py + 2 * dx1;
+                    py += 2 * dx1;
                 }
                 else
                 {
                     if (dx<0 && dy<0) || (dx>0 && dy>0) {
-                        x = x + 1;
+                        x += 1;
                     }
                     else {
-                        x = x - 1;
+                        x -= 1;
                     }
-                    py = py + 2 * (dx1 - dy1);
+                    py += 2 * (dx1 - dy1);
                 }
                 // Draw(x, y, c, col);
-                self.put_pixel(x, y, color, pixels);
+                self.put_pixel(x, y, color);
             }
         }
     }
 
-    fn draw_hline(&self, mut sx: i64, mut ex: i64, ny: i64, color: &egui::Color32, pixels: &mut Vec<egui::Color32>){
-        if sx > ex {
-            let t = sx;
-            sx = ex;
-            ex = t;
-        }
-        for i in sx..=ex {
-            self.put_pixel(i, ny, color, pixels);
+    // Outline of a screen space triangle in the line style the settings ask for, optionally only
+    // where it isn't hidden by what has been filled, and optionally fogged like the scene's surfaces
+    fn draw_outline(&mut self, p: &[Vec3d], color: &egui::Color32, depth_test: bool, fog: bool){
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            self.draw_edge(&p[a], &p[b], color, depth_test, fog);
         }
     }
 
-    fn draw_triangle(&self, x1: i64, y1: i64, x2: i64, y2: i64, x3: i64, y3: i64, color: &egui::Color32, pixels: &mut Vec<egui::Color32>){
-        self.draw_line(x1, y1, x2, y2, color, pixels);
-        self.draw_line(x2, y2, x3, y3, color, pixels);
-        self.draw_line(x1, y1, x3, y3, color, pixels);
+    // Line between screen space points in the line style the settings ask for. Fogged lines fade
+    // pixel by pixel by their depth along the line.
+    fn draw_edge(&mut self, a: &Vec3d, b: &Vec3d, color: &egui::Color32, depth_test: bool, fog: bool){
+        let color = color::to_linear(*color);
+        (self.drawn_edge, self.edge_depth_tested, self.edge_fogged) = (Some((*a, *b)), depth_test, fog);
+        if self.settings.smooth_lines {
+            // Widths are in output pixels, so they stay put when supersampling enlarges the target
+            let width = self.settings.line_width*self.target.mode.scale() as f64;
+            raster::smooth_line((a.x, a.y), (b.x, b.y), width, |x, y, alpha| self.blend_pixel(x, y, &color, alpha));
+        }
+        else {
+            self.draw_line(a.x as i64, a.y as i64, b.x as i64, b.y as i64, &color);
+        }
+        self.drawn_edge = None;
     }
 
-    fn fill_bottom_flat_triangle(&self, x1: i64, y1: i64, x2: i64, y2: i64, x3: i64, y3: i64, color: &egui::Color32, pixels: &mut Vec<egui::Color32>) {
-        let invslope1 = ((x2 - x1) as f64) / ((y2 - y1) as f64);
-        let invslope2 = ((x3 - x1) as f64) / ((y3 - y1) as f64);
-        
-        let mut curx1 = x1 as f64;
-        let mut curx2 = x1 as f64;
+    // Line between view space points, cut off where it passes behind the near plane
+    fn draw_view_line(&mut self, a: &Vec3d, b: &Vec3d, color: &egui::Color32, depth_test: bool){
+        let (near_p, near_n) = (Vec3d::new(0.0, 0.0, NEAR_Z), Vec3d::new(0.0, 0.0, 1.0));
+        let (mut a, mut b) = (*a, *b);
+        match (a.z >= NEAR_Z, b.z >= NEAR_Z) {
+            (false, false) => return,
+            (false, true) => a = Vec3d::vector_intersect_plane(&near_p, &near_n, &a, &b),
+            (true, false) => b = Vec3d::vector_intersect_plane(&near_p, &near_n, &a, &b),
+            (true, true) => {}
+        }
+        let (mut a, mut b) = (self.mat_proj.mul_mat_vec(&a), self.mat_proj.mul_mat_vec(&b));
+        self.to_screen_point(&mut a);
+        self.to_screen_point(&mut b);
+        self.draw_edge(&a, &b, color, depth_test, false);
+    }
 
-        let mut scanline_y = y1;
-        loop {
-            if scanline_y > y2 {
-                break;
+    // Round dot of point_size output pixels centred on screen space point p, hidden where filled
+    // surfaces are in front of it and fogged by its depth
+    fn draw_point(&mut self, p: &Vec3d, color: &egui::Color32){
+        let fog = self.settings.fog;
+        let color = fog.apply(color::to_linear(*color), fog.visibility(p.w));
+        let radius = 0.5*self.settings.point_size*self.target.mode.scale() as f64;
+        for y in (p.y - radius).floor() as i64..=(p.y + radius).ceil() as i64 {
+            for x in (p.x - radius).floor() as i64..=(p.x + radius).ceil() as i64 {
+                if p.w > self.target.depth_at(x, y)*(1.0 + DEPTH_TEST_BIAS) {
+                    continue;
+                }
+                let distance = ((x as f64 + 0.5 - p.x).powi(2) + (y as f64 + 0.5 - p.y).powi(2)).sqrt();
+                let coverage = (radius + 0.5 - distance).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend_pixel(x, y, &color, coverage);
+                }
             }
-            self.draw_hline(curx1 as i64, curx2 as i64, scanline_y, color, pixels);
-            curx1 += invslope1;
-            curx2 += invslope2;
-            scanline_y += 1;
         }
     }
 
-    fn fill_top_flat_triangle(&self, x1: i64, y1: i64, x2: i64, y2: i64, x3: i64, y3: i64, color: &egui::Color32, pixels: &mut Vec<egui::Color32>){
-        let invslope1 = ((x3 - x1) as f64) / ((y3 - y1) as f64);
-        let invslope2 = ((x3 - x2) as f64) / ((y3 - y2) as f64);
-        
-        let mut curx1 = x3 as f64;
-        let mut curx2 = x3 as f64;
+    // Fills a screen space triangle, blending its per-vertex colors across the interior, modulating
+    // them by the texture when the triangle has texture coordinates, and lighting and fogging every pixel.
+    // Colors are decoded to linear light first, so the lit result is HDR until the target is resolved.
+    // Hidden line triangles only go into the depth buffer. See-through triangles are depth tested
+    // against it without writing to it, and blended or accumulated as the transparency mode says.
+    fn fill_triangle(&mut self, rt: &RasterTri, texture: Option<&Texture>, lights: &[(Light, Option<ShadowMap>)]) {
+        let tri = &rt.tri;
+        let c: Vec<Rgb> = tri.col.iter().map(|c| color::to_linear(*c)).collect();
+        let texture = texture.filter(|_| tri.uv.len() == 3);
+        let pcf_radius = self.settings.pcf_radius;
+        let (width, height) = (self.target.width, self.target.height);
+        let offsets = self.target.mode.sample_offsets();
 
-        let mut scanline_y = y3;
-        loop {
-            if scanline_y <= y1 {
-                break;
+        // Shaded once per pixel however many of its samples the triangle covers
+        let depth_only = rt.mode == RenderMode::HiddenLine;
+        let transparency = self.settings.transparency;
+        let fog = self.settings.fog;
+        let vertex_visibility = [0, 1, 2].map(|k| fog.visibility(rt.view[k].z));
+        raster::rasterize_samples(&tri.p, width, height, offsets, |x, y, mask, [w0, w1, w2]| {
+            let z = rt.view[0].z*w0 + rt.view[1].z*w1 + rt.view[2].z*w2;
+            let mask = match rt.opacity {
+                Some(_) => self.target.depth_test(x, y, mask, z),
+                None => {
+                    self.target.put_depth(x, y, mask, z);
+                    mask
+                }
+            };
+            if depth_only || mask == 0 {
+                return;
+            }
+            let texel = match texture {
+                Some(t) => t.sample(tri.uv[0].u*w0 + tri.uv[1].u*w1 + tri.uv[2].u*w2, tri.uv[0].v*w0 + tri.uv[1].v*w1 + tri.uv[2].v*w2),
+                None => egui::Color32::WHITE,
+            };
+            let p = rt.view[0]*w0 + rt.view[1]*w1 + rt.view[2]*w2;
+            let mut n = rt.normals[0]*w0 + rt.normals[1]*w1 + rt.normals[2]*w2;
+            n.normalize();
+            let intensity = Self::illuminate(&p, &n, lights, pcf_radius);
+
+            let [r, g, b, a] = texel.to_srgba_unmultiplied();
+            let tex = color::to_linear(egui::Color32::from_rgb(r, g, b));
+            let lit = [0, 1, 2].map(|k| (c[0][k]*w0 + c[1][k]*w1 + c[2][k]*w2)*tex[k]*intensity);
+            let visibility = if fog.per_vertex { vertex_visibility[0]*w0 + vertex_visibility[1]*w1 + vertex_visibility[2]*w2 } else { fog.visibility(z) };
+            let lit = fog.apply(lit, visibility);
+            let written = match rt.opacity.map(|o| o*a as f64/255.0) {
+                None => self.target.put(x, y, mask, lit),
+                Some(alpha) if transparency == TransparencyMode::WeightedBlended => self.target.accumulate(x, y, mask, lit, alpha, z),
+                Some(alpha) => self.target.blend(x, y, mask, lit, alpha),
+            };
+            if written {
+                self.stats.pixels_written += 1;
             }
-            self.draw_hline(curx1 as i64, curx2 as i64, scanline_y, color, pixels);
-            curx1 -= invslope1;
-            curx2 -= invslope2;
-            scanline_y -= 1;
+        });
+    }
+
+    // Light reaching view space point p with unit normal n: ambient plus each light's N.L term,
+    // faded towards the edge of spot cones and scaled by how much of it the shadow map lets through
+    fn illuminate(p: &Vec3d, n: &Vec3d, lights: &[(Light, Option<ShadowMap>)], pcf_radius: usize) -> f64 {
+        let mut intensity = AMBIENT;
+        for (light, shadow_map) in lights.iter() {
+            let (to_light, spot) = match light.kind {
+                LightKind::Directional => (light.direction*-1.0, 1.0),
+                LightKind::Spot { position, cone_angle } => {
+                    let mut to_light = position - *p;
+                    to_light.normalize();
+                    let (outer, inner) = (cone_angle.cos(), (cone_angle*0.8).cos());
+                    let t = ((-to_light.dot(&light.direction) - outer)/(inner - outer)).clamp(0.0, 1.0);
+                    (to_light, t*t*(3.0 - 2.0*t))
+                }
+            };
+            let n_dot_l = n.dot(&to_light);
+            if n_dot_l <= 0.0 || spot <= 0.0 {
+                continue;
+            }
+            let lit = shadow_map.as_ref().map_or(1.0, |m| m.lit_fraction(p, n_dot_l, pcf_radius));
+            intensity += light.intensity*n_dot_l*spot*lit;
         }
+        intensity
     }
 
-    fn fill_triangle(&self, mut x1: i64, mut y1: i64, mut x2: i64, mut y2: i64, mut x3: i64, mut y3: i64, color: &egui::Color32, pixels: &mut Vec<egui::Color32>) {
-        let mut temp_arr = [[y1, x1], [y2, x2], [y3, x3]];
-        temp_arr.sort_by(|a, b| {
-            if a[0] >= b[0] {
-                cmpOrdering::Greater
+    fn to_screen_point(&self, p: &mut Vec3d){
+        p.x += 1.0;
+        p.y += 1.0;
+        // if p.x<0.0 {p.x = 0.0}
+        // if p.y<0.0 {p.y = 0.0}
+        // if p.x>2.0 {p.x = 2.0}
+        // if p.y>2.0 {p.y = 2.0}
+        p.x *= 0.5*(self.target.width as f64);
+        p.y *= 0.5*(self.target.height as f64);
+    }
+
+    fn to_screen_space(&self, tri: &mut Tri){
+        for i in 0..3 {
+            self.to_screen_point(&mut tri.p[i]);
+        }
+    }
+
+    // View space overlay lines (from, to, color, depth tested) for the scene grid and the lights,
+    // sized to the scene's bounds in the scene's root space
+    fn scene_overlays(&self, mat_view: &Matrix4x4, lights: &[(Light, Option<ShadowMap>)], lines: &mut Vec<(Vec3d, Vec3d, egui::Color32, bool)>) {
+        let overlays = self.settings.overlays;
+        let bounds = self.instances.iter().fold(Aabb::empty(), |b, (mesh_index, mat_node, _)| b.union(&self.mesh_bounds[*mesh_index].transformed(mat_node)));
+        if bounds.is_empty() {
+            return;
+        }
+
+        if overlays.grid {
+            // Lines every 1, 2 or 5 times a power of ten, about ten across the scene
+            let (center, size) = (bounds.center(), bounds.size());
+            let extent = size.x.max(size.z).max(1e-9)*1.5;
+            let magnitude = 10f64.powf((extent/10.0).log10().floor());
+            let step = [1.0, 2.0, 5.0, 10.0].iter().map(|f| f*magnitude).find(|s| extent/s <= 10.0).unwrap_or(magnitude*10.0);
+            let half = (extent*0.5/step).ceil() as i64;
+            let (cx, cz) = ((center.x/step).round()*step, (center.z/step).round()*step);
+            let (lo, hi) = (-half as f64*step, half as f64*step);
+            for k in -half..=half {
+                let offset = k as f64*step;
+                for (from, to) in [
+                    (Vec3d::new(cx + offset, bounds.min.y, cz + lo), Vec3d::new(cx + offset, bounds.min.y, cz + hi)),
+                    (Vec3d::new(cx + lo, bounds.min.y, cz + offset), Vec3d::new(cx + hi, bounds.min.y, cz + offset)),
+                ] {
+                    lines.push((mat_view.mul_mat_vec(&from), mat_view.mul_mat_vec(&to), egui::Color32::from_rgb(90, 90, 90), true));
+                }
             }
-            else {
-                cmpOrdering::Less
+        }
+
+        if overlays.lights {
+            let sphere = bounds.transformed(mat_view).bounding_sphere();
+            let color = egui::Color32::from_rgb(255, 220, 0);
+            for (light, _) in lights.iter() {
+                let dir = light.direction;
+                let (u, v) = perpendiculars(&dir);
+                match light.kind {
+                    // Arrow along the light into the scene's centre, which stays in view even for
+                    // lights from behind the camera
+                    LightKind::Directional => {
+                        let head = sphere.center;
+                        let tail = head - dir*(sphere.radius*0.6);
+                        lines.push((tail, head, color, false));
+                        for side in [u, u*-1.0, v, v*-1.0] {
+                            lines.push((head, head - dir*(sphere.radius*0.1) + side*(sphere.radius*0.05), color, false));
+                        }
+                    }
+                    // Cross at the light and the cone's edge, out to the scene's centre
+                    LightKind::Spot { position, cone_angle } => {
+                        let size = sphere.radius*0.05;
+                        for axis in [u, v, dir] {
+                            lines.push((position - axis*size, position + axis*size, color, false));
+                        }
+                        let to_center = sphere.center - position;
+                        let reach = to_center.dot(&to_center).sqrt().max(sphere.radius*0.5);
+                        let (along, across) = (cone_angle.cos()*reach, cone_angle.sin()*reach);
+                        lines.push((position, position + dir*reach, color, false));
+                        for side in [u, u*-1.0, v, v*-1.0] {
+                            lines.push((position, position + dir*along + side*across, color, false));
+                        }
+                    }
+                }
             }
-        });
-        (x1, y1, x2, y2, x3, y3) = (temp_arr[0][1], temp_arr[0][0], temp_arr[1][1], temp_arr[1][0], temp_arr[2][1], temp_arr[2][0]);
-        
-        if y2 == y3 {
-            self.fill_bottom_flat_triangle(x1, y1, x2, y2, x3, y3, color, pixels);
         }
-        else if y1 == y2 {
-            self.fill_top_flat_triangle(x1, y1, x2, y2, x3, y3, color, pixels);
+    }
+
+    // Arms along the scene's axes (x red, y green, z blue) as the view currently turns them, drawn
+    // in the bottom left corner with the nearest arm on top
+    fn draw_axes(&mut self, mat_view: &Matrix4x4) {
+        let scale = self.target.mode.scale() as f64;
+        let origin = Vec3d::new((AXES_SIZE + 10.0)*scale, self.target.height as f64 - (AXES_SIZE + 10.0)*scale, 0.0);
+        let mut arms: Vec<(Vec3d, egui::Color32)> = [
+            (Vec3d::new(1.0, 0.0, 0.0), egui::Color32::RED),
+            (Vec3d::new(0.0, 1.0, 0.0), egui::Color32::GREEN),
+            (Vec3d::new(0.0, 0.0, 1.0), egui::Color32::from_rgb(60, 120, 255)),
+        ].iter().map(|(axis, color)| {
+            let mut dir = mat_view.mul_mat_dir(axis);
+            dir.normalize();
+            (dir, *color)
+        }).collect();
+        arms.sort_by(|a, b| b.0.z.total_cmp(&a.0.z));
+        for (dir, color) in arms {
+            let end = Vec3d::new(origin.x + dir.x*AXES_SIZE*scale, origin.y + dir.y*AXES_SIZE*scale, 0.0);
+            self.draw_edge(&origin, &end, &color, false, false);
         }
-        else {
-            let x4 = (x1 as f64 + ((y2 - y1) as f64 / (y3 - y1) as f64) * ((x3 - x1) as f64)) as i64;
-            let y4 = y2;
-            self.fill_bottom_flat_triangle(x1, y1, x2, y2, x4, y4, color, pixels);
-            self.fill_top_flat_triangle(x2, y2, x4, y4, x3, y3, color, pixels);
+    }
+
+    // Scene lights taken to view space, or a key light above and to the left of the camera when the
+    // scene has none. Shadow casters get a map when shadows are on.
+    fn view_lights(&self, mat_view: &Matrix4x4) -> Vec<Light> {
+        let mut lights: Vec<Light> = self.scene.lights.iter().map(|l| l.transformed(mat_view)).collect();
+        if lights.is_empty() {
+            lights.push(Light::directional(Vec3d::new(0.4, 0.7, 0.6), 1.0 - AMBIENT));
         }
+        lights
     }
 
-    fn to_screen_space(&self, tri: &mut Tri){
-        for i in 0..3 {
-            tri.p[i].x += 1.0;
-            tri.p[i].y += 1.0;
-            // if tri.p[i].x<0.0 {tri.p[i].x = 0.0}
-            // if tri.p[i].y<0.0 {tri.p[i].y = 0.0}
-            // if tri.p[i].x>2.0 {tri.p[i].x = 2.0}
-            // if tri.p[i].y>2.0 {tri.p[i].y = 2.0}
-            tri.p[i].x *= 0.5*(self.buffers.buf_size[0] as f64);
-            tri.p[i].y *= 0.5*(self.buffers.buf_size[1] as f64);
+    // The floor sits at the bottom of the framed sphere, turned to face up the screen, and stays
+    // put while the model spins above it
+    fn ground_transform(&self) -> Matrix4x4 {
+        let mut mat_scale = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        mat_scale.make_scale(GROUND_SIZE, GROUND_SIZE, GROUND_SIZE);
+        let mut mat_flip = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        mat_flip.make_rotation_x(std::f64::consts::PI);
+        let mut mat_trans = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        mat_trans.make_translation(0.0, FRAME_RADIUS, self.view_distance);
+        mat_scale.mul_mat_mat(&mat_flip).mul_mat_mat(&mat_trans)
+    }
+
+    // Poses the nodes the active animation moves at the clock's time and puts the rest back at
+    // their rest transforms, returning the transform of the whole framed scene
+    fn pose_scene(&mut self) -> Matrix4x4 {
+        // Nodes are only ever added, so only new ones need their rest transforms recorded
+        for node in self.scene.nodes[self.rest_transforms.len()..].iter() {
+            self.rest_transforms.push(node.transform.clone());
+            self.rest_poses.push(Trs::from_matrix(&node.transform));
         }
+        self.animated_weights.clear();
+        let animation = match self.scene.animations.get(self.active_animation) {
+            Some(a) => a,
+            None => return Matrix4x4::identity(),
+        };
+        let t = self.clock.time;
+        for (i, node) in self.scene.nodes.iter_mut().enumerate() {
+            let animated = animation.channels.iter().any(|c| c.target == Target::Node(i) && !matches!(c.property, Property::Weight(..)));
+            node.transform = if animated { animation.pose(Target::Node(i), &self.rest_poses[i], t).to_matrix() } else { self.rest_transforms[i].clone() };
+            for m in node.meshes.iter() {
+                animation.morph_weights(Target::Node(i), &mut self.scene.meshes[*m].morph_weights, t);
+                for channel in animation.channels.iter().filter(|c| c.target == Target::Node(i)) {
+                    if let Property::Weight(k, _) = channel.property {
+                        self.animated_weights.push((*m, k));
+                    }
+                }
+            }
+        }
+        animation.pose(Target::Scene, &Trs::identity(), t).to_matrix()
     }
 
-    fn render(&mut self, inp_buffer_index: usize){
-        let mut pixels = self.buffers.bufs[inp_buffer_index].lock().unwrap();
-        pixels.clear();
-        pixels.resize(self.buffers.buf_size[0]*self.buffers.buf_size[1], egui::Color32::from_rgba_premultiplied(0, 0, 0, 255,));
-        
-        let mut ftheta: f64 = self.begin_time.elapsed().as_secs_f64();
-        // ftheta = 10.0;
-        let mut mat_rot_z = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
-        mat_rot_z.make_rotation_z(ftheta);
+    // Takes the UI's playback controls, moves the clock on and reports where it is
+    fn sync_playback(&mut self, playback: &mut Playback) {
+        if playback.active != self.active_animation && playback.active < self.scene.animations.len() {
+            self.active_animation = playback.active;
+            self.clock.seek(0.0);
+        }
+        let duration = self.scene.animations.get(self.active_animation).map_or(0.0, Animation::duration);
+        if let Some(time) = playback.seek.take() {
+            self.clock.seek(time.clamp(0.0, duration));
+        }
+        self.clock.speed = playback.speed;
+        self.clock.looping = playback.looping;
+        if playback.playing { self.clock.play(duration) } else { self.clock.pause() }
 
-        ftheta *= 0.5;
-        let mut mat_rot_x = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
-        mat_rot_x.make_rotation_x(ftheta);
+        let now = Instant::now();
+        self.clock.advance(now.duration_since(self.last_tick).as_secs_f64(), duration);
+        self.last_tick = now;
 
-        let mut mat_trans = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
-        mat_trans.make_translation(0.0, 0.0, 8.0);
+        playback.playing = self.clock.playing;
+        playback.time = self.clock.time;
+        playback.duration = duration;
+        playback.active = self.active_animation;
+        if playback.animations.len() != self.scene.animations.len() {
+            playback.animations = self.scene.animations.iter().map(|a| a.name.clone()).collect();
+        }
+    }
 
-        let light = Vec3d::new(0.0, 0.0, -1.0);
+    // Draws one frame of the scene into display buffer inp_buffer_index
+    pub fn render(&mut self, inp_buffer_index: usize){
+        let frame_start = Instant::now();
+        self.stats = RenderStats::new();
 
-        for m in self.meshes.iter() {
-            let mut triangles_to_raster = Vec::with_capacity(m.tris.len());
-            for t in &m.tris {
-                let tri_rotated = mat_rot_z.mul_mat_tri(&t);
-                let tri_rotated = mat_rot_x.mul_mat_tri(&tri_rotated);
-                let tri_translated = mat_trans.mul_mat_tri(&tri_rotated);
+        if self.target.mode != self.settings.aa {
+            self.target = Framebuffer::new(self.buffers.buf_size, self.settings.aa);
+        }
+        self.target.clear(self.settings.fog.background());
+        
+        let mat_scene = self.pose_scene();
+        // View y runs down the screen, so a half turn about x stands y up scenes upright, facing
+        // the camera with their +z side
+        let mut mat_upright = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        mat_upright.make_rotation_x(std::f64::consts::PI);
+        let mut mat_trans = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        mat_trans.make_translation(0.0, 0.0, self.view_distance);
 
-                let triangles_to_project = tri_translated.triangle_clip_against_plane(&Vec3d::new( 0.0, 0.0, 1.0 ), &Vec3d::new( 0.0, 0.0, 1.0 ));
+        // Takes the scene's root space to view space
+        let mat_view = self.mat_frame.mul_mat_mat(&mat_scene).mul_mat_mat(&mat_upright).mul_mat_mat(&mat_trans);
 
-                for tri_translated in triangles_to_project {
-                    // Use Cross-Product to get surface normal
-                    let normal = tri_translated.get_normal();
+        let near_p = Vec3d::new( 0.0, 0.0, NEAR_Z );
+        let near_n = Vec3d::new( 0.0, 0.0, 1.0 );
 
-                    if  normal.dot(&(tri_translated.p[0] - self.v_camera)) < 0.0 {
+        let frustum = Frustum::from_projection(&self.mat_proj, near_p.z);
+        // Skinned and morphed meshes change shape every frame, so they keep no BVH and have their bounds refreshed
+        self.scene.pose_meshes();
+        if self.settings.use_bvh {
+            self.scene.meshes.iter_mut().filter(|m| m.bvh.is_none() && !m.is_deformable()).for_each(|m| m.build_bvh());
+        }
+        if self.mesh_bounds.len() != self.scene.meshes.len() {
+            self.mesh_bounds = self.scene.meshes.iter().map(|m| m.aabb()).collect();
+        }
+        for (bounds, m) in self.mesh_bounds.iter_mut().zip(self.scene.meshes.iter()).filter(|(_, m)| m.is_deformable()) {
+            *bounds = m.aabb();
+        }
 
-                        let mut tri_projected = self.mat_proj.mul_mat_tri(&tri_translated);
-                        self.to_screen_space(&mut tri_projected);
-                        
-                        let shade = ((normal.x*light.x + normal.y*light.y + normal.z*light.z)*(255.0)) as u8;
-                        tri_projected.shade = shade;
+        // Everything to draw as (scene mesh index, model to view transform); the ground plane has
+        // no scene mesh and goes last so scene instances keep their numbering for picking
+        self.instances.clear();
+        let mut draws: Vec<(Option<usize>, Matrix4x4)> = Vec::new();
+        for (mesh_index, mat_node) in self.scene.world_meshes() {
+            let mat_world = mat_node.mul_mat_mat(&mat_view);
+            // Meshes with simplified levels are swapped for the one that suits their size on screen
+            let mesh_index = match self.scene.lod(mesh_index).filter(|_| self.settings.lod) {
+                Some(lod) => {
+                    let sphere = self.mesh_bounds[mesh_index].bounding_sphere().transformed(&mat_world);
+                    let level = lod.select(lod::screen_size(&sphere, &self.mat_proj));
+                    if level != mesh_index {
+                        self.stats.meshes_lod_reduced += 1;
+                    }
+                    level
+                }
+                None => mesh_index,
+            };
+            self.instances.push((mesh_index, mat_node, mat_world.clone()));
+            draws.push((Some(mesh_index), mat_world));
+        }
+        if self.settings.ground_plane {
+            draws.push((None, self.ground_transform()));
+        }
 
-                        triangles_to_raster.push(tri_projected);
+        // Shadow maps are drawn from every instance, not just those in view, since casters
+        // outside the view can still shadow what is inside it
+        let stage = Instant::now();
+        let mut lights: Vec<(Light, Option<ShadowMap>)> = self.view_lights(&mat_view).into_iter().map(|l| (l, None)).collect();
+        if self.settings.shadows && lights.iter().any(|(l, _)| l.casts_shadows) {
+            let mut bounds = Aabb::empty();
+            for (mesh_index, mat_world) in draws.iter() {
+                let local = match mesh_index {
+                    Some(i) => self.mesh_bounds[*i],
+                    None => self.ground.aabb(),
+                };
+                bounds = bounds.union(&local.transformed(mat_world));
+            }
+            let sphere = bounds.bounding_sphere();
+            for (light, shadow_map) in lights.iter_mut().filter(|(l, _)| l.casts_shadows) {
+                *shadow_map = ShadowMap::new(light, &sphere, self.settings.shadow_map_size);
+                if let Some(map) = shadow_map.as_mut() {
+                    for (mesh_index, mat_world) in draws.iter() {
+                        // Meshes drawn without surfaces have nothing to cast a shadow with
+                        let m = mesh_index.map_or(&self.ground, |i| &self.scene.meshes[i]);
+                        if !matches!(m.render_mode, RenderMode::Wireframe | RenderMode::Points) {
+                            map.draw_mesh(m, mat_world);
+                        }
                     }
-                }                
+                }
             }
-            triangles_to_raster.sort_by(|a, b| {
-                let za = (a.p[0].z+a.p[1].z+a.p[2].z)/3.0;
-                let zb = (b.p[0].z+b.p[1].z+b.p[2].z)/3.0;
-                if za<zb {
-                    cmpOrdering::Greater
+        }
+        self.stats.shadow_time += stage.elapsed();
+
+        // Triangles of every mesh are sorted together, each tagged with its mesh's material
+        // along with whether it belongs to the picked triangle
+        let mut triangles_to_raster: Vec<RasterTri> = Vec::new();
+        // Screen position and color of every vertex of point cloud meshes
+        let mut points: Vec<(Vec3d, egui::Color32)> = Vec::new();
+        let overlays = self.settings.overlays;
+        let mut overlay_lines: Vec<(Vec3d, Vec3d, egui::Color32, bool)> = Vec::new();
+        for (instance, (mesh_index, mat_world)) in draws.iter().enumerate() {
+            let m = mesh_index.map_or(&self.ground, |i| &self.scene.meshes[i]);
+            let material = mesh_index.and(m.material);
+            let base_color = material.map(|i| self.scene.materials[i].base_color).unwrap_or([1.0; 4]);
+            // Unlit colors: the vertex's own (white if it has none) tinted by the material in linear light
+            let tint = |v: usize| {
+                let base = color::to_linear(m.colors.get(v).cloned().unwrap_or(egui::Color32::WHITE));
+                color::to_srgb([0, 1, 2].map(|k| base[k]*base_color[k]))
+            };
+            let mode = m.render_mode;
+            let edge_color = match mode {
+                RenderMode::ShadedEdges => self.settings.line_color,
+                _ => color::to_srgb([base_color[0], base_color[1], base_color[2]]),
+            };
+            // Only filled surfaces can be seen through; hidden line meshes still hide what is behind them
+            let opacity = material.and_then(|i| self.scene.materials[i].opacity()).filter(|_| matches!(mode, RenderMode::Shaded | RenderMode::ShadedEdges));
+            let picked_tri = self.picked.filter(|p| p.instance == instance).map(|p| p.triangle);
+            self.stats.tris_submitted += m.indices.len() as u64;
+
+            // Whole meshes outside the view are dropped before any of their vertices are touched,
+            // and with a BVH only the triangles in nodes that reach into the view are kept
+            let stage = Instant::now();
+            let local_bounds = mesh_index.map_or_else(|| m.aabb(), |i| self.mesh_bounds[i]);
+            if overlays.bounds && mesh_index.is_some() && !local_bounds.is_empty() {
+                let c = local_bounds.corners().map(|c| mat_world.mul_mat_vec(&c));
+                // Corner i has bit 0 set for max x, bit 1 for max y and bit 2 for max z
+                for (a, b) in [(0, 1), (2, 3), (4, 5), (6, 7), (0, 2), (1, 3), (4, 6), (5, 7), (0, 4), (1, 5), (2, 6), (3, 7)] {
+                    overlay_lines.push((c[a], c[b], egui::Color32::from_rgb(255, 140, 0), false));
+                }
+            }
+            let visible_tris = match (frustum.classify_aabb(&local_bounds, mat_world), &m.bvh) {
+                (Containment::Outside, _) => {
+                    self.stats.meshes_frustum_culled += 1;
+                    self.stats.tris_frustum_culled += m.indices.len() as u64;
+                    self.stats.transform_time += stage.elapsed();
+                    continue;
                 }
-                else {
-                    cmpOrdering::Less
+                (Containment::Intersecting, Some(bvh)) if self.settings.use_bvh => {
+                    let mut tris = Vec::new();
+                    bvh.visible_tris(&frustum, mat_world, &mut tris);
+                    self.stats.tris_frustum_culled += (m.indices.len() - tris.len()) as u64;
+                    Some(tris)
                 }
+                _ => None,
+            };
+
+            // Every needed vertex is transformed and projected once; triangles are assembled from the results
+            let needed = visible_tris.as_ref().map(|tris| {
+                let mut needed = vec![false; m.positions.len()];
+                tris.iter().for_each(|t| m.indices[*t].iter().for_each(|v| needed[*v] = true));
+                needed
             });
-            for tri_projected in triangles_to_raster.iter() {
-                self.fill_triangle(tri_projected.p[0].x as i64, tri_projected.p[0].y as i64, tri_projected.p[1].x as i64, tri_projected.p[1].y as i64, tri_projected.p[2].x as i64, tri_projected.p[2].y as i64, &egui::Color32::from_rgba_premultiplied(tri_projected.shade, tri_projected.shade, tri_projected.shade, 255), &mut pixels);
-                self.draw_triangle(tri_projected.p[0].x as i64, tri_projected.p[0].y as i64, tri_projected.p[1].x as i64, tri_projected.p[1].y as i64, tri_projected.p[2].x as i64, tri_projected.p[2].y as i64, &egui::Color32::from_rgba_premultiplied(0, 0, 0, 255,), &mut pixels);
+            let is_needed = |v: usize| needed.as_ref().is_none_or(|n| n[v]);
+            let zero = Vec3d::new(0.0, 0.0, 0.0);
+            let view_positions: Vec<Vec3d> = m.positions.iter().enumerate().map(|(v, p)| if is_needed(v) { mat_world.mul_mat_vec(p) } else { zero }).collect();
+            let mat_normal = mat_world.normal_matrix();
+            let view_normals: Vec<Vec3d> = m.normals.iter().enumerate().map(|(v, n)| {
+                if !is_needed(v) {
+                    return zero;
+                }
+                let mut n = mat_normal.mul_mat_dir(n);
+                n.normalize();
+                n
+            }).collect();
+            let screen_positions: Vec<Vec3d> = view_positions.iter().enumerate().map(|(v, p)| {
+                if !is_needed(v) {
+                    return zero;
+                }
+                let mut projected = self.mat_proj.mul_mat_vec(p);
+                self.to_screen_point(&mut projected);
+                projected
+            }).collect();
+            self.stats.transform_time += stage.elapsed();
+
+            if overlays.vertex_normals && mesh_index.is_some() {
+                for (v, n) in view_normals.iter().enumerate().filter(|(v, _)| is_needed(*v)) {
+                    let mut n = *n;
+                    n.normalize();
+                    overlay_lines.push((view_positions[v], view_positions[v] + n*NORMAL_LENGTH, egui::Color32::from_rgb(255, 0, 255), true));
+                }
+            }
+
+            if mode == RenderMode::Points {
+                let in_front = |v: usize| is_needed(v) && view_positions[v].z >= near_p.z;
+                points.extend((0..m.positions.len()).filter(|v| in_front(*v)).map(|v| (screen_positions[v], tint(v))));
+                continue;
+            }
+
+            let tri_indices = visible_tris.unwrap_or_else(|| (0..m.indices.len()).collect());
+            for i in tri_indices {
+                let stage = Instant::now();
+                let mut tri_translated = m.assemble_tri(i, &view_positions, &view_normals);
+
+                // Use Cross-Product to get surface normal
+                let normal = tri_translated.get_normal();
+                // Clipping keeps the triangle in its plane, so facing can be decided before it.
+                // Wireframes show back faces too.
+                if mode != RenderMode::Wireframe && normal.dot(&(tri_translated.p[0] - self.v_camera)) >= 0.0 {
+                    self.stats.tris_culled += 1;
+                    self.stats.transform_time += stage.elapsed();
+                    continue;
+                }
+                if overlays.face_normals && mesh_index.is_some() {
+                    let centroid = (tri_translated.p[0] + tri_translated.p[1] + tri_translated.p[2])*(1.0/3.0);
+                    overlay_lines.push((centroid, centroid + normal*NORMAL_LENGTH, egui::Color32::from_rgb(0, 255, 255), true));
+                }
+                // Meshes without normals are lit by the face normal
+                tri_translated.col = m.indices[i].iter().map(|v| tint(*v)).collect();
+                if tri_translated.n.len() != 3 {
+                    tri_translated.n = vec![normal; 3];
+                }
+                self.stats.transform_time += stage.elapsed();
+
+                let stage = Instant::now();
+                let picked = picked_tri == Some(i);
+                if tri_translated.p.iter().all(|p| (*p - near_p).dot(&near_n) >= 0.0) {
+                    // Nothing to clip, so the shared projected vertices can be used as they are
+                    let mut tri_projected = m.assemble_tri(i, &screen_positions, &[]);
+                    tri_projected.col = tri_translated.col;
+                    triangles_to_raster.push(RasterTri { tri: tri_projected, view: tri_translated.p, normals: tri_translated.n, material, opacity, picked, mode, edge_color });
+                    self.stats.clip_time += stage.elapsed();
+                    continue;
+                }
+                self.stats.tris_clipped += 1;
+                let triangles_to_project = tri_translated.triangle_clip_against_plane(&near_p, &near_n);
+                self.stats.clip_time += stage.elapsed();
+
+                let stage = Instant::now();
+                for tri_translated in triangles_to_project {
+                    let mut tri_projected = self.mat_proj.mul_mat_tri(&tri_translated);
+                    self.to_screen_space(&mut tri_projected);
+                    triangles_to_raster.push(RasterTri { tri: tri_projected, view: tri_translated.p, normals: tri_translated.n, material, opacity, picked, mode, edge_color });
+                }
+                self.stats.transform_time += stage.elapsed();
+            }
+        }
+
+        let stage = Instant::now();
+        triangles_to_raster.sort_by(|a, b| {
+            let za = (a.tri.p[0].z+a.tri.p[1].z+a.tri.p[2].z)/3.0;
+            let zb = (b.tri.p[0].z+b.tri.p[1].z+b.tri.p[2].z)/3.0;
+            zb.total_cmp(&za)
+        });
+        self.stats.sort_time += stage.elapsed();
+
+        // Opaque surfaces go first so see-through ones, still back to front, have all they cover to
+        // blend over
+        let stage = Instant::now();
+        let (opaque, see_through): (Vec<&RasterTri>, Vec<&RasterTri>) = triangles_to_raster.iter().filter(|rt| rt.mode != RenderMode::Wireframe).partition(|rt| rt.opacity.is_none());
+        for rt in opaque.into_iter().chain(see_through) {
+            self.stats.tris_rasterized += 1;
+            let texture = rt.material.and_then(|i| self.scene.materials[i].base_color_texture.clone());
+            self.fill_triangle(rt, texture.as_deref(), &lights);
+            if rt.mode == RenderMode::ShadedEdges {
+                self.draw_outline(&rt.tri.p, &rt.edge_color, false, true);
             }
         }
+        self.target.composite_transparency();
+        // Edges that need the finished depth buffer, or that ignore it, go on once everything is filled
+        for rt in triangles_to_raster.iter().filter(|rt| matches!(rt.mode, RenderMode::HiddenLine | RenderMode::Wireframe)) {
+            self.draw_outline(&rt.tri.p, &rt.edge_color, rt.mode == RenderMode::HiddenLine, true);
+        }
+        for (p, color) in points.iter() {
+            self.draw_point(p, color);
+        }
+        self.scene_overlays(&mat_view, &lights, &mut overlay_lines);
+        for (from, to, color, depth_test) in overlay_lines.iter() {
+            self.draw_view_line(from, to, color, *depth_test);
+        }
+        if overlays.axes {
+            self.draw_axes(&mat_view);
+        }
+        // The picked triangle's outline goes on last so neighbouring outlines don't cover it
+        for rt in triangles_to_raster.iter().filter(|rt| rt.picked) {
+            self.draw_outline(&rt.tri.p, &egui::Color32::YELLOW, false, false);
+        }
+        self.stats.raster_time += stage.elapsed();
+
+        let stage = Instant::now();
+        let mut image = self.target.resolve_linear(self.settings.exposure);
+        self.stats.resolve_time += stage.elapsed();
+
+        // Light effects see the HDR frame, before tone mapping squeezes its highlights; the
+        // passes tuned for display values see it afterwards
+        let (width, height) = (self.buffers.buf_size[0], self.buffers.buf_size[1]);
+        let stage = Instant::now();
+        self.post.apply_linear(&mut image, width, height);
+        self.stats.post_time += stage.elapsed();
+        let stage = Instant::now();
+        image.iter_mut().for_each(|c| *c = self.settings.tone_mapping.display(*c));
+        self.stats.resolve_time += stage.elapsed();
+        let stage = Instant::now();
+        self.post.apply_display(&mut image, width, height);
+        self.stats.post_time += stage.elapsed();
+
+        let buf = self.buffers.bufs[inp_buffer_index].clone();
+        let mut pixels = buf.lock().unwrap();
+        pixels.clear();
+        pixels.extend(image.iter().map(|c| color::to_bytes(*c)));
         drop(pixels);
+
+        self.stats.frame_time = frame_start.elapsed();
     }
     
     pub fn lo(&mut self) {
 
-        self.load_from_object_file("./teapot.obj".to_string());
+        // Fall back to the generated teapot so the demo runs without the model file
+        if let Err(e) = self.load_model("./teapot.obj") {
+            self.state.lock().unwrap().notice = Some(format!("Could not load ./teapot.obj ({}), showing the built-in teapot", e));
+            self.scene.add_mesh("teapot".to_string(), Mesh::teapot(3.0, 8));
+        }
+        self.frame_scene();
+        // Scenes that don't move on their own turn slowly so they can be seen from every side
+        if self.scene.animations.is_empty() {
+            self.scene.animations.push(Animation::turntable(TURNTABLE_PERIOD));
+        }
+        self.last_tick = Instant::now();
 
         loop {
+            let state = self.state.clone();
+            let mut state_lock = state.lock().unwrap();
+            self.settings = state_lock.settings;
+            self.post.clone_from(&state_lock.post);
+            let frame_requested = std::mem::take(&mut state_lock.frame_requested);
+            // The old triangles are gone once the scene is subdivided or cleaned up, so nothing stays picked
+            let subdivision = std::mem::take(&mut state_lock.subdivide_requested).then_some((state_lock.subdivision_scheme, state_lock.subdivision_levels));
+            let clean_up = std::mem::take(&mut state_lock.clean_up_requested);
+            let lods_requested = std::mem::take(&mut state_lock.lod_requested);
+            if subdivision.is_some() || clean_up {
+                self.picked = None;
+                state_lock.picked = None;
+            }
+            if let Some([x, y]) = state_lock.pick_request.take() {
+                self.picked = self.pick(x, y);
+                state_lock.picked = self.picked;
+            }
+            // Mode edits from the UI go to the meshes, and newly added meshes are reported back. The
+            // UI only lists meshes the nodes draw, leaving out levels of detail.
+            let meshes = self.scene.drawn_meshes();
+            if state_lock.mesh_modes.len() == meshes.len() {
+                meshes.iter().zip(state_lock.mesh_modes.iter()).for_each(|(m, mode)| self.scene.meshes[*m].render_mode = *mode);
+                self.scene.sync_lod_modes();
+            }
+            else {
+                state_lock.mesh_modes = meshes.iter().map(|m| self.scene.meshes[*m].render_mode).collect();
+                state_lock.mesh_indices.clone_from(&meshes);
+            }
+            self.sync_playback(&mut state_lock.playback);
+            if state_lock.material_alpha.len() == self.scene.materials.len() {
+                for (m, (mode, opacity)) in self.scene.materials.iter_mut().zip(state_lock.material_alpha.iter()) {
+                    m.alpha_mode = *mode;
+                    m.base_color[3] = *opacity;
+                }
+            }
+            else {
+                state_lock.material_alpha = self.scene.materials.iter().map(|m| (m.alpha_mode, m.base_color[3])).collect();
+                state_lock.material_names = self.scene.materials.iter().map(|m| m.name.clone()).collect();
+            }
+            // Weights set with the sliders go to the meshes; animations may still override them
+            if state_lock.morph_weights.len() == meshes.len() {
+                for (m, weights) in meshes.iter().zip(state_lock.morph_weights.iter()) {
+                    let m = &mut self.scene.meshes[*m];
+                    if weights.len() == m.morph_weights.len() {
+                        m.morph_weights.clone_from(weights);
+                    }
+                }
+            }
+            drop(state_lock);
+            if let Some((scheme, levels)) = subdivision {
+                self.scene.subdivide(scheme, levels);
+                self.mesh_bounds.clear();
+            }
+            if clean_up {
+                self.scene.clean_up();
+                self.mesh_bounds.clear();
+            }
+            if lods_requested && self.generate_lods() == 0 {
+                self.state.lock().unwrap().notice = Some(format!("No mesh has the {} triangles it takes to get levels of detail", LOD_MIN_TRIANGLES));
+            }
+            if frame_requested {
+                self.frame_scene();
+            }
+
             let trip_state_lock = self.buffers.trip_state.lock().unwrap();
             let inp_buffer_index = trip_state_lock[1];
             drop(trip_state_lock);
@@ -321,7 +1059,20 @@ impl Engine{
             self.buffers.stale.store(false, Ordering::Relaxed);
             drop(trip_state_lock);
 
-            let state_lock = self.state.lock().unwrap();
+            let mut state_lock = self.state.lock().unwrap();
+            state_lock.stats = self.stats;
+            state_lock.history.push(self.stats.frame_time);
+            // Only animated weights are reported back, so slider edits made during the frame aren't lost
+            if state_lock.morph_names.len() != meshes.len() {
+                state_lock.morph_weights = meshes.iter().map(|m| self.scene.meshes[*m].morph_weights.clone()).collect();
+                state_lock.morph_names = meshes.iter().map(|m| self.scene.meshes[*m].morph_targets.iter().map(|t| t.name.clone()).collect()).collect();
+            }
+            for (m, k) in self.animated_weights.iter() {
+                let listed = meshes.iter().position(|d| d == m);
+                if let Some(weight) = listed.and_then(|i| state_lock.morph_weights.get_mut(i)).and_then(|w| w.get_mut(*k)) {
+                    *weight = self.scene.meshes[*m].morph_weights[*k];
+                }
+            }
             let ctx = &state_lock.ctx;
             match ctx {
                 Some(x) => x.request_repaint(),
@@ -329,4 +1080,12 @@ impl Engine{
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// Two unit vectors at right angles to each other and to the unit vector dir
+fn perpendiculars(dir: &Vec3d) -> (Vec3d, Vec3d) {
+    let helper = if dir.y.abs() > 0.9 { Vec3d::new(1.0, 0.0, 0.0) } else { Vec3d::new(0.0, 1.0, 0.0) };
+    let mut u = dir.cross(&helper);
+    u.normalize();
+    (u, dir.cross(&u))
+}
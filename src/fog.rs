@@ -0,0 +1,80 @@
+use eframe::egui::Color32;
+
+use crate::color::{ self, Rgb };
+
+// How the share of a surface's own color left after fog falls off with view depth z
+#[derive(Copy, Clone, PartialEq, Default)]
+pub enum FogMode {
+    #[default]
+    Off,
+    // All of it before start, none of it past end
+    Linear,
+    // e^(-density*z)
+    Exponential,
+    // e^(-(density*z)²), which stays clear for longer then closes in faster
+    ExponentialSquared,
+}
+
+impl FogMode {
+    pub const ALL: [FogMode; 4] = [FogMode::Off, FogMode::Linear, FogMode::Exponential, FogMode::ExponentialSquared];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FogMode::Off => "Off",
+            FogMode::Linear => "Linear",
+            FogMode::Exponential => "Exponential",
+            FogMode::ExponentialSquared => "Exponential squared",
+        }
+    }
+}
+
+// Distance fog, which also fills the background so distant surfaces fade into it
+#[derive(Copy, Clone)]
+pub struct Fog {
+    pub mode: FogMode,
+    pub color: Color32,
+    // View depths where linear fog begins and becomes solid
+    pub start: f64,
+    pub end: f64,
+    pub density: f64,
+    // Work the fog out at each vertex and interpolate it across triangles, instead of at every pixel
+    pub per_vertex: bool,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self { mode: FogMode::Off, color: Color32::from_gray(160), start: 6.0, end: 20.0, density: 0.08, per_vertex: false }
+    }
+}
+
+impl Fog {
+    // Share of a surface's own color seen through the fog at view depth z, 1 being clear
+    pub fn visibility(&self, z: f64) -> f64 {
+        let z = z.max(0.0);
+        let visibility = match self.mode {
+            FogMode::Off => 1.0,
+            FogMode::Linear if self.end <= self.start => if z < self.start { 1.0 } else { 0.0 },
+            FogMode::Linear => (self.end - z)/(self.end - self.start),
+            FogMode::Exponential => (-self.density*z).exp(),
+            FogMode::ExponentialSquared => (-(self.density*z).powi(2)).exp(),
+        };
+        visibility.clamp(0.0, 1.0)
+    }
+
+    // What is drawn where nothing else is
+    pub fn background(&self) -> Rgb {
+        match self.mode {
+            FogMode::Off => [0.0; 3],
+            _ => color::to_linear(self.color),
+        }
+    }
+
+    // Mixes linear color c towards the fog color, keeping the visible share of it
+    pub fn apply(&self, c: Rgb, visibility: f64) -> Rgb {
+        if visibility >= 1.0 {
+            return c;
+        }
+        let fog = color::to_linear(self.color);
+        [0, 1, 2].map(|k| fog[k] + (c[k] - fog[k])*visibility)
+    }
+}
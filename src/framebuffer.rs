@@ -0,0 +1,242 @@
+use eframe::egui::Color32;
+
+use crate::color::{ Rgb, ToneMapping };
+
+// Supersampling draws everything at scale x scale the output resolution and averages it down.
+// Multisampling keeps the output resolution but tests coverage at several points in each pixel,
+// shading once per pixel and writing that to the covered samples.
+#[derive(Copy, Clone, PartialEq, Default)]
+pub enum AaMode {
+    #[default]
+    Off,
+    Ssaa2x,
+    Ssaa4x,
+    Msaa4x,
+}
+
+// Rotated grid, so near horizontal and near vertical edges both get four distinct coverage steps
+const MSAA_4X: [(f64, f64); 4] = [(0.375, 0.125), (0.875, 0.375), (0.125, 0.625), (0.625, 0.875)];
+const CENTRE: [(f64, f64); 1] = [(0.5, 0.5)];
+
+impl AaMode {
+    pub const ALL: [AaMode; 4] = [AaMode::Off, AaMode::Ssaa2x, AaMode::Ssaa4x, AaMode::Msaa4x];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AaMode::Off => "Off",
+            AaMode::Ssaa2x => "SSAA 2x (2x2 grid)",
+            AaMode::Ssaa4x => "SSAA 4x (4x4 grid)",
+            AaMode::Msaa4x => "MSAA 4x",
+        }
+    }
+
+    // Render target pixels per output pixel along each axis
+    pub fn scale(&self) -> usize {
+        match self {
+            AaMode::Ssaa2x => 2,
+            AaMode::Ssaa4x => 4,
+            _ => 1,
+        }
+    }
+
+    // Where coverage is tested within a render target pixel
+    pub fn sample_offsets(&self) -> &'static [(f64, f64)] {
+        match self {
+            AaMode::Msaa4x => &MSAA_4X,
+            _ => &CENTRE,
+        }
+    }
+}
+
+// How see-through surfaces are combined. Sorted blending draws them back to front over what is
+// behind, which goes wrong where triangles cross or overlap out of order. Weighted blended
+// order-independent transparency (McGuire and Bavoil) sums them in any order, weighted towards
+// the nearer ones, and is approximate but free of sorting artifacts.
+#[derive(Copy, Clone, PartialEq, Default)]
+pub enum TransparencyMode {
+    #[default]
+    Sorted,
+    WeightedBlended,
+}
+
+impl TransparencyMode {
+    pub const ALL: [TransparencyMode; 2] = [TransparencyMode::Sorted, TransparencyMode::WeightedBlended];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransparencyMode::Sorted => "Sorted blending",
+            TransparencyMode::WeightedBlended => "Weighted blended OIT",
+        }
+    }
+}
+
+// Render target holding every sample of every pixel, resolved into an output buffer once drawn
+pub struct Framebuffer {
+    pub mode: AaMode,
+    pub width: usize,
+    pub height: usize,
+    // Linear HDR color of every sample; single precision is plenty for color and keeps
+    // supersampled targets small. Samples of pixel (x, y) are at (y*width + x)*samples onwards.
+    pub color: Vec<[f32; 3]>,
+    // View depth of the nearest surface drawn into each sample, infinite where nothing was
+    pub depth: Vec<f64>,
+    // Weighted blended transparency: per sample sums of weighted premultiplied color and weighted
+    // alpha, and the product of (1 - alpha), the share of what is behind that shows through.
+    // Empty until something is accumulated.
+    accum: Vec<[f32; 4]>,
+    revealage: Vec<f32>,
+    samples: usize,
+}
+
+impl Framebuffer {
+    // A target for an output of output_size pixels
+    pub fn new(output_size: [usize; 2], mode: AaMode) -> Self {
+        let (width, height) = (output_size[0]*mode.scale(), output_size[1]*mode.scale());
+        let samples = mode.sample_offsets().len();
+        Self { mode, width, height, color: vec![[0.0; 3]; width*height*samples], depth: vec![f64::INFINITY; width*height*samples], accum: Vec::new(), revealage: Vec::new(), samples }
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    // Mask with every sample of a pixel set
+    pub fn full_mask(&self) -> u32 {
+        (1 << self.samples) - 1
+    }
+
+    pub fn clear(&mut self, color: Rgb) {
+        self.color.fill(color.map(|v| v as f32));
+        self.depth.fill(f64::INFINITY);
+        self.accum.clear();
+        self.revealage.clear();
+    }
+
+    // Lowers the depth of the samples of pixel (x, y) set in mask to z where that is nearer
+    pub fn put_depth(&mut self, x: i64, y: i64, mask: u32, z: f64) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let base = (y as usize*self.width + x as usize)*self.samples;
+        for s in 0..self.samples {
+            if mask & (1 << s) != 0 && z < self.depth[base + s] {
+                self.depth[base + s] = z;
+            }
+        }
+    }
+
+    // Samples of pixel (x, y) set in mask that a surface at depth z is in front of
+    pub fn depth_test(&self, x: i64, y: i64, mask: u32, z: f64) -> u32 {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return 0;
+        }
+        let base = (y as usize*self.width + x as usize)*self.samples;
+        (0..self.samples).filter(|s| mask & (1 << s) != 0 && z <= self.depth[base + s]).fold(0, |m, s| m | (1 << s))
+    }
+
+    // Farthest depth among the samples of pixel (x, y), so something drawn at z is visible in at
+    // least part of the pixel when z is no further than this
+    pub fn depth_at(&self, x: i64, y: i64) -> f64 {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return f64::INFINITY;
+        }
+        let base = (y as usize*self.width + x as usize)*self.samples;
+        self.depth[base..base + self.samples].iter().cloned().fold(f64::MIN, f64::max)
+    }
+
+    // Writes color to the samples of pixel (x, y) set in mask, returning whether the pixel is on the target
+    pub fn put(&mut self, x: i64, y: i64, mask: u32, color: Rgb) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return false;
+        }
+        let color = color.map(|v| v as f32);
+        let base = (y as usize*self.width + x as usize)*self.samples;
+        for s in 0..self.samples {
+            if mask & (1 << s) != 0 {
+                self.color[base + s] = color;
+            }
+        }
+        true
+    }
+
+    // Mixes color over the samples of pixel (x, y) set in mask, alpha being color's share
+    pub fn blend(&mut self, x: i64, y: i64, mask: u32, color: Rgb, alpha: f64) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return false;
+        }
+        let alpha = alpha.clamp(0.0, 1.0) as f32;
+        let color = color.map(|v| v as f32);
+        let base = (y as usize*self.width + x as usize)*self.samples;
+        for s in 0..self.samples {
+            if mask & (1 << s) != 0 {
+                let under = &mut self.color[base + s];
+                *under = [0, 1, 2].map(|k| under[k] + (color[k] - under[k])*alpha);
+            }
+        }
+        true
+    }
+
+    // Adds a see-through surface at view depth z to the samples of pixel (x, y) set in mask, for
+    // composite_transparency to combine with everything else accumulated there
+    pub fn accumulate(&mut self, x: i64, y: i64, mask: u32, color: Rgb, alpha: f64, z: f64) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return false;
+        }
+        if self.accum.is_empty() {
+            self.accum = vec![[0.0; 4]; self.color.len()];
+            self.revealage = vec![1.0; self.color.len()];
+        }
+        let alpha = alpha.clamp(0.0, 1.0);
+        // Equation 7 of the paper, for view depths of a few units to a few hundred
+        let weight = alpha*(10.0/(1e-5 + (z/5.0).powi(2) + (z/200.0).powi(6))).clamp(1e-2, 3e3);
+        let add = [color[0]*weight, color[1]*weight, color[2]*weight, weight].map(|v| v as f32);
+        let base = (y as usize*self.width + x as usize)*self.samples;
+        for s in 0..self.samples {
+            if mask & (1 << s) != 0 {
+                let sum = &mut self.accum[base + s];
+                *sum = [0, 1, 2, 3].map(|k| sum[k] + add[k]);
+                self.revealage[base + s] *= 1.0 - alpha as f32;
+            }
+        }
+        true
+    }
+
+    // Lays the weighted average of the accumulated surfaces over the color of each sample, letting
+    // through as much of it as they all reveal together
+    pub fn composite_transparency(&mut self) {
+        for ((c, sum), revealage) in self.color.iter_mut().zip(self.accum.iter()).zip(self.revealage.iter()) {
+            if *revealage < 1.0 {
+                let average = [0, 1, 2].map(|k| sum[k]/sum[3].max(1e-5));
+                *c = [0, 1, 2].map(|k| average[k]*(1.0 - revealage) + c[k]*revealage);
+            }
+        }
+        self.accum.clear();
+        self.revealage.clear();
+    }
+
+    // Box filters every output pixel's block of pixels and their samples, averaging in linear
+    // light, and scales the result by 2^exposure. The image is still HDR, for post processing.
+    pub fn resolve_linear(&self, exposure: f64) -> Vec<Rgb> {
+        let scale = self.mode.scale();
+        let (out_width, out_height) = (self.width/scale, self.height/scale);
+        let weight = exposure.exp2()/(scale*scale*self.samples) as f64;
+        (0..out_width*out_height).map(|i| {
+            let (ox, oy) = (i % out_width, i / out_width);
+            let mut sum = [0.0; 3];
+            for y in oy*scale..(oy + 1)*scale {
+                let row = (y*self.width + ox*scale)*self.samples;
+                for c in self.color[row..row + scale*self.samples].iter() {
+                    for k in 0..3 {
+                        sum[k] += c[k] as f64;
+                    }
+                }
+            }
+            sum.map(|s| s*weight)
+        }).collect()
+    }
+
+    // resolve_linear, then tone mapped and encoded as sRGB into out
+    pub fn resolve(&self, out: &mut [Color32], tone_mapping: ToneMapping, exposure: f64) {
+        tone_mapping.encode(&self.resolve_linear(exposure), out);
+    }
+}
@@ -1,2 +1,23 @@
+pub mod animation;
+pub mod bounds;
+pub mod bvh;
+pub mod cleanup;
+pub mod color;
 pub mod engine;
-pub mod objs;
\ No newline at end of file
+pub mod fog;
+pub mod framebuffer;
+pub mod loaders;
+pub mod lod;
+pub mod morph;
+pub mod objs;
+pub mod picking;
+pub mod post;
+pub mod primitives;
+pub mod raster;
+pub mod scene;
+pub mod shadow;
+pub mod simplify;
+pub mod skin;
+pub mod stats;
+pub mod subdivision;
+pub mod writers;
\ No newline at end of file
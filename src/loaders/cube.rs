@@ -0,0 +1,50 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::loaders::{ parse_f64, LoadError };
+use crate::post::Lut;
+
+pub fn load_cube<P: AsRef<Path>>(path: P) -> Result<Lut, LoadError> {
+    let file = File::open(path)?;
+    parse_cube(BufReader::new(file))
+}
+
+// Reads a 3D color lookup table in the .cube format: a LUT_3D_SIZE line, optional DOMAIN_MIN and
+// DOMAIN_MAX lines, then size³ "r g b" lines with red changing fastest
+pub fn parse_cube<R: BufRead>(reader: R) -> Result<Lut, LoadError> {
+    let mut size = 0;
+    let mut domain = ([0.0; 3], [1.0; 3]);
+    let mut entries = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_no = i + 1;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            None => {}
+            Some(t) if t.starts_with('#') => {}
+            Some("TITLE") | Some("LUT_1D_INPUT_RANGE") | Some("LUT_3D_INPUT_RANGE") => {}
+            Some("LUT_1D_SIZE") => return Err(LoadError::Unsupported("1D lookup tables".to_string())),
+            Some("LUT_3D_SIZE") => {
+                size = parse_f64(tokens.next(), line_no)? as usize;
+                if !(2..=256).contains(&size) {
+                    return Err(LoadError::parse(line_no, format!("LUT_3D_SIZE {} out of range", size)));
+                }
+            }
+            Some(key @ ("DOMAIN_MIN" | "DOMAIN_MAX")) => {
+                let value = [parse_f64(tokens.next(), line_no)?, parse_f64(tokens.next(), line_no)?, parse_f64(tokens.next(), line_no)?];
+                if key == "DOMAIN_MIN" { domain.0 = value } else { domain.1 = value }
+            }
+            Some(first) => {
+                entries.push([parse_f64(Some(first), line_no)?, parse_f64(tokens.next(), line_no)?, parse_f64(tokens.next(), line_no)?]);
+            }
+        }
+    }
+    if size == 0 {
+        return Err(LoadError::parse(0, "missing LUT_3D_SIZE"));
+    }
+    if entries.len() != size*size*size {
+        return Err(LoadError::parse(0, format!("expected {} entries, found {}", size*size*size, entries.len())));
+    }
+    Ok(Lut { size, entries, domain_min: domain.0, domain_max: domain.1 })
+}
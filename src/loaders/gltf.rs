@@ -0,0 +1,595 @@
+use eframe::egui::Color32;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::animation::{ Animation, Channel, Interpolation, Property, Target, Track, Trs };
+use crate::color;
+use crate::loaders::json::{ self, Json };
+use crate::loaders::LoadError;
+use crate::morph::MorphTarget;
+use crate::objs::{ Matrix4x4, Mesh, Vec2d, Vec3d };
+use crate::scene::{ AlphaMode, Material, Node, Scene, Texture };
+use crate::skin::Skin;
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F534A;
+const CHUNK_BIN: u32 = 0x004E4942;
+// Most elements an accessor without a bufferView may have
+const MAX_UNBACKED_ELEMENTS: usize = 1 << 24;
+
+// Reads a .gltf or .glb file; external buffers and images are resolved next to it
+pub fn load_gltf<P: AsRef<Path>>(path: P) -> Result<Scene, LoadError> {
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+    parse_gltf(&data, path.parent())
+}
+
+// base_dir is where relative URIs are looked up; without one only embedded data can be used
+pub fn parse_gltf(data: &[u8], base_dir: Option<&Path>) -> Result<Scene, LoadError> {
+    let (doc, bin) = if data.starts_with(GLB_MAGIC) {
+        let (text, bin) = split_glb(data)?;
+        (json::parse(text)?, bin)
+    }
+    else {
+        let text = std::str::from_utf8(data).map_err(|_| LoadError::parse(0, "glTF JSON is not valid UTF-8"))?;
+        (json::parse(text)?, None)
+    };
+
+    let version = doc.get("asset").get("version").as_str().unwrap_or("");
+    if !version.starts_with("2.") {
+        return Err(LoadError::Unsupported(format!("glTF version '{}'", version)));
+    }
+
+    let mut loader = Loader { doc: &doc, base_dir, buffers: Vec::new(), images: HashMap::new() };
+    loader.load_buffers(bin)?;
+    loader.build_scene()
+}
+
+// Splits a binary container into its JSON text and optional BIN chunk
+fn split_glb(data: &[u8]) -> Result<(&str, Option<&[u8]>), LoadError> {
+    let word = |o: usize| -> Result<u32, LoadError> {
+        data.get(o..o + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or(LoadError::Truncated { expected: o + 4, found: data.len() })
+    };
+    if word(4)? != 2 {
+        return Err(LoadError::Unsupported(format!("GLB container version {}", word(4)?)));
+    }
+    let total = word(8)? as usize;
+    if data.len() < total {
+        return Err(LoadError::Truncated { expected: total, found: data.len() });
+    }
+
+    let mut text = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset + 8 <= total {
+        let length = word(offset)? as usize;
+        let kind = word(offset + 4)?;
+        let chunk = data.get(offset + 8..offset + 8 + length).ok_or(LoadError::Truncated { expected: offset + 8 + length, found: data.len() })?;
+        match kind {
+            CHUNK_JSON => text = Some(std::str::from_utf8(chunk).map_err(|_| LoadError::parse(0, "GLB JSON chunk is not valid UTF-8"))?),
+            CHUNK_BIN if bin.is_none() => bin = Some(chunk),
+            _ => {}
+        }
+        // Chunks are padded to 4 byte boundaries
+        offset += 8 + ((length + 3) & !3);
+    }
+    let text = text.ok_or_else(|| LoadError::parse(0, "GLB has no JSON chunk"))?;
+    Ok((text, bin))
+}
+
+struct Loader<'a> {
+    doc: &'a Json,
+    base_dir: Option<&'a Path>,
+    buffers: Vec<Vec<u8>>,
+    // Decoded images by index, shared between the materials that use them
+    images: HashMap<usize, Arc<Texture>>,
+}
+
+impl<'a> Loader<'a> {
+    fn load_buffers(&mut self, bin: Option<&[u8]>) -> Result<(), LoadError> {
+        for (i, buffer) in self.doc.get("buffers").as_array().iter().enumerate() {
+            let length = buffer.get("byteLength").as_usize().ok_or_else(|| LoadError::parse(0, format!("buffer {} has no byteLength", i)))?;
+            let data = match buffer.get("uri").as_str() {
+                Some(uri) => self.read_uri(uri)?,
+                None if i == 0 => bin.ok_or_else(|| LoadError::parse(0, "buffer 0 has no uri and there is no GLB BIN chunk"))?.to_vec(),
+                None => return Err(LoadError::parse(0, format!("buffer {} has no uri", i))),
+            };
+            if data.len() < length {
+                return Err(LoadError::Truncated { expected: length, found: data.len() });
+            }
+            self.buffers.push(data);
+        }
+        Ok(())
+    }
+
+    // Only data URIs and paths relative to the file are accepted; anything with a scheme is refused
+    fn read_uri(&self, uri: &str) -> Result<Vec<u8>, LoadError> {
+        if let Some(rest) = uri.strip_prefix("data:") {
+            let (meta, payload) = rest.split_once(',').ok_or_else(|| LoadError::parse(0, "malformed data URI"))?;
+            if !meta.ends_with(";base64") {
+                return Err(LoadError::Unsupported("data URI without base64 encoding".to_string()));
+            }
+            return decode_base64(payload);
+        }
+        if uri.contains("://") {
+            return Err(LoadError::Unsupported(format!("non-local URI '{}'", uri)));
+        }
+        let base_dir = self.base_dir.ok_or_else(|| LoadError::Unsupported(format!("external URI '{}' without a base directory", uri)))?;
+        Ok(fs::read(base_dir.join(percent_decode(uri)))?)
+    }
+
+    fn buffer_view(&self, index: usize) -> Result<(&'a Json, &[u8]), LoadError> {
+        let view = self.doc.get("bufferViews").at(index);
+        let buffer = view.get("buffer").as_usize().and_then(|b| self.buffers.get(b)).ok_or(LoadError::BadIndex { line: 0, index: index as i64 })?;
+        let offset = view.get("byteOffset").as_usize().unwrap_or(0);
+        let length = view.get("byteLength").as_usize().ok_or_else(|| LoadError::parse(0, format!("bufferView {} has no byteLength", index)))?;
+        let bytes = buffer.get(offset..offset + length).ok_or(LoadError::Truncated { expected: offset + length, found: buffer.len() })?;
+        Ok((view, bytes))
+    }
+
+    // Reads an accessor as floats, returning them flattened along with the component count per element
+    fn read_accessor(&self, index: usize) -> Result<(Vec<f64>, usize), LoadError> {
+        let accessor = self.doc.get("accessors").at(index);
+        if accessor.is_null() {
+            return Err(LoadError::BadIndex { line: 0, index: index as i64 });
+        }
+        if !accessor.get("sparse").is_null() {
+            return Err(LoadError::Unsupported("sparse accessors".to_string()));
+        }
+        let count = accessor.get("count").as_usize().ok_or_else(|| LoadError::parse(0, format!("accessor {} has no count", index)))?;
+        let components = match accessor.get("type").as_str() {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") | Some("MAT2") => 4,
+            Some("MAT3") => 9,
+            Some("MAT4") => 16,
+            other => return Err(LoadError::parse(0, format!("accessor {} has unknown type {:?}", index, other))),
+        };
+        let component_type = accessor.get("componentType").as_usize().unwrap_or(0);
+        let size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            other => return Err(LoadError::parse(0, format!("accessor {} has unknown componentType {}", index, other))),
+        };
+        let normalized = accessor.get("normalized").as_bool().unwrap_or(false);
+
+        let view_index = match accessor.get("bufferView").as_usize() {
+            Some(v) => v,
+            // Accessors without a view are all zeros, so nothing in the file bounds their count
+            None if count <= MAX_UNBACKED_ELEMENTS => return Ok((vec![0.0; count*components], components)),
+            None => return Err(LoadError::Unsupported(format!("accessor {} with {} elements and no bufferView", index, count))),
+        };
+        let (view, bytes) = self.buffer_view(view_index)?;
+        let offset = accessor.get("byteOffset").as_usize().unwrap_or(0);
+        let element = size*components;
+        let stride = view.get("byteStride").as_usize().unwrap_or(element);
+        if stride < element {
+            return Err(LoadError::parse(0, format!("accessor {} has a byteStride smaller than its elements", index)));
+        }
+        // The last element has to end inside the view, which is checked before anything is allocated
+        if count > 0 {
+            let end = (count - 1).checked_mul(stride).and_then(|n| n.checked_add(offset)).and_then(|n| n.checked_add(element)).unwrap_or(usize::MAX);
+            if end > bytes.len() {
+                return Err(LoadError::Truncated { expected: end, found: bytes.len() });
+            }
+        }
+
+        let mut out = vec![0.0; count*components];
+        for i in 0..count {
+            for c in 0..components {
+                let at = offset + i*stride + c*size;
+                let b = &bytes[at..at + size];
+                out[i*components + c] = match (component_type, normalized) {
+                    (5120, false) => b[0] as i8 as f64,
+                    (5120, true) => (b[0] as i8 as f64 / 127.0).max(-1.0),
+                    (5121, false) => b[0] as f64,
+                    (5121, true) => b[0] as f64 / 255.0,
+                    (5122, false) => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    (5122, true) => (i16::from_le_bytes([b[0], b[1]]) as f64 / 32767.0).max(-1.0),
+                    (5123, false) => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    (5123, true) => u16::from_le_bytes([b[0], b[1]]) as f64 / 65535.0,
+                    (5125, _) => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                };
+            }
+        }
+        Ok((out, components))
+    }
+
+    fn image(&mut self, index: usize) -> Result<Arc<Texture>, LoadError> {
+        if let Some(texture) = self.images.get(&index) {
+            return Ok(texture.clone());
+        }
+        let image = self.doc.get("images").at(index);
+        let bytes = if let Some(uri) = image.get("uri").as_str() {
+            self.read_uri(uri)?
+        }
+        else if let Some(view) = image.get("bufferView").as_usize() {
+            self.buffer_view(view)?.1.to_vec()
+        }
+        else {
+            return Err(LoadError::parse(0, format!("image {} has neither uri nor bufferView", index)));
+        };
+        let decoded = image::load_from_memory(&bytes).map_err(|e| LoadError::Unsupported(format!("image {}: {}", index, e)))?.to_rgba8();
+        let pixels = decoded.pixels().map(|p| Color32::from_rgba_unmultiplied(p[0], p[1], p[2], p[3])).collect();
+        let texture = Arc::new(Texture::new(decoded.width() as usize, decoded.height() as usize, pixels));
+        self.images.insert(index, texture.clone());
+        Ok(texture)
+    }
+
+    fn material(&mut self, index: usize) -> Result<Material, LoadError> {
+        let json = self.doc.get("materials").at(index);
+        let mut material = Material::new(json.get("name").as_str().unwrap_or("").to_string());
+        let pbr = json.get("pbrMetallicRoughness");
+        for (i, c) in pbr.get("baseColorFactor").as_array().iter().take(4).enumerate() {
+            material.base_color[i] = c.as_f64().unwrap_or(1.0);
+        }
+        if let Some(texture) = pbr.get("baseColorTexture").get("index").as_usize() {
+            if let Some(source) = self.doc.get("textures").at(texture).get("source").as_usize() {
+                material.base_color_texture = Some(self.image(source)?);
+            }
+        }
+        // MASK cut-outs aren't supported, so only BLEND materials are see-through
+        if json.get("alphaMode").as_str() == Some("BLEND") {
+            material.alpha_mode = AlphaMode::Blend;
+        }
+        Ok(material)
+    }
+
+    // Turns one primitive into a Mesh; returns None for point and line primitives
+    fn primitive(&self, primitive: &Json) -> Result<Option<Mesh>, LoadError> {
+        let mode = primitive.get("mode").as_usize().unwrap_or(4);
+        if !(4..=6).contains(&mode) {
+            return Ok(None);
+        }
+        let attributes = primitive.get("attributes");
+        let position = attributes.get("POSITION").as_usize().ok_or_else(|| LoadError::parse(0, "primitive has no POSITION attribute"))?;
+        let (positions, components) = self.read_accessor(position)?;
+        if components != 3 {
+            return Err(LoadError::parse(0, format!("POSITION has {} components, expected 3", components)));
+        }
+        let vertex_count = positions.len() / 3;
+
+        // Attributes with a different number of components than allowed are refused rather than misread
+        let optional = |name: &str, allowed: &[usize]| -> Result<Option<(Vec<f64>, usize)>, LoadError> {
+            match attributes.get(name).as_usize() {
+                Some(a) => {
+                    let (data, components) = self.read_accessor(a)?;
+                    if !allowed.contains(&components) {
+                        return Err(LoadError::parse(0, format!("{} has {} components, expected {:?}", name, components, allowed)));
+                    }
+                    if data.len() / components < vertex_count {
+                        return Err(LoadError::parse(0, format!("{} has fewer elements than POSITION", name)));
+                    }
+                    Ok(Some((data, components)))
+                }
+                None => Ok(None),
+            }
+        };
+        let normals = optional("NORMAL", &[3])?;
+        let uvs = optional("TEXCOORD_0", &[2])?;
+        let colors = optional("COLOR_0", &[3, 4])?;
+        let joints = optional("JOINTS_0", &[4])?;
+        let weights = optional("WEIGHTS_0", &[4])?;
+
+        let indices: Vec<usize> = match primitive.get("indices").as_usize() {
+            Some(a) => self.read_accessor(a)?.0.iter().map(|i| *i as usize).collect(),
+            None => (0..vertex_count).collect(),
+        };
+        if let Some(bad) = indices.iter().find(|i| **i >= vertex_count) {
+            return Err(LoadError::BadIndex { line: 0, index: *bad as i64 });
+        }
+
+        let indices: Vec<[usize; 3]> = match mode {
+            4 => indices.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+            // Strips flip every other triangle to keep the winding consistent
+            5 => (0..indices.len().saturating_sub(2)).map(|i| if i % 2 == 0 { [indices[i], indices[i + 1], indices[i + 2]] } else { [indices[i + 1], indices[i], indices[i + 2]] }).collect(),
+            _ => (1..indices.len().saturating_sub(1)).map(|i| [indices[0], indices[i], indices[i + 1]]).collect(),
+        };
+
+        let mut mesh = Mesh::new((0..vertex_count).map(|i| Vec3d::new(positions[i*3], positions[i*3 + 1], positions[i*3 + 2])).collect(), indices);
+        if let Some((n, k)) = &normals {
+            mesh.normals = (0..vertex_count).map(|i| Vec3d::new(n[i*k], n[i*k + 1], n[i*k + 2])).collect();
+        }
+        if let Some((t, k)) = &uvs {
+            mesh.uvs = (0..vertex_count).map(|i| Vec2d::new(t[i*k], t[i*k + 1])).collect();
+        }
+        // glTF vertex colors are linear, while Color32 holds sRGB; alpha is stored as it is
+        if let Some((col, k)) = &colors {
+            let channel = |x: f64| (x*255.0).round().clamp(0.0, 255.0) as u8;
+            mesh.colors = (0..vertex_count).map(|i| {
+                let alpha = if *k == 4 { channel(col[i*k + 3]) } else { 255 };
+                let [r, g, b, _] = color::to_srgb([col[i*k], col[i*k + 1], col[i*k + 2]]).to_array();
+                Color32::from_rgba_unmultiplied(r, g, b, alpha)
+            }).collect();
+        }
+        for (i, target) in primitive.get("targets").as_array().iter().enumerate() {
+            let deltas = |name: &str| -> Result<Vec<Vec3d>, LoadError> {
+                let a = match target.get(name).as_usize() {
+                    Some(a) => a,
+                    None => return Ok(Vec::new()),
+                };
+                let (d, k) = self.read_accessor(a)?;
+                if k != 3 || d.len() < vertex_count*3 {
+                    return Err(LoadError::parse(0, format!("morph target {} {} has fewer elements than POSITION", i, name)));
+                }
+                Ok((0..vertex_count).map(|v| Vec3d::new(d[v*3], d[v*3 + 1], d[v*3 + 2])).collect())
+            };
+            let mut morph = MorphTarget::new(format!("Target {}", i), deltas("POSITION")?);
+            morph.normals = deltas("NORMAL")?;
+            mesh.morph_targets.push(morph);
+        }
+        mesh.morph_weights = vec![0.0; mesh.morph_targets.len()];
+        if let (Some((j, _)), Some((w, _))) = (&joints, &weights) {
+            mesh.joints = (0..vertex_count).map(|i| [j[i*4] as usize, j[i*4 + 1] as usize, j[i*4 + 2] as usize, j[i*4 + 3] as usize]).collect();
+            mesh.weights = (0..vertex_count).map(|i| [w[i*4], w[i*4 + 1], w[i*4 + 2], w[i*4 + 3]]).collect();
+        }
+        Ok(Some(mesh))
+    }
+
+    fn build_scene(&mut self) -> Result<Scene, LoadError> {
+        let mut scene = Scene::new();
+
+        let material_count = self.doc.get("materials").as_array().len();
+        for i in 0..material_count {
+            let material = self.material(i)?;
+            scene.materials.push(material);
+        }
+
+        // Each glTF mesh becomes one engine mesh per drawable primitive
+        let mut mesh_map = Vec::new();
+        for mesh in self.doc.get("meshes").as_array() {
+            let mut indices = Vec::new();
+            for primitive in mesh.get("primitives").as_array() {
+                if let Some(mut m) = self.primitive(primitive)? {
+                    m.material = primitive.get("material").as_usize().filter(|i| *i < material_count);
+                    // Targets are named and weighted per mesh, and every primitive has the same targets
+                    for (target, name) in m.morph_targets.iter_mut().zip(mesh.get("extras").get("targetNames").as_array()) {
+                        if let Some(name) = name.as_str() {
+                            target.name = name.to_string();
+                        }
+                    }
+                    for (weight, default) in m.morph_weights.iter_mut().zip(mesh.get("weights").as_array()) {
+                        *weight = default.as_f64().unwrap_or(0.0);
+                    }
+                    scene.meshes.push(m);
+                    indices.push(scene.meshes.len() - 1);
+                }
+            }
+            mesh_map.push(indices);
+        }
+
+        let nodes = self.doc.get("nodes").as_array();
+        let mut parent = vec![None; nodes.len()];
+        for (i, json) in nodes.iter().enumerate() {
+            let mut node = Node::new(json.get("name").as_str().unwrap_or("").to_string());
+            node.transform = node_transform(json);
+            if let Some(m) = json.get("mesh").as_usize() {
+                node.meshes = mesh_map.get(m).cloned().ok_or(LoadError::BadIndex { line: 0, index: m as i64 })?;
+            }
+            let weights = json.get("weights").as_array();
+            if !weights.is_empty() {
+                for m in node.meshes.iter() {
+                    for (weight, value) in scene.meshes[*m].morph_weights.iter_mut().zip(weights) {
+                        *weight = value.as_f64().unwrap_or(0.0);
+                    }
+                }
+            }
+            if let Some(skin) = json.get("skin").as_usize() {
+                if skin >= self.doc.get("skins").as_array().len() {
+                    return Err(LoadError::BadIndex { line: 0, index: skin as i64 });
+                }
+                node.skin = Some(skin);
+            }
+            for c in json.get("children").as_array() {
+                let c = c.as_usize().filter(|c| *c < nodes.len()).ok_or_else(|| LoadError::parse(0, format!("node {} has an invalid child", i)))?;
+                // A node with two parents would make the hierarchy a graph, and possibly a cycle
+                if parent[c].replace(i).is_some() {
+                    return Err(LoadError::parse(0, format!("node {} has more than one parent", c)));
+                }
+                node.children.push(c);
+            }
+            scene.nodes.push(node);
+        }
+
+        let default_scene = self.doc.get("scene").as_usize().unwrap_or(0);
+        let listed = self.doc.get("scenes").at(default_scene).get("nodes");
+        scene.roots = if listed.is_null() {
+            (0..nodes.len()).filter(|i| parent[*i].is_none()).collect()
+        }
+        else {
+            listed.as_array().iter().filter_map(|n| n.as_usize()).collect()
+        };
+        if let Some(bad) = scene.roots.iter().find(|r| **r >= nodes.len() || parent[**r].is_some()) {
+            return Err(LoadError::parse(0, format!("scene root {} is not a root node", bad)));
+        }
+
+        // Files without nodes still get their meshes drawn
+        if nodes.is_empty() {
+            for i in 0..scene.meshes.len() {
+                let mut node = Node::new(String::new());
+                node.meshes.push(i);
+                scene.nodes.push(node);
+                scene.roots.push(i);
+            }
+        }
+
+        for (i, json) in self.doc.get("skins").as_array().iter().enumerate() {
+            let skin = self.skin(json, i, nodes.len())?;
+            scene.skins.push(skin);
+        }
+        let morph_counts: Vec<usize> = scene.nodes.iter().take(nodes.len()).map(|n| n.meshes.first().map_or(0, |m| scene.meshes[*m].morph_targets.len())).collect();
+        for (i, json) in self.doc.get("animations").as_array().iter().enumerate() {
+            let name = json.get("name").as_str().map(|n| n.to_string()).unwrap_or_else(|| format!("Animation {}", i));
+            let animation = self.animation(json, name, &morph_counts)?;
+            if !animation.channels.is_empty() {
+                scene.animations.push(animation);
+            }
+        }
+        Ok(scene)
+    }
+
+    fn skin(&self, json: &Json, index: usize, node_count: usize) -> Result<Skin, LoadError> {
+        let joints = json.get("joints").as_array().iter()
+            .map(|j| j.as_usize().filter(|j| *j < node_count).ok_or_else(|| LoadError::parse(0, format!("skin {} has an invalid joint", index))))
+            .collect::<Result<Vec<usize>, LoadError>>()?;
+        let mut skin = Skin::new(json.get("name").as_str().unwrap_or("").to_string(), joints);
+        // Without inverse bind matrices the joints were bound where they sit at the origin
+        if let Some(accessor) = json.get("inverseBindMatrices").as_usize() {
+            let (values, components) = self.read_accessor(accessor)?;
+            if components != 16 || values.len() < 16*skin.joints.len() {
+                return Err(LoadError::parse(0, format!("skin {} needs a MAT4 per joint", index)));
+            }
+            // Column-major for column vectors, the same layout as node matrices
+            for (j, inverse_bind) in skin.inverse_binds.iter_mut().enumerate() {
+                for (k, v) in values[j*16..(j + 1)*16].iter().enumerate() {
+                    inverse_bind.m[k / 4][k % 4] = *v;
+                }
+            }
+        }
+        Ok(skin)
+    }
+
+    // Reads an animation's node channels, given how many morph targets each node's meshes have
+    fn animation(&self, json: &Json, name: String, morph_counts: &[usize]) -> Result<Animation, LoadError> {
+        let node_count = morph_counts.len();
+        let samplers = json.get("samplers").as_array();
+        let mut animation = Animation::new(name);
+        for channel in json.get("channels").as_array() {
+            let target = channel.get("target");
+            let node = match target.get("node").as_usize() {
+                Some(n) if n < node_count => n,
+                Some(n) => return Err(LoadError::BadIndex { line: 0, index: n as i64 }),
+                // Channels without a node are for extensions to animate
+                None => continue,
+            };
+            // Weights key every morph target at once
+            let components = match target.get("path").as_str() {
+                Some("translation") | Some("scale") => 3,
+                Some("rotation") => 4,
+                Some("weights") if morph_counts[node] > 0 => morph_counts[node],
+                _ => continue,
+            };
+            let index = channel.get("sampler").as_usize().unwrap_or(usize::MAX);
+            let sampler = samplers.get(index).ok_or(LoadError::BadIndex { line: 0, index: index as i64 })?;
+
+            let interpolation = match sampler.get("interpolation").as_str().unwrap_or("LINEAR") {
+                "LINEAR" => Interpolation::Linear,
+                "STEP" => Interpolation::Step,
+                "CUBICSPLINE" => Interpolation::Cubic,
+                other => return Err(LoadError::Unsupported(format!("animation interpolation '{}'", other))),
+            };
+            let input = sampler.get("input").as_usize().ok_or_else(|| LoadError::parse(0, "animation sampler has no input"))?;
+            let output = sampler.get("output").as_usize().ok_or_else(|| LoadError::parse(0, "animation sampler has no output"))?;
+            let (times, _) = self.read_accessor(input)?;
+            let (values, _) = self.read_accessor(output)?;
+            if times.windows(2).any(|w| w[1] < w[0]) {
+                return Err(LoadError::parse(0, "animation sampler input is not increasing"));
+            }
+            // Cubic splines store an in-tangent, value and out-tangent for every key
+            let per_key = if interpolation == Interpolation::Cubic { 3 } else { 1 };
+            if values.len() != times.len()*per_key*components {
+                return Err(LoadError::parse(0, format!("animation sampler {} has {} output values for {} keys", index, values.len(), times.len())));
+            }
+
+            let property = match target.get("path").as_str() {
+                Some("translation") => Property::Translation(keyed_track::<3>(times, &values, interpolation)),
+                Some("scale") => Property::Scale(keyed_track::<3>(times, &values, interpolation)),
+                Some("rotation") => Property::Rotation(keyed_track::<4>(times, &values, interpolation)),
+                _ => {
+                    // One single component track per target, picked out of the interleaved weights
+                    for k in 0..components {
+                        let weights: Vec<f64> = values.iter().skip(k).step_by(components).cloned().collect();
+                        let property = Property::Weight(k, keyed_track::<1>(times.clone(), &weights, interpolation));
+                        animation.channels.push(Channel { target: Target::Node(node), property });
+                    }
+                    continue;
+                }
+            };
+            animation.channels.push(Channel { target: Target::Node(node), property });
+        }
+        Ok(animation)
+    }
+}
+
+// Groups flat sampler output into keys, splitting out cubic spline tangents
+fn keyed_track<const N: usize>(times: Vec<f64>, values: &[f64], interpolation: Interpolation) -> Track<N> {
+    let element = |i: usize| -> [f64; N] { std::array::from_fn(|k| values[i*N + k]) };
+    if interpolation != Interpolation::Cubic {
+        let keys = (0..times.len()).map(element).collect();
+        return Track::new(times, keys, interpolation);
+    }
+    let keys = (0..times.len()).map(|i| element(3*i + 1)).collect();
+    let mut track = Track::new(times, keys, interpolation);
+    track.tangents = (0..track.times.len()).map(|i| (element(3*i), element(3*i + 2))).collect();
+    track
+}
+
+// Local transform of a node, from either its matrix or its translation/rotation/scale
+fn node_transform(json: &Json) -> Matrix4x4 {
+    let values: Vec<f64> = json.get("matrix").as_array().iter().filter_map(|v| v.as_f64()).collect();
+    if values.len() == 16 {
+        // glTF matrices are column-major for column vectors, which is exactly row-major for our row vectors
+        let mut matrix = Matrix4x4::identity();
+        for (i, v) in values.iter().enumerate() {
+            matrix.m[i / 4][i % 4] = *v;
+        }
+        return matrix;
+    }
+
+    let read = |key: &str, default: &[f64]| -> Vec<f64> {
+        let v: Vec<f64> = json.get(key).as_array().iter().filter_map(|v| v.as_f64()).collect();
+        if v.len() == default.len() { v } else { default.to_vec() }
+    };
+    let t = read("translation", &[0.0, 0.0, 0.0]);
+    let r = read("rotation", &[0.0, 0.0, 0.0, 1.0]);
+    let s = read("scale", &[1.0, 1.0, 1.0]);
+    Trs { translation: [t[0], t[1], t[2]], rotation: [r[0], r[1], r[2], r[3]], scale: [s[0], s[1], s[2]] }.to_matrix()
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, LoadError> {
+    let mut out = Vec::with_capacity(text.len()*3/4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b'\r' | b'\n' | b' ' => continue,
+            _ => return Err(LoadError::parse(0, "invalid base64 in data URI")),
+        };
+        acc = ((acc << 6) | value as u32) & 0xFFFFFF;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(b) = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
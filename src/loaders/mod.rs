@@ -0,0 +1,61 @@
+use std::fmt;
+use std::io;
+
+pub mod cube;
+pub mod gltf;
+pub mod json;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+// Errors produced while reading a model file
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    // File ended before the data its header promised
+    Truncated { expected: usize, found: usize },
+    // Malformed content; line is 1-based for text formats and 0 for binary ones
+    Parse { line: usize, message: String },
+    // Index refers to an element that does not exist
+    BadIndex { line: usize, index: i64 },
+    Unsupported(String),
+}
+
+impl LoadError {
+    pub fn parse(line: usize, message: impl Into<String>) -> Self {
+        LoadError::Parse { line, message: message.into() }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "io error: {}", e),
+            LoadError::Truncated { expected, found } => write!(f, "file truncated: expected {} bytes, found {}", expected, found),
+            LoadError::Parse { line, message } => write!(f, "parse error on line {}: {}", line, message),
+            LoadError::BadIndex { line, index } => write!(f, "index {} out of range on line {}", index, line),
+            LoadError::Unsupported(what) => write!(f, "unsupported: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+// Parses a whitespace separated token as a float, reporting the line on failure
+pub(crate) fn parse_f64(token: Option<&str>, line: usize) -> Result<f64, LoadError> {
+    let token = token.ok_or_else(|| LoadError::parse(line, "missing number"))?;
+    token.parse::<f64>().map_err(|_| LoadError::parse(line, format!("invalid number '{}'", token)))
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use eframe::egui::Color32;
+
+use crate::loaders::{ parse_f64, LoadError };
+use crate::objs::{ Mesh, Vec2d, Vec3d };
+
+// Position, texture coordinate and normal indices of one face corner
+type Corner = (usize, Option<usize>, Option<usize>);
+
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Mesh, LoadError> {
+    let file = File::open(path)?;
+    parse_obj(BufReader::new(file))
+}
+
+pub fn parse_obj<R: BufRead>(mut reader: R) -> Result<Mesh, LoadError> {
+    let mut vec_cache = Vec::with_capacity(0);
+    let mut color_cache = Vec::with_capacity(0);
+    let mut uv_cache = Vec::with_capacity(0);
+    let mut normal_cache = Vec::with_capacity(0);
+    let mut sample: Vec<Vec<Corner>> = Vec::with_capacity(0);
+
+    let mut line = String::new();
+    let mut line_no = 0;
+    loop {
+        let bytes_read = reader.read_line(&mut line)?;
+        if bytes_read == 0 {
+            break;
+        }
+        line_no += 1;
+        let mut trimmed = line.split_whitespace();
+        match trimmed.next() {
+            Some("v") => {
+                vec_cache.push(Vec3d::new(parse_f64(trimmed.next(), line_no)?, parse_f64(trimmed.next(), line_no)?, parse_f64(trimmed.next(), line_no)?));
+                // Some exporters append an RGB triple in [0, 1] to each position
+                let rest: Vec<&str> = trimmed.collect();
+                if rest.len() >= 3 {
+                    let channel = |s: &str| parse_f64(Some(s), line_no).map(|c| (c*255.0).round().clamp(0.0, 255.0) as u8);
+                    color_cache.resize(vec_cache.len() - 1, Color32::WHITE);
+                    color_cache.push(Color32::from_rgb(channel(rest[0])?, channel(rest[1])?, channel(rest[2])?));
+                }
+            }
+            Some("vt") => {
+                let u = parse_f64(trimmed.next(), line_no)?;
+                let v = trimmed.next().map(|v| parse_f64(Some(v), line_no)).transpose()?.unwrap_or(0.0);
+                uv_cache.push(Vec2d::new(u, v));
+            }
+            Some("vn") => {
+                normal_cache.push(Vec3d::new(parse_f64(trimmed.next(), line_no)?, parse_f64(trimmed.next(), line_no)?, parse_f64(trimmed.next(), line_no)?));
+            }
+            Some("f") => {
+                // Faces can have any number of corners; they are triangulated once everything is read
+                let counts = [vec_cache.len(), uv_cache.len(), normal_cache.len()];
+                let mut face = Vec::with_capacity(4);
+                for token in trimmed {
+                    face.push(resolve_corner(token, counts, line_no)?);
+                }
+                if face.len() < 3 {
+                    return Err(LoadError::parse(line_no, "face has fewer than 3 vertices"));
+                }
+                sample.push(face);
+            }
+            _ => {}
+        }
+        line.clear();
+    }
+    if !color_cache.is_empty() {
+        color_cache.resize(vec_cache.len(), Color32::WHITE);
+    }
+
+    let has_uvs = sample.iter().flatten().any(|c| c.1.is_some());
+    let has_normals = sample.iter().flatten().any(|c| c.2.is_some());
+
+    // When every corner reuses its position index for the other attributes the file's
+    // vertex order can be kept, otherwise each distinct corner becomes its own vertex
+    let shared = sample.iter().flatten().all(|(v, t, n)| t.map_or(!has_uvs, |t| t == *v) && n.map_or(!has_normals, |n| n == *v));
+    if shared {
+        let mut mesh = Mesh::new(vec_cache, Vec::new());
+        mesh.set_polygons(sample.iter().map(|f| f.iter().map(|c| c.0).collect()).collect());
+        mesh.colors = color_cache;
+        if has_uvs {
+            uv_cache.resize(mesh.positions.len(), Vec2d::new(0.0, 0.0));
+            mesh.uvs = uv_cache;
+        }
+        if has_normals {
+            normal_cache.resize(mesh.positions.len(), Vec3d::new(0.0, 0.0, 0.0));
+            mesh.normals = normal_cache;
+        }
+        return Ok(mesh);
+    }
+
+    let mut mesh = Mesh::new(Vec::new(), Vec::new());
+    let mut lookup: HashMap<Corner, usize> = HashMap::new();
+    let mut polygons = Vec::with_capacity(sample.len());
+    for face in sample.iter() {
+        let mut idx = vec![0; face.len()];
+        for (slot, corner) in idx.iter_mut().zip(face.iter()) {
+            *slot = *lookup.entry(*corner).or_insert_with(|| {
+                let (v, t, n) = *corner;
+                mesh.positions.push(vec_cache[v]);
+                if !color_cache.is_empty() { mesh.colors.push(color_cache[v]); }
+                if has_uvs { mesh.uvs.push(t.map(|t| uv_cache[t]).unwrap_or(Vec2d::new(0.0, 0.0))); }
+                if has_normals { mesh.normals.push(n.map(|n| normal_cache[n]).unwrap_or(Vec3d::new(0.0, 0.0, 0.0))); }
+                mesh.positions.len() - 1
+            });
+        }
+        polygons.push(idx);
+    }
+    mesh.set_polygons(polygons);
+    Ok(mesh)
+}
+
+// Splits a face token ("7", "7/2", "7//3" or "7/2/3") into zero-based indices for the
+// position, texture coordinate and normal lists whose current lengths are in counts
+fn resolve_corner(token: &str, counts: [usize; 3], line_no: usize) -> Result<Corner, LoadError> {
+    let mut parts = token.split('/');
+    let v = resolve_index(parts.next(), counts[0], token, line_no)?
+        .ok_or_else(|| LoadError::parse(line_no, format!("invalid face index '{}'", token)))?;
+    let t = resolve_index(parts.next(), counts[1], token, line_no)?;
+    let n = resolve_index(parts.next(), counts[2], token, line_no)?;
+    Ok((v, t, n))
+}
+
+// Resolves one slash-separated part, where negative indices count back from the end of the list
+fn resolve_index(part: Option<&str>, count: usize, token: &str, line_no: usize) -> Result<Option<usize>, LoadError> {
+    let part = match part {
+        Some(p) if !p.is_empty() => p,
+        _ => return Ok(None),
+    };
+    let index = part.parse::<i64>()
+        .map_err(|_| LoadError::parse(line_no, format!("invalid face index '{}'", token)))?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(LoadError::BadIndex { line: line_no, index });
+    }
+    Ok(Some(resolved as usize))
+}
@@ -0,0 +1,291 @@
+use std::fs;
+use std::path::Path;
+
+use eframe::egui::Color32;
+
+use crate::loaders::LoadError;
+use crate::objs::{ Mesh, Vec2d, Vec3d };
+
+#[derive(Copy, Clone, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum ScalarType {
+    I8, U8, I16, U16, I32, U32, F32, F64,
+}
+
+impl ScalarType {
+    fn from_name(name: &str, line: usize) -> Result<Self, LoadError> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::I8,
+            "uchar" | "uint8" => ScalarType::U8,
+            "short" | "int16" => ScalarType::I16,
+            "ushort" | "uint16" => ScalarType::U16,
+            "int" | "int32" => ScalarType::I32,
+            "uint" | "uint32" => ScalarType::U32,
+            "float" | "float32" => ScalarType::F32,
+            "double" | "float64" => ScalarType::F64,
+            other => return Err(LoadError::parse(line, format!("unknown property type '{}'", other))),
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+
+    fn is_float(&self) -> bool {
+        matches!(self, ScalarType::F32 | ScalarType::F64)
+    }
+}
+
+struct Property {
+    name: String,
+    ty: ScalarType,
+    // Type of the length prefix for list properties
+    count_ty: Option<ScalarType>,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    props: Vec<Property>,
+}
+
+// Pulls scalar values out of the body in whichever encoding the header declared
+struct BodyReader<'a> {
+    format: Format,
+    data: &'a [u8],
+    pos: usize,
+    // Line number of the current ascii token, for error messages
+    line: usize,
+    tokens: Vec<&'a str>,
+    token_pos: usize,
+}
+
+impl<'a> BodyReader<'a> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, LoadError> {
+        if self.format == Format::Ascii {
+            return self.read_ascii();
+        }
+        let size = ty.size();
+        if self.pos + size > self.data.len() {
+            return Err(LoadError::Truncated { expected: self.pos + size, found: self.data.len() });
+        }
+        let mut b = [0u8; 8];
+        b[..size].copy_from_slice(&self.data[self.pos..self.pos + size]);
+        if self.format == Format::BinaryBigEndian {
+            b[..size].reverse();
+        }
+        self.pos += size;
+        Ok(match ty {
+            ScalarType::I8 => b[0] as i8 as f64,
+            ScalarType::U8 => b[0] as f64,
+            ScalarType::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            ScalarType::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            ScalarType::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            ScalarType::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            ScalarType::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            ScalarType::F64 => f64::from_le_bytes(b),
+        })
+    }
+
+    // Ascii bodies are one element per line, so tokens are consumed a line at a time
+    fn read_ascii(&mut self) -> Result<f64, LoadError> {
+        while self.token_pos >= self.tokens.len() {
+            if self.pos >= self.data.len() {
+                return Err(LoadError::parse(self.line, "unexpected end of file"));
+            }
+            let end = self.data[self.pos..].iter().position(|b| *b == b'\n').map(|i| self.pos + i).unwrap_or(self.data.len());
+            let text = std::str::from_utf8(&self.data[self.pos..end]).map_err(|_| LoadError::parse(self.line + 1, "not valid UTF-8"))?;
+            self.tokens = text.split_whitespace().collect();
+            self.token_pos = 0;
+            self.pos = end + 1;
+            self.line += 1;
+        }
+        let token = self.tokens[self.token_pos];
+        self.token_pos += 1;
+        token.parse::<f64>().map_err(|_| LoadError::parse(self.line, format!("invalid number '{}'", token)))
+    }
+}
+
+pub fn load_ply<P: AsRef<Path>>(path: P) -> Result<Mesh, LoadError> {
+    let data = fs::read(path)?;
+    parse_ply(&data)
+}
+
+pub fn parse_ply(data: &[u8]) -> Result<Mesh, LoadError> {
+    let (format, elements, body_start, header_lines) = parse_header(data)?;
+
+    let mut reader = BodyReader { format, data, pos: body_start, line: header_lines, tokens: Vec::new(), token_pos: 0 };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut colors = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+
+    for element in elements.iter() {
+        match element.name.as_str() {
+            "vertex" => {
+                let find = |name: &str| element.props.iter().position(|p| p.name == name);
+                let pos_idx = [find("x"), find("y"), find("z")];
+                let norm_idx = [find("nx"), find("ny"), find("nz")];
+                let col_idx = [find("red").or(find("r")), find("green").or(find("g")), find("blue").or(find("b")), find("alpha").or(find("a"))];
+                let uv_idx = [find("u").or(find("s")).or(find("texture_u")), find("v").or(find("t")).or(find("texture_v"))];
+                if pos_idx.iter().any(|i| i.is_none()) {
+                    return Err(LoadError::parse(0, "vertex element is missing x, y or z"));
+                }
+                let has_normals = norm_idx.iter().all(|i| i.is_some());
+                let has_colors = col_idx[..3].iter().all(|i| i.is_some());
+                let has_uvs = uv_idx.iter().all(|i| i.is_some());
+
+                let mut values = vec![0.0; element.props.len()];
+                for _ in 0..element.count {
+                    for (k, prop) in element.props.iter().enumerate() {
+                        values[k] = read_property(&mut reader, prop)?.first().cloned().unwrap_or(0.0);
+                    }
+                    let get = |i: Option<usize>| i.map(|i| values[i]).unwrap_or(0.0);
+                    positions.push(Vec3d::new(get(pos_idx[0]), get(pos_idx[1]), get(pos_idx[2])));
+                    if has_normals {
+                        normals.push(Vec3d::new(get(norm_idx[0]), get(norm_idx[1]), get(norm_idx[2])));
+                    }
+                    if has_colors {
+                        let channel = |i: Option<usize>| match i {
+                            Some(i) if element.props[i].ty.is_float() => (values[i]*255.0).round().clamp(0.0, 255.0) as u8,
+                            Some(i) => values[i].clamp(0.0, 255.0) as u8,
+                            None => 255,
+                        };
+                        colors.push(Color32::from_rgba_unmultiplied(channel(col_idx[0]), channel(col_idx[1]), channel(col_idx[2]), channel(col_idx[3])));
+                    }
+                    if has_uvs {
+                        uvs.push(Vec2d::new(get(uv_idx[0]), get(uv_idx[1])));
+                    }
+                }
+            }
+            "face" => {
+                let list = element.props.iter().position(|p| p.name == "vertex_indices" || p.name == "vertex_index");
+                for _ in 0..element.count {
+                    for (k, prop) in element.props.iter().enumerate() {
+                        let values = read_property(&mut reader, prop)?;
+                        if Some(k) != list {
+                            continue;
+                        }
+                        let mut idx = Vec::with_capacity(values.len());
+                        for v in values {
+                            // Float typed lists could hold anything, and casting would take NaN to vertex 0
+                            if !v.is_finite() || v.fract() != 0.0 {
+                                return Err(LoadError::parse(reader.line, format!("vertex index {} is not a whole number", v)));
+                            }
+                            if v < 0.0 || v as usize >= positions.len() {
+                                return Err(LoadError::BadIndex { line: reader.line, index: v as i64 });
+                            }
+                            idx.push(v as usize);
+                        }
+                        // Polygons are split into a fan around their first vertex
+                        for j in 1..idx.len().saturating_sub(1) {
+                            indices.push([idx[0], idx[j], idx[j + 1]]);
+                        }
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    for prop in element.props.iter() {
+                        read_property(&mut reader, prop)?;
+                    }
+                }
+            }
+        }
+    }
+    let mut mesh = Mesh::new(positions, indices);
+    mesh.normals = normals;
+    mesh.colors = colors;
+    mesh.uvs = uvs;
+    Ok(mesh)
+}
+
+fn read_property(reader: &mut BodyReader, prop: &Property) -> Result<Vec<f64>, LoadError> {
+    match prop.count_ty {
+        Some(count_ty) => {
+            let count = reader.read(count_ty)?;
+            if count < 0.0 {
+                return Err(LoadError::parse(reader.line, "negative list length"));
+            }
+            if !count.is_finite() || count.fract() != 0.0 {
+                return Err(LoadError::parse(reader.line, format!("list length {} is not a whole number", count)));
+            }
+            (0..count as usize).map(|_| reader.read(prop.ty)).collect()
+        }
+        None => Ok(vec![reader.read(prop.ty)?]),
+    }
+}
+
+// Returns the body format, the declared elements, the byte offset of the body and the header length in lines
+fn parse_header(data: &[u8]) -> Result<(Format, Vec<Element>, usize, usize), LoadError> {
+    let mut pos = 0;
+    let mut line_no = 0;
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    loop {
+        let end = match data[pos..].iter().position(|b| *b == b'\n') {
+            Some(i) => pos + i,
+            None => return Err(LoadError::parse(line_no + 1, "header is missing end_header")),
+        };
+        let line = std::str::from_utf8(&data[pos..end]).map_err(|_| LoadError::parse(line_no + 1, "header is not valid UTF-8"))?;
+        pos = end + 1;
+        line_no += 1;
+
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next();
+        if line_no == 1 {
+            if keyword != Some("ply") {
+                return Err(LoadError::parse(1, "missing 'ply' magic"));
+            }
+            continue;
+        }
+        match keyword {
+            Some("format") => {
+                format = Some(match tokens.next() {
+                    Some("ascii") => Format::Ascii,
+                    Some("binary_little_endian") => Format::BinaryLittleEndian,
+                    Some("binary_big_endian") => Format::BinaryBigEndian,
+                    other => return Err(LoadError::Unsupported(format!("ply format {:?}", other.unwrap_or("")))),
+                });
+            }
+            Some("element") => {
+                let name = tokens.next().ok_or_else(|| LoadError::parse(line_no, "element without a name"))?;
+                let count = tokens.next().and_then(|c| c.parse::<usize>().ok()).ok_or_else(|| LoadError::parse(line_no, "element without a valid count"))?;
+                elements.push(Element { name: name.to_string(), count, props: Vec::new() });
+            }
+            Some("property") => {
+                let element = elements.last_mut().ok_or_else(|| LoadError::parse(line_no, "property before any element"))?;
+                let ty = tokens.next().ok_or_else(|| LoadError::parse(line_no, "property without a type"))?;
+                let prop = if ty == "list" {
+                    let count_ty = ScalarType::from_name(tokens.next().unwrap_or(""), line_no)?;
+                    let item_ty = ScalarType::from_name(tokens.next().unwrap_or(""), line_no)?;
+                    Property { name: tokens.next().unwrap_or("").to_string(), ty: item_ty, count_ty: Some(count_ty) }
+                }
+                else {
+                    Property { name: tokens.next().unwrap_or("").to_string(), ty: ScalarType::from_name(ty, line_no)?, count_ty: None }
+                };
+                element.props.push(prop);
+            }
+            Some("end_header") => break,
+            Some("comment") | Some("obj_info") | None => {}
+            Some(other) => return Err(LoadError::parse(line_no, format!("unexpected header keyword '{}'", other))),
+        }
+    }
+
+    let format = format.ok_or_else(|| LoadError::parse(line_no, "header has no format line"))?;
+    Ok((format, elements, pos, line_no))
+}
@@ -0,0 +1,129 @@
+use std::fs;
+use std::path::Path;
+
+use crate::loaders::{ parse_f64, LoadError };
+use crate::objs::{ Mesh, Tri, Vec3d };
+
+const HEADER_LEN: usize = 80;
+const FACET_LEN: usize = 50;
+
+pub fn load_stl<P: AsRef<Path>>(path: P) -> Result<Mesh, LoadError> {
+    let data = fs::read(path)?;
+    parse_stl(&data)
+}
+
+// Binary files may also begin with "solid", so the size implied by the facet count
+// is checked first and the text parser is only used when it doesn't match
+pub fn parse_stl(data: &[u8]) -> Result<Mesh, LoadError> {
+    if data.len() >= HEADER_LEN + 4 {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if count.checked_mul(FACET_LEN).and_then(|n| n.checked_add(HEADER_LEN + 4)) == Some(data.len()) {
+            return parse_binary(data);
+        }
+    }
+    if is_ascii_stl(data) {
+        return parse_ascii(data);
+    }
+    parse_binary(data)
+}
+
+fn is_ascii_stl(data: &[u8]) -> bool {
+    let start = data.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(data.len());
+    data[start..].starts_with(b"solid") && std::str::from_utf8(data).is_ok()
+}
+
+fn parse_binary(data: &[u8]) -> Result<Mesh, LoadError> {
+    if data.len() < HEADER_LEN + 4 {
+        return Err(LoadError::Truncated { expected: HEADER_LEN + 4, found: data.len() });
+    }
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    let expected = HEADER_LEN + 4 + count*FACET_LEN;
+    if data.len() < expected {
+        return Err(LoadError::Truncated { expected, found: data.len() });
+    }
+
+    let read_vec = |offset: usize| {
+        let f = |o: usize| f32::from_le_bytes([data[o], data[o+1], data[o+2], data[o+3]]) as f64;
+        Vec3d::new(f(offset), f(offset + 4), f(offset + 8))
+    };
+
+    let mut tris = Vec::with_capacity(count);
+    for i in 0..count {
+        let offset = HEADER_LEN + 4 + i*FACET_LEN;
+        let normal = read_vec(offset);
+        let p = vec![read_vec(offset + 12), read_vec(offset + 24), read_vec(offset + 36)];
+        tris.push(facet(p, normal));
+    }
+    Ok(Mesh::from_tris(&tris))
+}
+
+fn parse_ascii(data: &[u8]) -> Result<Mesh, LoadError> {
+    let text = std::str::from_utf8(data).map_err(|_| LoadError::parse(0, "not valid UTF-8"))?;
+
+    let mut tris = Vec::new();
+    let mut normal = None;
+    let mut verts = Vec::with_capacity(3);
+    let mut in_solid = false;
+
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) => k,
+            None => continue,
+        };
+        match keyword {
+            "solid" => in_solid = true,
+            "endsolid" => in_solid = false,
+            "facet" => {
+                if !in_solid {
+                    return Err(LoadError::parse(line_no, "facet outside of solid"));
+                }
+                if normal.is_some() {
+                    return Err(LoadError::parse(line_no, "facet before previous endfacet"));
+                }
+                if tokens.next() != Some("normal") {
+                    return Err(LoadError::parse(line_no, "expected 'facet normal'"));
+                }
+                normal = Some(Vec3d::new(parse_f64(tokens.next(), line_no)?, parse_f64(tokens.next(), line_no)?, parse_f64(tokens.next(), line_no)?));
+                verts.clear();
+            }
+            "outer" | "endloop" => {}
+            "vertex" => {
+                if normal.is_none() {
+                    return Err(LoadError::parse(line_no, "vertex outside of facet"));
+                }
+                if verts.len() == 3 {
+                    return Err(LoadError::parse(line_no, "facet has more than 3 vertices"));
+                }
+                verts.push(Vec3d::new(parse_f64(tokens.next(), line_no)?, parse_f64(tokens.next(), line_no)?, parse_f64(tokens.next(), line_no)?));
+            }
+            "endfacet" => {
+                let n = normal.take().ok_or_else(|| LoadError::parse(line_no, "endfacet without facet"))?;
+                if verts.len() != 3 {
+                    return Err(LoadError::parse(line_no, format!("facet has {} vertices, expected 3", verts.len())));
+                }
+                tris.push(facet(verts.clone(), n));
+            }
+            other => return Err(LoadError::parse(line_no, format!("unexpected keyword '{}'", other))),
+        }
+    }
+
+    if normal.is_some() {
+        return Err(LoadError::parse(text.lines().count(), "unterminated facet"));
+    }
+    Ok(Mesh::from_tris(&tris))
+}
+
+// Exporters often write a zero normal and leave it to the reader, so fall back to the winding
+fn facet(p: Vec<Vec3d>, normal: Vec3d) -> Tri {
+    let n = if normal.dot(&normal) > 0.0 {
+        let mut n = normal;
+        n.normalize();
+        n
+    }
+    else {
+        Tri::new(p.clone()).get_normal()
+    };
+    Tri::with_normals(p, vec![n; 3])
+}
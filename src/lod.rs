@@ -0,0 +1,86 @@
+use crate::bounds::BoundingSphere;
+use crate::objs::{ Matrix4x4, Mesh };
+use crate::scene::Scene;
+
+// Screen size below which the full mesh gives way to its first simplified level
+const FULL_DETAIL_SIZE: f64 = 0.5;
+
+// One version of a mesh and the smallest screen size it is drawn at
+pub struct LodLevel {
+    // Index into Scene::meshes
+    pub mesh: usize,
+    pub min_screen_size: f64,
+}
+
+// Simplified stand-ins for a scene mesh. Levels run from most to least detailed; the first the
+// mesh is big enough on screen for is drawn in its place, or the last if it is smaller than all.
+pub struct Lod {
+    // The full detail mesh the nodes refer to
+    pub mesh: usize,
+    pub levels: Vec<LodLevel>,
+}
+
+impl Lod {
+    pub fn select(&self, screen_size: f64) -> usize {
+        self.levels.iter().find(|l| screen_size >= l.min_screen_size).or(self.levels.last()).map_or(self.mesh, |l| l.mesh)
+    }
+}
+
+// How much of the viewport's height a view space sphere spans, 1 being all of it, for the
+// projection mat_proj. Spheres reaching the camera count as filling the view.
+pub fn screen_size(sphere: &BoundingSphere, mat_proj: &Matrix4x4) -> f64 {
+    if sphere.center.z <= sphere.radius {
+        return 1.0;
+    }
+    sphere.radius*mat_proj.m[1][1]/sphere.center.z
+}
+
+impl Scene {
+    // Gives the mesh levels simplified versions of itself, each with a quarter of the triangles of
+    // the one before and drawn once the mesh spans half as much of the screen, which keeps about
+    // as many triangles per pixel. Deformable meshes are left alone since only the meshes the
+    // nodes refer to get posed. Returns how many levels were added.
+    pub fn add_lods(&mut self, mesh: usize, levels: usize) -> usize {
+        if self.meshes[mesh].is_deformable() || self.lod(mesh).is_some() {
+            return 0;
+        }
+        let mut lod = Lod { mesh, levels: vec![LodLevel { mesh, min_screen_size: FULL_DETAIL_SIZE }] };
+        let mut size = FULL_DETAIL_SIZE;
+        let mut triangles = self.meshes[mesh].indices.len();
+        for _ in 0..levels {
+            triangles /= 4;
+            let source = &self.meshes[mesh];
+            let mut simplified = Mesh::new(source.positions.clone(), source.indices.clone());
+            simplified.normals.clone_from(&source.normals);
+            simplified.colors.clone_from(&source.colors);
+            simplified.uvs.clone_from(&source.uvs);
+            simplified.material = source.material;
+            simplified.render_mode = source.render_mode;
+            simplified.simplify(triangles);
+            // Stop once simplifying stops making a difference
+            if simplified.indices.len() >= self.meshes[lod.levels.last().unwrap().mesh].indices.len() {
+                break;
+            }
+            size /= 2.0;
+            self.meshes.push(simplified);
+            lod.levels.push(LodLevel { mesh: self.meshes.len() - 1, min_screen_size: size });
+        }
+        let added = lod.levels.len() - 1;
+        if added > 0 {
+            self.lods.push(lod);
+        }
+        added
+    }
+
+    pub fn lod(&self, mesh: usize) -> Option<&Lod> {
+        self.lods.iter().find(|l| l.mesh == mesh)
+    }
+
+    // Levels are drawn the way their full detail mesh is set to be
+    pub fn sync_lod_modes(&mut self) {
+        for lod in self.lods.iter() {
+            let mode = self.meshes[lod.mesh].render_mode;
+            lod.levels.iter().for_each(|l| self.meshes[l.mesh].render_mode = mode);
+        }
+    }
+}
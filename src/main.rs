@@ -2,8 +2,15 @@
 
 use eframe::egui;
 use std::sync::{ atomic::Ordering, Arc, Mutex };
-use r3de::objs::{ GUIState, DisplayBuffers };
+use r3de::objs::{ GUIState, DisplayBuffers, RenderMode };
 use r3de::engine::Engine;
+use r3de::fog::FogMode;
+use r3de::loaders::cube::load_cube;
+use r3de::color::ToneMapping;
+use r3de::framebuffer::{ AaMode, TransparencyMode };
+use r3de::post::{ LutPreset, PostChain, PostPass };
+use r3de::scene::AlphaMode;
+use r3de::subdivision::SubdivisionScheme;
 use std::time::Instant;
 
 fn main() -> Result<(), eframe::Error> {
@@ -22,10 +29,13 @@ fn main() -> Result<(), eframe::Error> {
 }
 
 struct R3DE {
-    // state: Arc<Mutex<GUIState>>, 
+    state: Arc<Mutex<GUIState>>,
     buffers: DisplayBuffers,
     time: Instant,
-    frames: f64,
+    // Smoothed UI repaint interval in seconds
+    ui_frame_time: f64,
+    // Path typed in for a .cube color lookup table to grade with
+    lut_path: String,
 }
 
 impl R3DE {
@@ -33,17 +43,18 @@ impl R3DE {
         let state = Arc::new(Mutex::new(GUIState::new()));
         state.lock().unwrap().ctx = Some(cc.egui_ctx.clone());
 
-        let buf_size = [700 as usize, 700 as usize];
+        let buf_size = [700_usize, 700_usize];
         let buffers = DisplayBuffers::new(buf_size);
         
         let mut engine = Engine::new(state.clone(), &buffers);
         std::thread::spawn(move ||{Engine::lo(&mut engine)});
 
         Self {
-            // state, 
+            state,
             buffers,
             time: Instant::now(),
-            frames: 0.0,
+            ui_frame_time: 0.0,
+            lut_path: String::new(),
         }
     }
 }
@@ -68,16 +79,333 @@ impl eframe::App for R3DE {
                 Default::default()
             );
             drop(buf_lock);
-            ui.add(egui::Image::new(&img));
-
-            let elapsed = self.time.elapsed().as_millis();
-            self.frames += 1.0;
-            ui.label(format!("FPS: {}", (self.frames/elapsed as f64)*(1000 as f64)));
-            if elapsed >1000 {
-                self.frames = 0.0;
-                self.time = Instant::now();
+            let response = ui.add(egui::Image::new(&img).sense(egui::Sense::click()));
+            // Clicks are mapped from the displayed image back to buffer pixels for picking
+            if let Some(pos) = response.interact_pointer_pos().filter(|_| response.clicked()) {
+                let rel = pos - response.rect.min;
+                let x = rel.x as f64*self.buffers.buf_size[0] as f64/response.rect.width() as f64;
+                let y = rel.y as f64*self.buffers.buf_size[1] as f64/response.rect.height() as f64;
+                self.state.lock().unwrap().pick_request = Some([x, y]);
             }
+        });
+
+        let dt = self.time.elapsed().as_secs_f64();
+        self.time = Instant::now();
+        self.ui_frame_time = if self.ui_frame_time == 0.0 { dt } else { 0.9*self.ui_frame_time + 0.1*dt };
+
+        let state_lock = self.state.lock().unwrap();
+        let stats = state_lock.stats;
+        let frame_times: Vec<f64> = state_lock.history.frame_times.iter().cloned().collect();
+        let avg_frame_time = state_lock.history.average();
+        let max_frame_time = state_lock.history.max();
+        drop(state_lock);
+
+        egui::Window::new("View")
+            .default_open(false)
+            .resizable(false)
+            .anchor(egui::Align2::LEFT_TOP, [10.0, 10.0])
+            .show(ctx, |ui| {
+                let mut state_lock = self.state.lock().unwrap();
+                if ui.button("Frame scene").clicked() {
+                    state_lock.frame_requested = true;
+                }
+                ui.checkbox(&mut state_lock.settings.use_bvh, "BVH triangle culling");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut state_lock.settings.lod, "Level of detail");
+                    if ui.button("Generate levels").clicked() {
+                        state_lock.lod_requested = true;
+                    }
+                });
+                egui::ComboBox::from_label("Anti-aliasing")
+                    .selected_text(state_lock.settings.aa.label())
+                    .show_ui(ui, |ui| {
+                        for mode in AaMode::ALL {
+                            ui.selectable_value(&mut state_lock.settings.aa, mode, mode.label());
+                        }
+                    });
+                egui::ComboBox::from_label("Tone mapping")
+                    .selected_text(state_lock.settings.tone_mapping.label())
+                    .show_ui(ui, |ui| {
+                        for mode in ToneMapping::ALL {
+                            ui.selectable_value(&mut state_lock.settings.tone_mapping, mode, mode.label());
+                        }
+                    });
+                ui.add(egui::Slider::new(&mut state_lock.settings.exposure, -4.0..=4.0).text("Exposure (stops)"));
+                egui::ComboBox::from_label("Transparency")
+                    .selected_text(state_lock.settings.transparency.label())
+                    .show_ui(ui, |ui| {
+                        for mode in TransparencyMode::ALL {
+                            ui.selectable_value(&mut state_lock.settings.transparency, mode, mode.label());
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut state_lock.settings.smooth_lines, "Anti-aliased lines");
+                    ui.color_edit_button_srgba(&mut state_lock.settings.line_color);
+                });
+                ui.add_enabled(state_lock.settings.smooth_lines, egui::Slider::new(&mut state_lock.settings.line_width, 0.5..=5.0).text("Line width"));
+                ui.add(egui::Slider::new(&mut state_lock.settings.point_size, 1.0..=10.0).text("Point size"));
+                let state = &mut *state_lock;
+                egui::CollapsingHeader::new("Mesh render modes").show(ui, |ui| {
+                    for (i, mode) in state.mesh_indices.iter().zip(state.mesh_modes.iter_mut()) {
+                        egui::ComboBox::from_label(format!("Mesh {}", i))
+                            .selected_text(mode.label())
+                            .show_ui(ui, |ui| {
+                                for m in RenderMode::ALL {
+                                    ui.selectable_value(mode, m, m.label());
+                                }
+                            });
+                    }
+                });
+                egui::CollapsingHeader::new("Fog").show(ui, |ui| {
+                    let fog = &mut state_lock.settings.fog;
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_label("Fog")
+                            .selected_text(fog.mode.label())
+                            .show_ui(ui, |ui| {
+                                for mode in FogMode::ALL {
+                                    ui.selectable_value(&mut fog.mode, mode, mode.label());
+                                }
+                            });
+                        ui.color_edit_button_srgba(&mut fog.color);
+                    });
+                    let linear = fog.mode == FogMode::Linear;
+                    let exponential = matches!(fog.mode, FogMode::Exponential | FogMode::ExponentialSquared);
+                    ui.add_enabled(linear, egui::Slider::new(&mut fog.start, 0.0..=50.0).text("Start"));
+                    ui.add_enabled(linear, egui::Slider::new(&mut fog.end, 0.0..=100.0).text("End"));
+                    ui.add_enabled(exponential, egui::Slider::new(&mut fog.density, 0.0..=0.5).text("Density"));
+                    ui.add_enabled(fog.mode != FogMode::Off, egui::Checkbox::new(&mut fog.per_vertex, "Per vertex"));
+                });
+                let state = &mut *state_lock;
+                egui::CollapsingHeader::new("Material opacity").show(ui, |ui| {
+                    for (i, ((mode, opacity), name)) in state.material_alpha.iter_mut().zip(state.material_names.iter()).enumerate() {
+                        let mut blend = *mode == AlphaMode::Blend;
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut blend, if name.is_empty() { format!("Material {}", i) } else { name.clone() });
+                            ui.add_enabled(blend, egui::Slider::new(opacity, 0.0..=1.0));
+                        });
+                        *mode = if blend { AlphaMode::Blend } else { AlphaMode::Opaque };
+                    }
+                });
+                egui::CollapsingHeader::new("Subdivision").show(ui, |ui| {
+                    egui::ComboBox::from_label("Scheme")
+                        .selected_text(state_lock.subdivision_scheme.label())
+                        .show_ui(ui, |ui| {
+                            for scheme in SubdivisionScheme::ALL {
+                                ui.selectable_value(&mut state_lock.subdivision_scheme, scheme, scheme.label());
+                            }
+                        });
+                    ui.add(egui::Slider::new(&mut state_lock.subdivision_levels, 1..=3).text("Levels"));
+                    if ui.button("Subdivide").clicked() {
+                        state_lock.subdivide_requested = true;
+                    }
+                });
+                if ui.button("Clean up meshes").clicked() {
+                    state_lock.clean_up_requested = true;
+                }
+                egui::CollapsingHeader::new("Overlays").show(ui, |ui| {
+                    let overlays = &mut state_lock.settings.overlays;
+                    ui.checkbox(&mut overlays.face_normals, "Face normals");
+                    ui.checkbox(&mut overlays.vertex_normals, "Vertex normals");
+                    ui.checkbox(&mut overlays.grid, "Grid");
+                    ui.checkbox(&mut overlays.axes, "Axes");
+                    ui.checkbox(&mut overlays.bounds, "Bounding boxes");
+                    ui.checkbox(&mut overlays.lights, "Lights");
+                });
+                ui.checkbox(&mut state_lock.settings.ground_plane, "Ground plane");
+                ui.checkbox(&mut state_lock.settings.shadows, "Shadows");
+                ui.add_enabled_ui(state_lock.settings.shadows, |ui| {
+                    egui::ComboBox::from_label("Shadow map size")
+                        .selected_text(state_lock.settings.shadow_map_size.to_string())
+                        .show_ui(ui, |ui| {
+                            for size in [256, 512, 1024, 2048] {
+                                ui.selectable_value(&mut state_lock.settings.shadow_map_size, size, size.to_string());
+                            }
+                        });
+                    ui.add(egui::Slider::new(&mut state_lock.settings.pcf_radius, 0..=3).text("PCF radius"));
+                });
+                let post = egui::CollapsingHeader::new("Post-processing").show(ui, |ui| {
+                    post_processing_ui(ui, &mut state_lock.post, &mut self.lut_path)
+                });
+                if let Some(Err(e)) = post.body_returned {
+                    state_lock.notice = Some(e);
+                }
+                ui.separator();
+                match state_lock.picked {
+                    Some(hit) => {
+                        ui.label(format!("Picked mesh {}, triangle {}", hit.mesh, hit.triangle));
+                        ui.label(format!("At ({:.3}, {:.3}, {:.3})", hit.point.x, hit.point.y, hit.point.z));
+                    }
+                    None => { ui.label("Click the image to pick a triangle"); }
+                }
+            });
+
+        egui::Window::new("Animation")
+            .default_open(false)
+            .resizable(false)
+            .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+            .show(ctx, |ui| {
+                let mut state_lock = self.state.lock().unwrap();
+                let playback = &mut state_lock.playback;
+                let selected = playback.animations.get(playback.active).cloned().unwrap_or_default();
+                egui::ComboBox::from_label("Animation")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for (i, name) in playback.animations.iter().enumerate() {
+                            ui.selectable_value(&mut playback.active, i, name);
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    if ui.button(if playback.playing { "Pause" } else { "Play" }).clicked() {
+                        playback.playing = !playback.playing;
+                    }
+                    ui.checkbox(&mut playback.looping, "Loop");
+                });
+                // Dragging the time slider seeks, leaving the clock playing or paused as it was
+                let mut time = playback.time;
+                if ui.add(egui::Slider::new(&mut time, 0.0..=playback.duration.max(0.0)).text("Time (s)")).changed() {
+                    playback.seek = Some(time);
+                    playback.time = time;
+                }
+                ui.add(egui::Slider::new(&mut playback.speed, -2.0..=2.0).text("Speed"));
+                let state = &mut *state_lock;
+                if state.morph_weights.iter().any(|w| !w.is_empty()) {
+                    egui::CollapsingHeader::new("Morph targets").show(ui, |ui| {
+                        for (i, (weights, names)) in state.mesh_indices.iter().zip(state.morph_weights.iter_mut().zip(state.morph_names.iter())).filter(|(_, (w, _))| !w.is_empty()) {
+                            ui.label(format!("Mesh {}", i));
+                            for (k, weight) in weights.iter_mut().enumerate() {
+                                let name = names.get(k).filter(|n| !n.is_empty()).cloned().unwrap_or_else(|| format!("Target {}", k));
+                                ui.add(egui::Slider::new(weight, 0.0..=1.0).text(name));
+                            }
+                        }
+                    });
+                }
+            });
+
+        let notice = self.state.lock().unwrap().notice.clone();
+        if let Some(notice) = notice {
+            egui::Window::new("Notice")
+                .resizable(false)
+                .collapsible(false)
+                .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -10.0])
+                .show(ctx, |ui| {
+                    ui.label(notice);
+                    if ui.button("Dismiss").clicked() {
+                        self.state.lock().unwrap().notice = None;
+                    }
+                });
+        }
+
+        egui::Window::new("Render stats")
+            .default_open(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+            .show(ctx, |ui| {
+                ui.label(format!("Engine: {:.2} ms ({:.1} FPS)", avg_frame_time, if avg_frame_time > 0.0 { 1000.0/avg_frame_time } else { 0.0 }));
+                ui.label(format!("UI repaint: {:.1} FPS", if self.ui_frame_time > 0.0 { 1.0/self.ui_frame_time } else { 0.0 }));
+                ui.separator();
+                egui::Grid::new("stats_counters").num_columns(2).show(ui, |ui| {
+                    ui.label("Submitted"); ui.label(stats.tris_submitted.to_string()); ui.end_row();
+                    ui.label("Frustum culled meshes"); ui.label(stats.meshes_frustum_culled.to_string()); ui.end_row();
+                    ui.label("Frustum culled"); ui.label(stats.tris_frustum_culled.to_string()); ui.end_row();
+                    ui.label("Lower detail meshes"); ui.label(stats.meshes_lod_reduced.to_string()); ui.end_row();
+                    ui.label("Backface culled"); ui.label(stats.tris_culled.to_string()); ui.end_row();
+                    ui.label("Clipped"); ui.label(stats.tris_clipped.to_string()); ui.end_row();
+                    ui.label("Rasterized"); ui.label(stats.tris_rasterized.to_string()); ui.end_row();
+                    ui.label("Pixels written"); ui.label(stats.pixels_written.to_string()); ui.end_row();
+                });
+                ui.separator();
+                egui::Grid::new("stats_timings").num_columns(2).show(ui, |ui| {
+                    ui.label("Transform"); ui.label(format!("{:.2} ms", stats.transform_time.as_secs_f64()*1000.0)); ui.end_row();
+                    ui.label("Shadow maps"); ui.label(format!("{:.2} ms", stats.shadow_time.as_secs_f64()*1000.0)); ui.end_row();
+                    ui.label("Clip"); ui.label(format!("{:.2} ms", stats.clip_time.as_secs_f64()*1000.0)); ui.end_row();
+                    ui.label("Sort"); ui.label(format!("{:.2} ms", stats.sort_time.as_secs_f64()*1000.0)); ui.end_row();
+                    ui.label("Raster"); ui.label(format!("{:.2} ms", stats.raster_time.as_secs_f64()*1000.0)); ui.end_row();
+                    ui.label("Resolve"); ui.label(format!("{:.2} ms", stats.resolve_time.as_secs_f64()*1000.0)); ui.end_row();
+                    ui.label("Post-processing"); ui.label(format!("{:.2} ms", stats.post_time.as_secs_f64()*1000.0)); ui.end_row();
+                    ui.label("Frame"); ui.label(format!("{:.2} ms", stats.frame_time.as_secs_f64()*1000.0)); ui.end_row();
+                });
+                ui.separator();
+                frame_time_graph(ui, &frame_times, max_frame_time);
+            });
+    }
+}
 
+// Passes in the order they run, each with a switch and buttons to move it, then their settings.
+// Fails with a message if the lookup table at lut_path can't be loaded.
+fn post_processing_ui(ui: &mut egui::Ui, post: &mut PostChain, lut_path: &mut String) -> Result<(), String> {
+    let mut result = Ok(());
+    let mut moved = None;
+    let count = post.passes.len();
+    for (i, (pass, enabled)) in post.passes.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.add_enabled(i > 0, egui::Button::new("⏶")).clicked() {
+                moved = Some((i, i - 1));
+            }
+            if ui.add_enabled(i + 1 < count, egui::Button::new("⏷")).clicked() {
+                moved = Some((i, i + 1));
+            }
+            ui.checkbox(enabled, pass.label());
+        });
+    }
+    if let Some((from, to)) = moved {
+        post.move_pass(from, to);
+    }
+    ui.label("Bloom and vignette run before tone mapping, the rest after it");
+    ui.separator();
+    ui.add_enabled(post.is_enabled(PostPass::Gamma), egui::Slider::new(&mut post.gamma, 0.5..=2.5).text("Gamma"));
+    ui.add_enabled(post.is_enabled(PostPass::Vignette), egui::Slider::new(&mut post.vignette, 0.0..=1.0).text("Vignette"));
+    ui.add_enabled_ui(post.is_enabled(PostPass::Sobel), |ui| {
+        ui.horizontal(|ui| {
+            ui.add(egui::Slider::new(&mut post.edge_threshold, 0.05..=1.0).text("Edge threshold"));
+            ui.color_edit_button_srgba(&mut post.edge_color);
         });
+    });
+    ui.add_enabled_ui(post.is_enabled(PostPass::Bloom), |ui| {
+        ui.add(egui::Slider::new(&mut post.bloom_threshold, 0.0..=4.0).text("Bloom threshold"));
+        ui.add(egui::Slider::new(&mut post.bloom_intensity, 0.0..=2.0).text("Bloom intensity"));
+        ui.add(egui::Slider::new(&mut post.bloom_radius, 1..=16).text("Bloom radius"));
+    });
+    ui.add_enabled_ui(post.is_enabled(PostPass::ColorGrade), |ui| {
+        egui::ComboBox::from_label("Look")
+            .selected_text(post.lut_file.clone().unwrap_or_else(|| post.lut_preset.label().to_string()))
+            .show_ui(ui, |ui| {
+                for preset in LutPreset::ALL {
+                    if ui.selectable_label(post.lut_file.is_none() && post.lut_preset == preset, preset.label()).clicked() {
+                        post.lut_preset = preset;
+                        post.lut_file = None;
+                        post.lut = Some(Arc::new(preset.build()));
+                    }
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(lut_path);
+            if ui.button("Load .cube…").clicked() {
+                match load_cube(lut_path.as_str()) {
+                    Ok(lut) => {
+                        post.lut = Some(Arc::new(lut));
+                        post.lut_file = Some(lut_path.clone());
+                    }
+                    Err(e) => result = Err(format!("Could not load {} ({})", lut_path, e)),
+                }
+            }
+        });
+        ui.add(egui::Slider::new(&mut post.lut_strength, 0.0..=1.0).text("Grading strength"));
+    });
+    result
+}
+
+// Line graph of recent engine frame times, scaled to the slowest frame in the window
+fn frame_time_graph(ui: &mut egui::Ui, frame_times: &[f64], max_frame_time: f64) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(220.0, 60.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(20));
+    if frame_times.len() < 2 || max_frame_time <= 0.0 {
+        return;
     }
+    let step = rect.width() / (frame_times.len() - 1) as f32;
+    let points: Vec<egui::Pos2> = frame_times.iter().enumerate().map(|(i, t)| {
+        egui::pos2(rect.left() + i as f32*step, rect.bottom() - (t/max_frame_time) as f32*rect.height())
+    }).collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, egui::Color32::LIGHT_GREEN)));
+    painter.text(rect.left_top() + egui::vec2(2.0, 2.0), egui::Align2::LEFT_TOP, format!("{:.1} ms", max_frame_time), egui::FontId::monospace(10.0), egui::Color32::GRAY);
 }
\ No newline at end of file
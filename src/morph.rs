@@ -0,0 +1,40 @@
+use crate::objs::{ Mesh, Vec3d };
+
+// One blend shape: how far each vertex moves (and its normal turns) at full weight
+#[derive(Clone)]
+pub struct MorphTarget {
+    pub name: String,
+    // Per vertex offsets from the rest pose; either list may be empty
+    pub positions: Vec<Vec3d>,
+    pub normals: Vec<Vec3d>,
+}
+
+impl MorphTarget {
+    pub fn new(name: String, positions: Vec<Vec3d>) -> Self {
+        Self { name, positions, normals: Vec::new() }
+    }
+}
+
+// Puts a deformable mesh's vertices back at its rest pose plus each morph target's offsets
+// scaled by its weight. Skinning then moves them on from there.
+pub fn morph_mesh(mesh: &mut Mesh) {
+    mesh.keep_rest_pose();
+    mesh.positions.clone_from(&mesh.rest_positions);
+    mesh.normals.clone_from(&mesh.rest_normals);
+    let vertex_count = mesh.positions.len();
+    let mut normals_moved = false;
+    for (target, weight) in mesh.morph_targets.iter().zip(mesh.morph_weights.iter()).filter(|(_, w)| **w != 0.0) {
+        if target.positions.len() == vertex_count {
+            mesh.positions.iter_mut().zip(target.positions.iter()).for_each(|(p, d)| *p = *p + *d*(*weight));
+        }
+        if target.normals.len() == vertex_count && mesh.normals.len() == vertex_count {
+            mesh.normals.iter_mut().zip(target.normals.iter()).for_each(|(n, d)| *n = *n + *d*(*weight));
+            normals_moved = true;
+        }
+    }
+    if normals_moved {
+        mesh.normals.iter_mut().for_each(|n| n.normalize());
+    }
+    // The hierarchy no longer matches the triangles
+    mesh.bvh = None;
+}
@@ -1,20 +1,157 @@
 use eframe::egui;
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::AtomicBool;
+use std::collections::HashMap;
 use std::ops::{Add, Mul, Sub};
 
+use crate::animation::Playback;
+use crate::bvh::Bvh;
+use crate::color::ToneMapping;
+use crate::fog::Fog;
+use crate::framebuffer::{ AaMode, TransparencyMode };
+use crate::morph::MorphTarget;
+use crate::picking::PickHit;
+use crate::post::PostChain;
+use crate::scene::AlphaMode;
+use crate::stats::{ FrameHistory, RenderStats };
+use crate::subdivision::SubdivisionScheme;
+
 pub struct GUIState {
     pub ctx: Option<egui::Context>,
+    pub stats: RenderStats,
+    pub history: FrameHistory,
+    pub settings: RenderSettings,
+    // Set by the UI to have the engine refit the view to the scene
+    pub frame_requested: bool,
+    // Buffer pixel the user clicked, answered by the engine in picked
+    pub pick_request: Option<[f64; 2]>,
+    pub picked: Option<PickHit>,
+    // Scene index of each mesh the per-mesh lists describe, which are those the nodes draw
+    pub mesh_indices: Vec<usize>,
+    // Render mode of each listed mesh; the engine fills it in when meshes are added and applies edits
+    pub mesh_modes: Vec<RenderMode>,
+    pub playback: Playback,
+    // Morph target weights of each listed mesh, which the UI edits and the engine reports back as
+    // animations change them, with the names of the targets to label them by
+    pub morph_weights: Vec<Vec<f64>>,
+    pub morph_names: Vec<Vec<String>>,
+    // Alpha mode and opacity of each scene material, which the UI edits, with names to label them by
+    pub material_alpha: Vec<(AlphaMode, f64)>,
+    pub material_names: Vec<String>,
+    // How the UI would have the scene subdivided, and whether it has asked the engine to do so
+    pub subdivision_scheme: SubdivisionScheme,
+    pub subdivision_levels: usize,
+    pub subdivide_requested: bool,
+    // Set by the UI to have the engine weld, drop broken faces from and fill in normals for the scene's meshes
+    pub clean_up_requested: bool,
+    // Set by the UI to have the engine give large meshes simplified levels of detail
+    pub lod_requested: bool,
+    // Passes run over each finished frame
+    pub post: PostChain,
+    // Something the engine wants the user to know, shown until it is dismissed
+    pub notice: Option<String>,
 }
 
 impl GUIState {
     pub fn new() -> Self {
         Self {
             ctx: None,
+            stats: RenderStats::new(),
+            history: FrameHistory::new(240),
+            settings: RenderSettings::default(),
+            frame_requested: false,
+            pick_request: None,
+            picked: None,
+            mesh_indices: Vec::new(),
+            mesh_modes: Vec::new(),
+            playback: Playback::default(),
+            morph_weights: Vec::new(),
+            morph_names: Vec::new(),
+            material_alpha: Vec::new(),
+            material_names: Vec::new(),
+            subdivision_scheme: SubdivisionScheme::default(),
+            subdivision_levels: 1,
+            subdivide_requested: false,
+            clean_up_requested: false,
+            lod_requested: false,
+            post: PostChain::new(),
+            notice: None,
+        }
+    }
+}
+
+// Options the UI sets and the engine picks up at the start of each frame
+#[derive(Copy, Clone)]
+pub struct RenderSettings {
+    // Cull triangles through each mesh's BVH instead of only whole meshes
+    pub use_bvh: bool,
+    // Draw meshes that are small on screen with their simplified levels of detail, if they have any
+    pub lod: bool,
+    pub aa: AaMode,
+    // How the HDR frame is brought into display range, after brightening it by 2^exposure
+    pub tone_mapping: ToneMapping,
+    pub exposure: f64,
+    pub transparency: TransparencyMode,
+    pub fog: Fog,
+    // Outlines drawn as anti-aliased lines of the given width and color instead of Bresenham's
+    pub smooth_lines: bool,
+    pub line_width: f64,
+    pub line_color: egui::Color32,
+    // Diameter of the dots point cloud meshes are drawn with, in output pixels
+    pub point_size: f64,
+    pub shadows: bool,
+    // Texels along each side of every light's shadow map
+    pub shadow_map_size: usize,
+    // Shadow lookups average a (2r + 1) x (2r + 1) block of texels, 0 for hard edges
+    pub pcf_radius: usize,
+    // Draw a floor under the framed model to catch its shadow
+    pub ground_plane: bool,
+    pub overlays: Overlays,
+}
+
+// Debug lines drawn over the frame, each toggled separately
+#[derive(Copy, Clone, Default)]
+pub struct Overlays {
+    pub face_normals: bool,
+    pub vertex_normals: bool,
+    // Grid on the plane under the scene, in the space the scene's root nodes are placed in
+    pub grid: bool,
+    // Orientation of the scene's axes, in a corner of the view
+    pub axes: bool,
+    // Each mesh's bounding box, turned with the mesh
+    pub bounds: bool,
+    pub lights: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            use_bvh: false,
+            lod: true,
+            aa: AaMode::Off,
+            tone_mapping: ToneMapping::default(),
+            exposure: 0.0,
+            transparency: TransparencyMode::default(),
+            fog: Fog::default(),
+            smooth_lines: false,
+            line_width: 1.0,
+            line_color: egui::Color32::BLACK,
+            point_size: 3.0,
+            shadows: true,
+            shadow_map_size: 512,
+            pcf_radius: 1,
+            ground_plane: true,
+            overlays: Overlays::default(),
         }
     }
 }
 
+impl Default for GUIState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct DisplayBuffers {
     pub buf_size: [usize; 2],
     pub bufs: Vec<Arc<Mutex<Vec<egui::Color32>>>>,
@@ -85,6 +222,10 @@ impl Vec3d {
 
     pub fn normalize(&mut self){
         let den = (self.x*self.x + self.y*self.y + self.z*self.z).sqrt();
+        // A zero vector has no direction; leave it rather than fill it with NaN
+        if den == 0.0 {
+            return;
+        }
         self.x /= den;
         self.y /= den;
         self.z /= den;
@@ -99,6 +240,18 @@ impl Vec3d {
     }
 }
 
+#[derive(Copy, Clone)]
+pub struct Vec2d {
+    pub u: f64,
+    pub v: f64,
+}
+
+impl Vec2d {
+    pub fn new(u: f64, v: f64)->Self{
+        Self { u, v }
+    }
+}
+
 impl Add for Vec3d {
     type Output = Vec3d;
     fn add(self, rhs: Self) -> Self::Output {
@@ -138,12 +291,36 @@ impl Mul<f64> for Vec3d {
 #[derive(Clone)]
 pub struct Tri {
     pub p: Vec<Vec3d>,
-    pub shade: u8,
+    // Per-vertex normals from the source file, empty if it had none
+    pub n: Vec<Vec3d>,
+    // Per-vertex colors, empty if the source had none
+    pub col: Vec<egui::Color32>,
+    // Per-vertex texture coordinates, empty if the source had none
+    pub uv: Vec<Vec2d>,
 }
 
 impl Tri {
     pub fn new(p: Vec<Vec3d>)->Self{
-        Self { p, shade:255 }
+        Self { p, n: Vec::new(), col: Vec::new(), uv: Vec::new() }
+    }
+
+    pub fn with_normals(p: Vec<Vec3d>, n: Vec<Vec3d>)->Self{
+        Self { p, n, col: Vec::new(), uv: Vec::new() }
+    }
+
+    // Sets vertex k to the point a fraction t of the way from vertex i to vertex j of src,
+    // carrying normals and colors along with the position
+    fn copy_vertex(&mut self, k: usize, src: &Tri, i: usize, j: usize, t: f64) {
+        self.p[k] = src.p[i] + (src.p[j] - src.p[i])*t;
+        if src.n.len() == 3 {
+            self.n[k] = src.n[i] + (src.n[j] - src.n[i])*t;
+        }
+        if src.col.len() == 3 {
+            self.col[k] = lerp_color(src.col[i], src.col[j], t);
+        }
+        if src.uv.len() == 3 {
+            self.uv[k] = Vec2d::new(src.uv[i].u + (src.uv[j].u - src.uv[i].u)*t, src.uv[i].v + (src.uv[j].v - src.uv[i].v)*t);
+        }
     }
 
     pub fn get_normal(&self) -> Vec3d{
@@ -168,28 +345,26 @@ impl Tri {
 
 		// Create two temporary storage arrays to classify points either side of plane
 		// If distance sign is positive, point lies on "inside" of plane
-		let mut inside_points = Vec::with_capacity(3);  let n_inside_point_count;
-		let mut outside_points = Vec::with_capacity(3); let n_outside_point_count;
+		let mut inside_points = Vec::with_capacity(3);
+		let mut outside_points = Vec::with_capacity(3);
 
 		// Get signed distance of each point in triangle to plane
-		let d0 = dist(&self.p[0]);
-		let d1 = dist(&self.p[1]);
-		let d2 = dist(&self.p[2]);
+		let d = [dist(&self.p[0]), dist(&self.p[1]), dist(&self.p[2])];
 
-        // println!("{} {} {}",d0,d1,d2);
+		// Points are kept as vertex indices so normals and colors can be interpolated too
+		for (i, di) in d.iter().enumerate() {
+			if *di >= 0.0 { inside_points.push(i); }
+			else { outside_points.push(i); }
+		}
 
-		if d0 >= 0.0 { inside_points.push(self.p[0]); }
-		else { outside_points.push(self.p[0]); }
-		if d1 >= 0.0 { inside_points.push(self.p[1]); }
-		else { outside_points.push(self.p[1]); }
-		if d2 >= 0.0 { inside_points.push(self.p[2]); }
-		else { outside_points.push(self.p[2]); }
+		// Fraction of the way along the edge from vertex a to vertex b where it crosses the plane
+		let t = |a: usize, b: usize| d[a] / (d[a] - d[b]);
 
 		// Now classify triangle points, and break the input triangle into 
 		// smaller output triangles if required. There are four possible
 		// outcomes...
-        n_inside_point_count = inside_points.len();
-        n_outside_point_count = outside_points.len();
+        let n_inside_point_count = inside_points.len();
+        let n_outside_point_count = outside_points.len();
 
 		if n_inside_point_count == 0
 		{
@@ -215,14 +390,15 @@ impl Tri {
 
 			// Copy appearance info to new triangle
 			ret.push(self.clone());
+			let (i0, o0, o1) = (inside_points[0], outside_points[0], outside_points[1]);
 
 			// The inside point is valid, so keep that...
-			ret[0].p[0] = inside_points[0];
+			ret[0].copy_vertex(0, self, i0, i0, 0.0);
 
 			// but the two new points are at the locations where the 
 			// original sides of the triangle (lines) intersect with the plane
-			ret[0].p[1] = Vec3d::vector_intersect_plane(&plane_p, &plane_n, &inside_points[0], &outside_points[0]);
-			ret[0].p[2] = Vec3d::vector_intersect_plane(&plane_p, &plane_n, &inside_points[0], &outside_points[1]);
+			ret[0].copy_vertex(1, self, i0, o0, t(i0, o0));
+			ret[0].copy_vertex(2, self, i0, o1, t(i0, o1));
 
 			return ret; // Return the newly formed single triangle
 		}
@@ -236,37 +412,193 @@ impl Tri {
 			// Copy appearance info to new triangles
             ret.push(self.clone());
             ret.push(self.clone());
+			let (i0, i1, o0) = (inside_points[0], inside_points[1], outside_points[0]);
 
 			// The first triangle consists of the two inside points and a new
 			// point determined by the location where one side of the triangle
 			// intersects with the plane
-			ret[0].p[0] = inside_points[0];
-			ret[0].p[1] = inside_points[1];
-			ret[0].p[2] = Vec3d::vector_intersect_plane(&plane_p, &plane_n, &inside_points[0], &outside_points[0]);
+			ret[0].copy_vertex(0, self, i0, i0, 0.0);
+			ret[0].copy_vertex(1, self, i1, i1, 0.0);
+			ret[0].copy_vertex(2, self, i0, o0, t(i0, o0));
 
 			// The second triangle is composed of one of he inside points, a
 			// new point determined by the intersection of the other side of the 
 			// triangle and the plane, and the newly created point above
-			ret[1].p[0] = inside_points[1];
-			ret[1].p[1] = ret[0].p[2];
-			ret[1].p[2] = Vec3d::vector_intersect_plane(&plane_p, &plane_n, &inside_points[1], &outside_points[0]);
+			ret[1].copy_vertex(0, self, i1, i1, 0.0);
+			ret[1].copy_vertex(1, self, i0, o0, t(i0, o0));
+			ret[1].copy_vertex(2, self, i1, o0, t(i1, o0));
 
 			return ret; // Return two newly formed triangles which form a quad
 		}
-        return ret;
+        ret
 	}
 
 }
+
+pub fn lerp_color(a: egui::Color32, b: egui::Color32, t: f64) -> egui::Color32 {
+    let l = |x: u8, y: u8| (x as f64 + (y as f64 - x as f64)*t).round().clamp(0.0, 255.0) as u8;
+    egui::Color32::from_rgba_premultiplied(l(a.r(), b.r()), l(a.g(), b.g()), l(a.b(), b.b()), l(a.a(), b.a()))
+}
+
+// How a mesh is drawn. Hidden line fills the mesh into the depth buffer only, so just its visible
+// edges show; wireframe draws every edge, front or back facing.
+#[derive(Copy, Clone, PartialEq, Default)]
+pub enum RenderMode {
+    #[default]
+    ShadedEdges,
+    Shaded,
+    Wireframe,
+    HiddenLine,
+    Points,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 5] = [RenderMode::ShadedEdges, RenderMode::Shaded, RenderMode::Wireframe, RenderMode::HiddenLine, RenderMode::Points];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RenderMode::ShadedEdges => "Shaded with edges",
+            RenderMode::Shaded => "Shaded",
+            RenderMode::Wireframe => "Wireframe",
+            RenderMode::HiddenLine => "Hidden line",
+            RenderMode::Points => "Points",
+        }
+    }
+}
+
+// Indexed triangle mesh: a shared vertex buffer plus three vertex indices per triangle
 pub struct Mesh {
-    pub tris: Vec<Tri>,
+    pub positions: Vec<Vec3d>,
+    // Optional vertex attributes, either empty or one entry per position
+    pub normals: Vec<Vec3d>,
+    pub colors: Vec<egui::Color32>,
+    pub uvs: Vec<Vec2d>,
+    // Up to four joints, indexing the skin of the node drawing the mesh, and how much each moves the vertex
+    pub joints: Vec<[usize; 4]>,
+    pub weights: Vec<[f64; 4]>,
+    // Blend shapes, mixed in by the weight of each before skinning
+    pub morph_targets: Vec<MorphTarget>,
+    pub morph_weights: Vec<f64>,
+    // Positions and normals as loaded, kept once deformation starts overwriting positions and normals
+    pub rest_positions: Vec<Vec3d>,
+    pub rest_normals: Vec<Vec3d>,
+    pub indices: Vec<[usize; 3]>,
+    // The faces indices was triangulated from, kept for meshes with faces of more than three
+    // corners so subdivision can work on them. Empty when the triangles are the faces, and cleared
+    // by anything that changes the triangles in a way the faces can't follow.
+    pub polygons: Vec<Vec<usize>>,
+    // Index into the owning scene's materials
+    pub material: Option<usize>,
+    // Optional triangle hierarchy for culling, see build_bvh
+    pub bvh: Option<Bvh>,
+    pub render_mode: RenderMode,
 }
 
 impl Mesh {
-    pub fn new(tris: Vec<Tri>)->Self{
-        Self { tris, }
+    pub fn new(positions: Vec<Vec3d>, indices: Vec<[usize; 3]>)->Self{
+        Self {
+            positions,
+            normals: Vec::new(),
+            colors: Vec::new(),
+            uvs: Vec::new(),
+            joints: Vec::new(),
+            weights: Vec::new(),
+            morph_targets: Vec::new(),
+            morph_weights: Vec::new(),
+            rest_positions: Vec::new(),
+            rest_normals: Vec::new(),
+            indices,
+            polygons: Vec::new(),
+            material: None,
+            bvh: None,
+            render_mode: RenderMode::default(),
+        }
+    }
+
+    // Builds the vertex buffer from loose triangles, sharing vertices whose attributes match exactly
+    pub fn from_tris(tris: &[Tri])->Self{
+        let has_normals = !tris.is_empty() && tris.iter().all(|t| t.n.len() == 3);
+        let has_colors = !tris.is_empty() && tris.iter().all(|t| t.col.len() == 3);
+        let has_uvs = !tris.is_empty() && tris.iter().all(|t| t.uv.len() == 3);
+
+        let mut mesh = Mesh::new(Vec::new(), Vec::with_capacity(tris.len()));
+        let mut lookup: HashMap<Vec<u64>, usize> = HashMap::new();
+        for t in tris {
+            let mut idx = [0; 3];
+            for (k, slot) in idx.iter_mut().enumerate() {
+                let mut key = vec![t.p[k].x.to_bits(), t.p[k].y.to_bits(), t.p[k].z.to_bits()];
+                if has_normals { key.extend([t.n[k].x.to_bits(), t.n[k].y.to_bits(), t.n[k].z.to_bits()]); }
+                if has_colors { key.push(u32::from_le_bytes(t.col[k].to_array()) as u64); }
+                if has_uvs { key.extend([t.uv[k].u.to_bits(), t.uv[k].v.to_bits()]); }
+                *slot = *lookup.entry(key).or_insert_with(|| {
+                    mesh.positions.push(t.p[k]);
+                    if has_normals { mesh.normals.push(t.n[k]); }
+                    if has_colors { mesh.colors.push(t.col[k]); }
+                    if has_uvs { mesh.uvs.push(t.uv[k]); }
+                    mesh.positions.len() - 1
+                });
+            }
+            mesh.indices.push(idx);
+        }
+        mesh
+    }
+
+    // Sets the faces and triangulates each as a fan from its first corner, which is exact for
+    // the convex faces files mostly have
+    pub fn set_polygons(&mut self, polygons: Vec<Vec<usize>>){
+        self.indices = polygons.iter().flat_map(|f| (1..f.len().saturating_sub(1)).map(move |k| [f[0], f[k], f[k + 1]])).collect();
+        self.polygons = if polygons.iter().all(|f| f.len() == 3) { Vec::new() } else { polygons };
+        self.bvh = None;
+    }
+
+    pub fn is_skinned(&self)->bool{
+        !self.joints.is_empty() && self.joints.len() == self.positions.len() && self.weights.len() == self.positions.len()
+    }
+
+    // Whether skinning or morphing reshape the mesh every frame
+    pub fn is_deformable(&self)->bool{
+        self.is_skinned() || !self.morph_targets.is_empty()
+    }
+
+    // Saves the current positions and normals as the rest pose, unless one is already saved
+    pub fn keep_rest_pose(&mut self){
+        if self.rest_positions.is_empty() {
+            self.rest_positions = self.positions.clone();
+            self.rest_normals = self.normals.clone();
+        }
+    }
+
+    // Has to be called again after positions or indices change
+    pub fn build_bvh(&mut self){
+        self.bvh = Some(Bvh::build(self));
+    }
+
+    pub fn to_tris(&self)->Vec<Tri>{
+        (0..self.indices.len()).map(|i| self.tri(i)).collect()
+    }
+
+    pub fn tri(&self, i: usize)->Tri{
+        self.assemble_tri(i, &self.positions, &self.normals)
+    }
+
+    // Triangle i built from already transformed positions and normals, with this mesh's other attributes
+    pub fn assemble_tri(&self, i: usize, positions: &[Vec3d], normals: &[Vec3d])->Tri{
+        let idx = self.indices[i];
+        let mut tri = Tri::new(idx.iter().map(|v| positions[*v]).collect());
+        if !normals.is_empty() {
+            tri.n = idx.iter().map(|v| normals[*v]).collect();
+        }
+        if !self.colors.is_empty() {
+            tri.col = idx.iter().map(|v| self.colors[*v]).collect();
+        }
+        if !self.uvs.is_empty() {
+            tri.uv = idx.iter().map(|v| self.uvs[*v]).collect();
+        }
+        tri
     }
 }
 
+#[derive(Clone)]
 pub struct Matrix4x4 {
     pub m: Vec<Vec<f64>>,
 }
@@ -288,15 +620,48 @@ impl Matrix4x4 {
             z /= w;
 		}
 
-        Vec3d::new(x, y, z)
+        // w is kept so projected points remember their view depth for perspective correct interpolation
+        Vec3d { x, y, z, w }
+    }
+
+    // Directions only go through the upper 3x3. Normals need normal_matrix instead once there
+    // are non-uniform scales.
+    pub fn mul_mat_dir(&self, i: &Vec3d)->Vec3d{
+        Vec3d::new(
+            i.x * self.m[0][0] + i.y * self.m[1][0] + i.z * self.m[2][0],
+            i.x * self.m[0][1] + i.y * self.m[1][1] + i.z * self.m[2][1],
+            i.x * self.m[0][2] + i.y * self.m[1][2] + i.z * self.m[2][2],
+        )
     }
 
     pub fn mul_mat_tri(&self, t: &Tri)->Tri{
-        let mut tri_projected = Vec::with_capacity(3);
-        tri_projected.push(self.mul_mat_vec(&t.p[0]));
-        tri_projected.push(self.mul_mat_vec(&t.p[1]));
-        tri_projected.push(self.mul_mat_vec(&t.p[2]));
-        Tri::new(tri_projected)
+        let mut tri_projected = t.clone();
+        for i in 0..3 {
+            tri_projected.p[i] = self.mul_mat_vec(&t.p[i]);
+        }
+        for n in tri_projected.n.iter_mut() {
+            *n = self.mul_mat_dir(n);
+        }
+        tri_projected
+    }
+
+    pub fn identity()->Self{
+        let mut matrix = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        for i in 0..4 {
+            matrix.m[i][i] = 1.0;
+        }
+        matrix
+    }
+
+    // Points are row vectors, so self is applied first and rhs second
+    pub fn mul_mat_mat(&self, rhs: &Matrix4x4)->Self{
+        let mut matrix = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        for r in 0..4 {
+            for c in 0..4 {
+                matrix.m[r][c] = self.m[r][0]*rhs.m[0][c] + self.m[r][1]*rhs.m[1][c] + self.m[r][2]*rhs.m[2][c] + self.m[r][3]*rhs.m[3][c];
+            }
+        }
+        matrix
     }
 
     // Only for rot and trans matrices
@@ -309,7 +674,59 @@ impl Matrix4x4 {
             matrix.m[3][1] = -(self.m[3][0] * matrix.m[0][1] + self.m[3][1] * matrix.m[1][1] + self.m[3][2] * matrix.m[2][1]);
             matrix.m[3][2] = -(self.m[3][0] * matrix.m[0][2] + self.m[3][1] * matrix.m[1][2] + self.m[3][2] * matrix.m[2][2]);
             matrix.m[3][3] = 1.0;
-            return matrix;
+            matrix
+    }
+
+    // General inverse by Gauss-Jordan elimination, None for singular matrices
+    pub fn inverse(&self)->Option<Self>{
+        let mut a = self.m.clone();
+        let mut inv = Matrix4x4::identity().m;
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|x, y| a[*x][col].abs().total_cmp(&a[*y][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+            let d = a[col][col];
+            for c in 0..4 {
+                a[col][c] /= d;
+                inv[col][c] /= d;
+            }
+            for r in 0..4 {
+                if r != col {
+                    let f = a[r][col];
+                    for c in 0..4 {
+                        a[r][c] -= f*a[col][c];
+                        inv[r][c] -= f*inv[col][c];
+                    }
+                }
+            }
+        }
+        Some(Matrix4x4::new(inv))
+    }
+
+    // The inverse transpose of the upper 3x3 up to a positive scale, which takes normals to ones
+    // still at right angles to the transformed surface, non-uniform scales included. Its rows
+    // are the cofactors, turned round for mirroring matrices so normals keep facing out. Results
+    // need renormalising.
+    pub fn normal_matrix(&self)->Self{
+        let row = |i: usize| Vec3d::new(self.m[i][0], self.m[i][1], self.m[i][2]);
+        let sign = if self.determinant3() < 0.0 { -1.0 } else { 1.0 };
+        let rows = [row(1).cross(&row(2)), row(2).cross(&row(0)), row(0).cross(&row(1))];
+        let mut out = Matrix4x4::identity();
+        for (i, r) in rows.iter().enumerate() {
+            out.m[i][0] = r.x*sign;
+            out.m[i][1] = r.y*sign;
+            out.m[i][2] = r.z*sign;
+        }
+        out
+    }
+
+    // Determinant of the upper 3x3, negative when the matrix mirrors geometry
+    pub fn determinant3(&self)->f64{
+        let m = &self.m;
+        m[0][0]*(m[1][1]*m[2][2] - m[1][2]*m[2][1]) - m[0][1]*(m[1][0]*m[2][2] - m[1][2]*m[2][0]) + m[0][2]*(m[1][0]*m[2][1] - m[1][1]*m[2][0])
     }
 
     pub fn make_rotation_x(&mut self, ftheta: f64){
@@ -339,8 +756,30 @@ impl Matrix4x4 {
 		self.m[3][3] = 1.0;
     }
 
+    pub fn make_scale(&mut self, x: f64, y: f64, z: f64){
+        self.m[0][0] = x;
+		self.m[1][1] = y;
+		self.m[2][2] = z;
+		self.m[3][3] = 1.0;
+    }
+
+    // Rotation from a unit quaternion (x, y, z, w)
+    pub fn make_rotation_quat(&mut self, q: [f64; 4]){
+        let [x, y, z, w] = q;
+        self.m[0][0] = 1.0 - 2.0*(y*y + z*z);
+		self.m[0][1] = 2.0*(x*y + z*w);
+		self.m[0][2] = 2.0*(x*z - y*w);
+		self.m[1][0] = 2.0*(x*y - z*w);
+		self.m[1][1] = 1.0 - 2.0*(x*x + z*z);
+		self.m[1][2] = 2.0*(y*z + x*w);
+		self.m[2][0] = 2.0*(x*z + y*w);
+		self.m[2][1] = 2.0*(y*z - x*w);
+		self.m[2][2] = 1.0 - 2.0*(x*x + y*y);
+		self.m[3][3] = 1.0;
+    }
+
     pub fn make_projection(&mut self, f_fov_degrees: f64, f_aspect_ratio: f64, f_near: f64, f_far: f64){
-        let f_fov_rad = 1.0 / ((f_fov_degrees * 0.5 / 180.0 * 3.14159) as f64).tan();
+        let f_fov_rad = 1.0 / (f_fov_degrees * 0.5 / 180.0 * std::f64::consts::PI).tan();
         self.m[0][0] = f_aspect_ratio * f_fov_rad;
 		self.m[1][1] = f_fov_rad;
 		self.m[2][2] = f_far / (f_far - f_near);
@@ -349,6 +788,15 @@ impl Matrix4x4 {
 		self.m[3][3] = 0.0;
     }
 
+    // Maps the box |x| <= half_width, |y| <= half_height, near <= z <= far to clip space, leaving w at 1
+    pub fn make_orthographic(&mut self, half_width: f64, half_height: f64, f_near: f64, f_far: f64){
+        self.m[0][0] = 1.0 / half_width;
+        self.m[1][1] = 1.0 / half_height;
+        self.m[2][2] = 1.0 / (f_far - f_near);
+        self.m[3][2] = -f_near / (f_far - f_near);
+        self.m[3][3] = 1.0;
+    }
+
     pub fn make_translation(&mut self, x: f64, y: f64, z: f64){
         self.m[0][0] = 1.0;
 		self.m[1][1] = 1.0;
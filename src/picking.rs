@@ -0,0 +1,81 @@
+use crate::bounds::Aabb;
+use crate::objs::{ Matrix4x4, Vec3d };
+
+#[derive(Copy, Clone)]
+pub struct Ray {
+    pub origin: Vec3d,
+    // Not normalised, so t keeps its meaning when the ray is moved between spaces
+    pub dir: Vec3d,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3d, dir: Vec3d) -> Self {
+        Self { origin, dir }
+    }
+
+    pub fn at(&self, t: f64) -> Vec3d {
+        self.origin + self.dir*t
+    }
+
+    // The same ray in another space; affine maps keep t, so hits compare across spaces
+    pub fn transformed(&self, mat: &Matrix4x4) -> Ray {
+        let mut origin = mat.mul_mat_vec(&self.origin);
+        origin.w = 1.0;
+        Ray { origin, dir: mat.mul_mat_dir(&self.dir) }
+    }
+
+    // Moller-Trumbore, two sided. Returns t and the barycentric weights (u, v) of b and c.
+    pub fn intersect_triangle(&self, a: &Vec3d, b: &Vec3d, c: &Vec3d) -> Option<(f64, f64, f64)> {
+        let (e1, e2) = (*b - *a, *c - *a);
+        let p = self.dir.cross(&e2);
+        let det = e1.dot(&p);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let s = self.origin - *a;
+        let u = s.dot(&p)/det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(&e1);
+        let v = self.dir.dot(&q)/det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        Some((e2.dot(&q)/det, u, v))
+    }
+
+    // Slab test, giving the parameter range where the ray is inside the box
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<(f64, f64)> {
+        let (mut t_min, mut t_max) = (f64::MIN, f64::MAX);
+        for (o, d, lo, hi) in [
+            (self.origin.x, self.dir.x, aabb.min.x, aabb.max.x),
+            (self.origin.y, self.dir.y, aabb.min.y, aabb.max.y),
+            (self.origin.z, self.dir.z, aabb.min.z, aabb.max.z),
+        ] {
+            if d.abs() < 1e-300 {
+                if o < lo || o > hi {
+                    return None;
+                }
+                continue;
+            }
+            let (t0, t1) = ((lo - o)/d, (hi - o)/d);
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+        }
+        if t_min <= t_max { Some((t_min, t_max)) } else { None }
+    }
+}
+
+// What the cursor landed on. instance indexes the (mesh, transform) list the frame was drawn from,
+// so the same mesh placed by several nodes can be told apart.
+#[derive(Copy, Clone)]
+pub struct PickHit {
+    pub instance: usize,
+    pub mesh: usize,
+    pub triangle: usize,
+    // Hit point after node transforms, before the view's framing and spin
+    pub point: Vec3d,
+    // View space depth of the hit
+    pub depth: f64,
+}
@@ -0,0 +1,378 @@
+use std::sync::Arc;
+
+use eframe::egui::Color32;
+
+use crate::color::Rgb;
+
+// Below this contrast FXAA leaves pixels alone, and how much of the local brightness is allowed
+// for when finding the edge direction, as in Lottes' original
+const FXAA_REDUCE_MIN: f64 = 1.0/128.0;
+const FXAA_REDUCE_MUL: f64 = 1.0/8.0;
+// Furthest FXAA looks along an edge, in pixels
+const FXAA_SPAN_MAX: f64 = 8.0;
+
+fn luma(c: &Rgb) -> f64 {
+    0.299*c[0] + 0.587*c[1] + 0.114*c[2]
+}
+
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0)/(edge1 - edge0)).clamp(0.0, 1.0);
+    t*t*(3.0 - 2.0*t)
+}
+
+// A 3D table of output colors over a cube of input colors, for color grading
+pub struct Lut {
+    // Entries along each axis of the cube
+    pub size: usize,
+    // Red changes fastest, then green, then blue
+    pub entries: Vec<Rgb>,
+    // Input colors the corners of the cube stand for
+    pub domain_min: Rgb,
+    pub domain_max: Rgb,
+}
+
+impl Lut {
+    pub fn from_fn(size: usize, f: impl Fn(Rgb) -> Rgb) -> Self {
+        let size = size.max(2);
+        let step = 1.0/(size - 1) as f64;
+        let entries = (0..size*size*size).map(|i| f([(i % size) as f64*step, (i/size % size) as f64*step, (i/(size*size)) as f64*step])).collect();
+        Self { size, entries, domain_min: [0.0; 3], domain_max: [1.0; 3] }
+    }
+
+    pub fn identity(size: usize) -> Self {
+        Self::from_fn(size, |c| c)
+    }
+
+    // Trilinear lookup, with inputs outside the domain clamped to its edges
+    pub fn sample(&self, c: Rgb) -> Rgb {
+        let n = self.size;
+        let mut base = [0; 3];
+        let mut frac = [0.0; 3];
+        for k in 0..3 {
+            let t = ((c[k] - self.domain_min[k])/(self.domain_max[k] - self.domain_min[k])).clamp(0.0, 1.0)*(n - 1) as f64;
+            base[k] = (t.floor() as usize).min(n - 2);
+            frac[k] = t - base[k] as f64;
+        }
+        let mut out = [0.0; 3];
+        for corner in 0..8 {
+            let (dr, dg, db) = (corner & 1, corner >> 1 & 1, corner >> 2 & 1);
+            let weight = [dr, dg, db].iter().zip(frac.iter()).map(|(d, f)| if *d == 1 { *f } else { 1.0 - f }).product::<f64>();
+            let entry = self.entries[(base[2] + db)*n*n + (base[1] + dg)*n + base[0] + dr];
+            for k in 0..3 {
+                out[k] += entry[k]*weight;
+            }
+        }
+        out
+    }
+}
+
+// Looks the GUI offers for color grading, each built into a table on demand
+#[derive(Copy, Clone, PartialEq, Default)]
+pub enum LutPreset {
+    #[default]
+    Warm,
+    Cool,
+    Sepia,
+    Desaturated,
+    HighContrast,
+}
+
+impl LutPreset {
+    pub const ALL: [LutPreset; 5] = [LutPreset::Warm, LutPreset::Cool, LutPreset::Sepia, LutPreset::Desaturated, LutPreset::HighContrast];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LutPreset::Warm => "Warm",
+            LutPreset::Cool => "Cool",
+            LutPreset::Sepia => "Sepia",
+            LutPreset::Desaturated => "Desaturated",
+            LutPreset::HighContrast => "High contrast",
+        }
+    }
+
+    pub fn build(&self) -> Lut {
+        let grade: fn(Rgb) -> Rgb = match self {
+            LutPreset::Warm => |c| [c[0]*1.08 + 0.02, c[1]*1.01, c[2]*0.88],
+            LutPreset::Cool => |c| [c[0]*0.9, c[1]*1.0, c[2]*1.1 + 0.02],
+            LutPreset::Sepia => |c| [
+                0.393*c[0] + 0.769*c[1] + 0.189*c[2],
+                0.349*c[0] + 0.686*c[1] + 0.168*c[2],
+                0.272*c[0] + 0.534*c[1] + 0.131*c[2],
+            ],
+            LutPreset::Desaturated => |c| { let l = luma(&c); c.map(|v| l + (v - l)*0.35) },
+            LutPreset::HighContrast => |c| c.map(|v| smoothstep(0.0, 1.0, v)),
+        };
+        Lut::from_fn(17, |c| grade(c).map(|v| v.clamp(0.0, 1.0)))
+    }
+}
+
+// Effects applied to the finished frame, each working on the whole image
+#[derive(Copy, Clone, PartialEq)]
+pub enum PostPass {
+    // Fast approximate anti-aliasing: blurs along edges it finds by contrast in the image
+    Fxaa,
+    // Raises each channel to 1/gamma, brightening the mid tones when gamma is above 1. The frame
+    // is already sRGB encoded by then, so this is an adjustment on top of that.
+    Gamma,
+    // Darkens towards the corners
+    Vignette,
+    // Draws outlines where brightness changes sharply
+    Sobel,
+    // Bright areas bleed light into their surroundings
+    Bloom,
+    // Remaps every color through the lookup table
+    ColorGrade,
+}
+
+impl PostPass {
+    pub const ALL: [PostPass; 6] = [PostPass::Fxaa, PostPass::Gamma, PostPass::Vignette, PostPass::Sobel, PostPass::Bloom, PostPass::ColorGrade];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PostPass::Fxaa => "FXAA",
+            PostPass::Gamma => "Gamma",
+            PostPass::Vignette => "Vignette",
+            PostPass::Sobel => "Sobel edges",
+            PostPass::Bloom => "Bloom",
+            PostPass::ColorGrade => "Color grading",
+        }
+    }
+
+    // Bloom and vignette work with amounts of light, so they run on the linear HDR frame before
+    // tone mapping. The others are tuned for display values in 0 to 1 on a perceptual scale, and
+    // run on the frame once it is tone mapped and sRGB encoded.
+    pub fn is_linear(&self) -> bool {
+        matches!(self, PostPass::Bloom | PostPass::Vignette)
+    }
+}
+
+// The passes run on each finished frame, in order within the stage each belongs to, and the
+// settings they run with
+#[derive(Clone)]
+pub struct PostChain {
+    // Every pass once, in the order they run, with whether each is on
+    pub passes: Vec<(PostPass, bool)>,
+    pub gamma: f64,
+    // How much darker the corners get, 0 to 1
+    pub vignette: f64,
+    // Brightness gradient at which outlines start, and their color
+    pub edge_threshold: f64,
+    pub edge_color: Color32,
+    // Brightness above which light blooms, how strongly, and how far in pixels
+    pub bloom_threshold: f64,
+    pub bloom_intensity: f64,
+    pub bloom_radius: usize,
+    pub lut: Option<Arc<Lut>>,
+    pub lut_preset: LutPreset,
+    // File lut was loaded from, when it isn't the preset's
+    pub lut_file: Option<String>,
+    // Share of the graded color in the result
+    pub lut_strength: f64,
+}
+
+impl PostChain {
+    // Every pass off, in the order they run: light effects, then the tone of the image, then the
+    // passes that work on its final look
+    pub fn new() -> Self {
+        let order = [PostPass::Bloom, PostPass::Vignette, PostPass::ColorGrade, PostPass::Gamma, PostPass::Sobel, PostPass::Fxaa];
+        Self {
+            passes: order.iter().map(|p| (*p, false)).collect(),
+            gamma: 1.0,
+            vignette: 0.5,
+            edge_threshold: 0.25,
+            edge_color: Color32::BLACK,
+            bloom_threshold: 0.8,
+            bloom_intensity: 0.8,
+            bloom_radius: 8,
+            lut: Some(Arc::new(LutPreset::default().build())),
+            lut_preset: LutPreset::default(),
+            lut_file: None,
+            lut_strength: 1.0,
+        }
+    }
+
+    pub fn is_enabled(&self, pass: PostPass) -> bool {
+        self.passes.iter().any(|(p, on)| *p == pass && *on)
+    }
+
+    pub fn set_enabled(&mut self, pass: PostPass, enabled: bool) {
+        self.passes.iter_mut().filter(|(p, _)| *p == pass).for_each(|(_, on)| *on = enabled);
+    }
+
+    // Moves the pass at position from to position to, shifting those in between
+    pub fn move_pass(&mut self, from: usize, to: usize) {
+        if from < self.passes.len() && to < self.passes.len() {
+            let pass = self.passes.remove(from);
+            self.passes.insert(to, pass);
+        }
+    }
+
+    // Runs the enabled linear passes over a width x height HDR image
+    pub fn apply_linear(&self, image: &mut [Rgb], width: usize, height: usize) {
+        self.run(image, width, height, true);
+    }
+
+    // Runs the rest of the enabled passes over the image once it is tone mapped and encoded
+    pub fn apply_display(&self, image: &mut [Rgb], width: usize, height: usize) {
+        self.run(image, width, height, false);
+    }
+
+    fn run(&self, image: &mut [Rgb], width: usize, height: usize, linear: bool) {
+        if image.len() != width*height {
+            return;
+        }
+        for (pass, _) in self.passes.iter().filter(|(p, on)| *on && p.is_linear() == linear) {
+            match pass {
+                PostPass::Fxaa => fxaa(image, width, height),
+                PostPass::Gamma => gamma(image, self.gamma),
+                PostPass::Vignette => vignette(image, width, height, self.vignette),
+                PostPass::Sobel => sobel(image, width, height, self.edge_threshold, self.edge_color),
+                PostPass::Bloom => bloom(image, width, height, self.bloom_threshold, self.bloom_intensity, self.bloom_radius),
+                PostPass::ColorGrade => if let Some(lut) = &self.lut {
+                    color_grade(image, lut, self.lut_strength);
+                },
+            }
+        }
+    }
+}
+
+impl Default for PostChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn at(image: &[Rgb], width: usize, height: usize, x: i64, y: i64) -> Rgb {
+    image[y.clamp(0, height as i64 - 1) as usize*width + x.clamp(0, width as i64 - 1) as usize]
+}
+
+// Bilinear lookup with pixel centres at whole coordinates
+fn sample(image: &[Rgb], width: usize, height: usize, x: f64, y: f64) -> Rgb {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let mut out = [0.0; 3];
+    for (dx, dy, w) in [(0, 0, (1.0 - fx)*(1.0 - fy)), (1, 0, fx*(1.0 - fy)), (0, 1, (1.0 - fx)*fy), (1, 1, fx*fy)] {
+        let c = at(image, width, height, x0 + dx, y0 + dy);
+        for k in 0..3 {
+            out[k] += c[k]*w;
+        }
+    }
+    out
+}
+
+// Finds the direction of the edge through each pixel from the brightness of its diagonal
+// neighbours and averages along it, unless that strays outside the range of brightness around it
+fn fxaa(image: &mut [Rgb], width: usize, height: usize) {
+    let source = image.to_vec();
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as i64, y as i64);
+            let l = |dx: i64, dy: i64| luma(&at(&source, width, height, xi + dx, yi + dy));
+            let (nw, ne, sw, se, m) = (l(-1, -1), l(1, -1), l(-1, 1), l(1, 1), l(0, 0));
+            let luma_min = m.min(nw.min(ne).min(sw.min(se)));
+            let luma_max = m.max(nw.max(ne).max(sw.max(se)));
+            let (dir_x, dir_y) = (-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+            let reduce = ((nw + ne + sw + se)*0.25*FXAA_REDUCE_MUL).max(FXAA_REDUCE_MIN);
+            let scale = 1.0/(dir_x.abs().min(dir_y.abs()) + reduce);
+            let (dir_x, dir_y) = ((dir_x*scale).clamp(-FXAA_SPAN_MAX, FXAA_SPAN_MAX), (dir_y*scale).clamp(-FXAA_SPAN_MAX, FXAA_SPAN_MAX));
+            if dir_x.abs() < 1e-3 && dir_y.abs() < 1e-3 {
+                continue;
+            }
+            let along = |t: f64| sample(&source, width, height, x as f64 + dir_x*t, y as f64 + dir_y*t);
+            let mix = |a: Rgb, b: Rgb, wa: f64, wb: f64| [0, 1, 2].map(|k| a[k]*wa + b[k]*wb);
+            let near = mix(along(1.0/3.0 - 0.5), along(2.0/3.0 - 0.5), 0.5, 0.5);
+            let far = mix(near, mix(along(-0.5), along(0.5), 0.5, 0.5), 0.5, 0.5);
+            let luma_far = luma(&far);
+            image[y*width + x] = if luma_far < luma_min || luma_far > luma_max { near } else { far };
+        }
+    }
+}
+
+fn gamma(image: &mut [Rgb], gamma: f64) {
+    let exponent = 1.0/gamma.max(1e-3);
+    for c in image.iter_mut() {
+        *c = c.map(|v| v.max(0.0).powf(exponent));
+    }
+}
+
+fn vignette(image: &mut [Rgb], width: usize, height: usize, strength: f64) {
+    let (cx, cy) = (0.5*(width as f64 - 1.0), 0.5*(height as f64 - 1.0));
+    let reach = (cx*cx + cy*cy).sqrt().max(1.0);
+    for y in 0..height {
+        for x in 0..width {
+            // Distance from the centre with the corners at 1
+            let d = ((x as f64 - cx).powi(2) + (y as f64 - cy).powi(2)).sqrt()/reach;
+            let factor = 1.0 - strength*smoothstep(0.3, 1.0, d);
+            image[y*width + x] = image[y*width + x].map(|v| v*factor);
+        }
+    }
+}
+
+// Gradient of brightness by the Sobel kernels; pixels where it passes threshold are drawn over in
+// color, fully once it is twice the threshold
+fn sobel(image: &mut [Rgb], width: usize, height: usize, threshold: f64, color: Color32) {
+    let lumas: Vec<f64> = image.iter().map(luma).collect();
+    let edge = [color.r(), color.g(), color.b()].map(|v| v as f64/255.0);
+    let threshold = threshold.max(1e-6);
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let l = |dx: i64, dy: i64| lumas[(y + dy).clamp(0, height as i64 - 1) as usize*width + (x + dx).clamp(0, width as i64 - 1) as usize];
+            let gx = (l(1, -1) + 2.0*l(1, 0) + l(1, 1)) - (l(-1, -1) + 2.0*l(-1, 0) + l(-1, 1));
+            let gy = (l(-1, 1) + 2.0*l(0, 1) + l(1, 1)) - (l(-1, -1) + 2.0*l(0, -1) + l(1, -1));
+            let t = (((gx*gx + gy*gy).sqrt() - threshold)/threshold).clamp(0.0, 1.0);
+            if t > 0.0 {
+                let c = &mut image[y as usize*width + x as usize];
+                *c = [0, 1, 2].map(|k| c[k] + (edge[k] - c[k])*t);
+            }
+        }
+    }
+}
+
+// Keeps the part of each pixel brighter than threshold, blurs it with a Gaussian radius pixels
+// wide either way and adds it back
+fn bloom(image: &mut [Rgb], width: usize, height: usize, threshold: f64, intensity: f64, radius: usize) {
+    let bright: Vec<Rgb> = image.iter().map(|c| {
+        let l = luma(c);
+        let share = if l > threshold { (l - threshold)/l } else { 0.0 };
+        c.map(|v| v*share)
+    }).collect();
+    if radius == 0 || bright.iter().all(|c| c.iter().all(|v| *v == 0.0)) {
+        return;
+    }
+    let sigma = radius as f64/2.0;
+    let kernel: Vec<f64> = (-(radius as i64)..=radius as i64).map(|i| (-((i*i) as f64)/(2.0*sigma*sigma)).exp()).collect();
+    let total: f64 = kernel.iter().sum();
+    let kernel: Vec<f64> = kernel.iter().map(|k| k/total).collect();
+
+    // Separable: along rows, then along columns
+    let r = radius as i64;
+    let blur = |source: &[Rgb], horizontal: bool| -> Vec<Rgb> {
+        let mut out = vec![[0.0; 3]; source.len()];
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                let mut sum = [0.0; 3];
+                for (i, k) in kernel.iter().enumerate() {
+                    let offset = i as i64 - r;
+                    let c = if horizontal { at(source, width, height, x + offset, y) } else { at(source, width, height, x, y + offset) };
+                    for ch in 0..3 {
+                        sum[ch] += c[ch]*k;
+                    }
+                }
+                out[y as usize*width + x as usize] = sum;
+            }
+        }
+        out
+    };
+    let blurred = blur(&blur(&bright, true), false);
+    for (c, b) in image.iter_mut().zip(blurred.iter()) {
+        *c = [0, 1, 2].map(|k| c[k] + b[k]*intensity);
+    }
+}
+
+fn color_grade(image: &mut [Rgb], lut: &Lut, strength: f64) {
+    for c in image.iter_mut() {
+        let graded = lut.sample(*c);
+        *c = [0, 1, 2].map(|k| c[k] + (graded[k] - c[k])*strength);
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::objs::{ Mesh, Vec2d, Vec3d };
+
+// Generated meshes are centred on the origin with +y up. Faces wind so that
+// (p1 - p0) x (p2 - p0) points outwards, which is what the renderer's backface test expects.
+impl Mesh {
+    // Axis aligned cube with side length size, each face split into divisions x divisions quads
+    pub fn cube(size: f64, divisions: usize) -> Self {
+        let h = size/2.0;
+        let axes = [
+            (Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0)),
+            (Vec3d::new(-1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0)),
+            (Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(0.0, 0.0, 1.0)),
+            (Vec3d::new(0.0, -1.0, 0.0), Vec3d::new(0.0, 0.0, 1.0)),
+            (Vec3d::new(0.0, 0.0, 1.0), Vec3d::new(0.0, 1.0, 0.0)),
+            (Vec3d::new(0.0, 0.0, -1.0), Vec3d::new(0.0, 1.0, 0.0)),
+        ];
+        let mut mesh = Mesh::new(Vec::new(), Vec::new());
+        for (n, up) in axes.iter() {
+            let right = n.cross(up);
+            add_surface(&mut mesh, divisions, divisions, |s, t| {
+                (*n*h + right*((s - 0.5)*size) - *up*((t - 0.5)*size), *n)
+            });
+        }
+        mesh
+    }
+
+    // Latitude/longitude sphere; the seam column is duplicated so texture coordinates wrap cleanly
+    pub fn uv_sphere(radius: f64, segments: usize, rings: usize) -> Self {
+        let mut mesh = Mesh::new(Vec::new(), Vec::new());
+        add_surface(&mut mesh, segments.max(3), rings.max(2), |s, t| {
+            let (theta, phi) = (2.0*PI*s, PI*t);
+            let n = Vec3d::new(phi.sin()*theta.cos(), phi.cos(), phi.sin()*theta.sin());
+            (n*radius, n)
+        });
+        mesh
+    }
+
+    // Icosahedron with each face split into four subdivisions times, projected onto the sphere
+    pub fn icosphere(radius: f64, subdivisions: usize) -> Self {
+        let g = (1.0 + 5f64.sqrt())/2.0;
+        let mut points: Vec<Vec3d> = [
+            (-1.0, g, 0.0), (1.0, g, 0.0), (-1.0, -g, 0.0), (1.0, -g, 0.0),
+            (0.0, -1.0, g), (0.0, 1.0, g), (0.0, -1.0, -g), (0.0, 1.0, -g),
+            (g, 0.0, -1.0), (g, 0.0, 1.0), (-g, 0.0, -1.0), (-g, 0.0, 1.0),
+        ].iter().map(|(x, y, z)| unit(Vec3d::new(*x, *y, *z))).collect();
+        let mut faces = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            // Edges are shared between faces, so midpoints are cached by their sorted endpoints
+            let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+            let mut midpoint = |a: usize, b: usize, points: &mut Vec<Vec3d>| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    points.push(unit((points[a] + points[b])*0.5));
+                    points.len() - 1
+                })
+            };
+            let mut next = Vec::with_capacity(faces.len()*4);
+            for [a, b, c] in faces {
+                let (ab, bc, ca) = (midpoint(a, b, &mut points), midpoint(b, c, &mut points), midpoint(c, a, &mut points));
+                next.extend([[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+            }
+            faces = next;
+        }
+
+        // The table above is wound the other way round, so flip any face whose normal points inwards
+        for f in faces.iter_mut() {
+            let (a, b, c) = (points[f[0]], points[f[1]], points[f[2]]);
+            if (b - a).cross(&(c - a)).dot(&a) < 0.0 {
+                f.swap(1, 2);
+            }
+        }
+        let mut mesh = Mesh::new(points.iter().map(|p| *p*radius).collect(), faces);
+        mesh.normals = points;
+        mesh
+    }
+
+    // Capped cylinder along y with the side split into segments around and stacks along its height
+    pub fn cylinder(radius: f64, height: f64, segments: usize, stacks: usize) -> Self {
+        let segments = segments.max(3);
+        let h = height/2.0;
+        let mut mesh = Mesh::new(Vec::new(), Vec::new());
+        add_surface(&mut mesh, segments, stacks.max(1), |s, t| {
+            let theta = 2.0*PI*s;
+            let n = Vec3d::new(theta.cos(), 0.0, theta.sin());
+            (n*radius + Vec3d::new(0.0, h - t*height, 0.0), n)
+        });
+        add_disk(&mut mesh, radius, h, segments, true);
+        add_disk(&mut mesh, radius, -h, segments, false);
+        mesh
+    }
+
+    // Cone along y with its apex at +height/2 and a capped base
+    pub fn cone(radius: f64, height: f64, segments: usize, stacks: usize) -> Self {
+        let segments = segments.max(3);
+        let h = height/2.0;
+        let mut mesh = Mesh::new(Vec::new(), Vec::new());
+        add_surface(&mut mesh, segments, stacks.max(1), |s, t| {
+            let theta = 2.0*PI*s;
+            let n = unit(Vec3d::new(height*theta.cos(), radius, height*theta.sin()));
+            (Vec3d::new(radius*t*theta.cos(), h - t*height, radius*t*theta.sin()), n)
+        });
+        add_disk(&mut mesh, radius, -h, segments, false);
+        mesh
+    }
+
+    // Torus around the y axis; major is the distance from the centre to the middle of the tube
+    pub fn torus(major: f64, minor: f64, major_segments: usize, minor_segments: usize) -> Self {
+        let mut mesh = Mesh::new(Vec::new(), Vec::new());
+        add_surface(&mut mesh, major_segments.max(3), minor_segments.max(3), |s, t| {
+            let (theta, phi) = (2.0*PI*s, -2.0*PI*t);
+            let n = Vec3d::new(phi.cos()*theta.cos(), phi.sin(), phi.cos()*theta.sin());
+            (Vec3d::new(major*theta.cos(), 0.0, major*theta.sin()) + n*minor, n)
+        });
+        mesh
+    }
+
+    // Flat grid in the xz plane facing +y
+    pub fn plane(width: f64, depth: f64, x_divisions: usize, z_divisions: usize) -> Self {
+        let mut mesh = Mesh::new(Vec::new(), Vec::new());
+        add_surface(&mut mesh, x_divisions, z_divisions, |s, t| {
+            (Vec3d::new((s - 0.5)*width, 0.0, (0.5 - t)*depth), Vec3d::new(0.0, 1.0, 0.0))
+        });
+        mesh
+    }
+
+    // Newell's teapot evaluated from its 32 bicubic Bezier patches, scaled to the given height.
+    // Each patch becomes a subdivisions x subdivisions grid; patch borders are not welded.
+    pub fn teapot(height: f64, subdivisions: usize) -> Self {
+        let scale = height/3.15;
+        let mut mesh = Mesh::new(Vec::new(), Vec::new());
+        for patch in TEAPOT_PATCHES.iter() {
+            let mut cp = [Vec3d::new(0.0, 0.0, 0.0); 16];
+            for (p, i) in cp.iter_mut().zip(patch.iter()) {
+                // The data is z-up, swap to y-up and sit the teapot around the origin
+                let [x, y, z] = TEAPOT_VERTICES[i - 1];
+                *p = Vec3d::new(x, z - 1.575, -y)*scale;
+            }
+            add_surface(&mut mesh, subdivisions, subdivisions, |s, t| {
+                let (p, mut n) = bezier_patch(&cp, s, t);
+                // Patches collapse to a point at the lid knob and the base centre, so take
+                // the normal from just inside the patch there
+                if n.dot(&n) < 1e-18 {
+                    n = bezier_patch(&cp, s.clamp(1e-3, 1.0 - 1e-3), t.clamp(1e-3, 1.0 - 1e-3)).1;
+                }
+                (p, unit(n))
+            });
+        }
+        mesh
+    }
+}
+
+fn unit(v: Vec3d) -> Vec3d {
+    let len = v.dot(&v).sqrt();
+    if len > 0.0 { Vec3d::new(v.x/len, v.y/len, v.z/len) } else { v }
+}
+
+// Appends a (cols + 1) x (rows + 1) grid of vertices from f(s, t) -> (position, normal) with s and t
+// in [0, 1] and uv = (s, t). Triangles wind so that dP/ds x dP/dt is their front, and triangles
+// collapsed by a pole or apex are dropped.
+fn add_surface<F: Fn(f64, f64) -> (Vec3d, Vec3d)>(mesh: &mut Mesh, cols: usize, rows: usize, f: F) {
+    let (cols, rows) = (cols.max(1), rows.max(1));
+    let base = mesh.positions.len();
+    for j in 0..=rows {
+        for i in 0..=cols {
+            let (s, t) = (i as f64/cols as f64, j as f64/rows as f64);
+            let (p, n) = f(s, t);
+            mesh.positions.push(p);
+            mesh.normals.push(n);
+            mesh.uvs.push(Vec2d::new(s, t));
+        }
+    }
+    let at = |i: usize, j: usize| base + j*(cols + 1) + i;
+    for j in 0..rows {
+        for i in 0..cols {
+            for tri in [[at(i, j), at(i + 1, j), at(i + 1, j + 1)], [at(i, j), at(i + 1, j + 1), at(i, j + 1)]] {
+                let (a, b, c) = (mesh.positions[tri[0]], mesh.positions[tri[1]], mesh.positions[tri[2]]);
+                let n = (b - a).cross(&(c - a));
+                if n.dot(&n) > 1e-24 {
+                    mesh.indices.push(tri);
+                }
+            }
+        }
+    }
+}
+
+// Flat cap at height y facing up or down
+fn add_disk(mesh: &mut Mesh, radius: f64, y: f64, segments: usize, up: bool) {
+    let n = Vec3d::new(0.0, if up { 1.0 } else { -1.0 }, 0.0);
+    add_surface(mesh, segments, 1, |s, t| {
+        let theta = 2.0*PI*s;
+        let r = if up { radius*t } else { radius*(1.0 - t) };
+        (Vec3d::new(r*theta.cos(), y, r*theta.sin()), n)
+    });
+}
+
+// Point and unnormalised normal dP/ds x dP/dt of a bicubic Bezier patch, with s along each row of cp
+fn bezier_patch(cp: &[Vec3d; 16], s: f64, t: f64) -> (Vec3d, Vec3d) {
+    let basis = |u: f64| [(1.0 - u).powi(3), 3.0*u*(1.0 - u).powi(2), 3.0*u*u*(1.0 - u), u.powi(3)];
+    let deriv = |u: f64| [-3.0*(1.0 - u).powi(2), 3.0*(1.0 - u)*(1.0 - 3.0*u), 3.0*u*(2.0 - 3.0*u), 3.0*u*u];
+    let (bs, bt, ds, dt) = (basis(s), basis(t), deriv(s), deriv(t));
+
+    let zero = Vec3d::new(0.0, 0.0, 0.0);
+    let (mut p, mut dp_ds, mut dp_dt) = (zero, zero, zero);
+    for j in 0..4 {
+        for i in 0..4 {
+            let c = cp[j*4 + i];
+            p = p + c*(bs[i]*bt[j]);
+            dp_ds = dp_ds + c*(ds[i]*bt[j]);
+            dp_dt = dp_dt + c*(bs[i]*dt[j]);
+        }
+    }
+    (p, dp_ds.cross(&dp_dt))
+}
+
+// Newell's control points (z-up) and the 1-based indices of each patch's 4 x 4 grid
+#[rustfmt::skip]
+const TEAPOT_PATCHES: [[usize; 16]; 32] = [
+    // Rim
+    [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16],
+    [4, 17, 18, 19, 8, 20, 21, 22, 12, 23, 24, 25, 16, 26, 27, 28],
+    [19, 29, 30, 31, 22, 32, 33, 34, 25, 35, 36, 37, 28, 38, 39, 40],
+    [31, 41, 42, 1, 34, 43, 44, 5, 37, 45, 46, 9, 40, 47, 48, 13],
+    // Body
+    [13, 14, 15, 16, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60],
+    [16, 26, 27, 28, 52, 61, 62, 63, 56, 64, 65, 66, 60, 67, 68, 69],
+    [28, 38, 39, 40, 63, 70, 71, 72, 66, 73, 74, 75, 69, 76, 77, 78],
+    [40, 47, 48, 13, 72, 79, 80, 49, 75, 81, 82, 53, 78, 83, 84, 57],
+    [57, 58, 59, 60, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95, 96],
+    [60, 67, 68, 69, 88, 97, 98, 99, 92, 100, 101, 102, 96, 103, 104, 105],
+    [69, 76, 77, 78, 99, 106, 107, 108, 102, 109, 110, 111, 105, 112, 113, 114],
+    [78, 83, 84, 57, 108, 115, 116, 85, 111, 117, 118, 89, 114, 119, 120, 93],
+    // Handle
+    [121, 122, 123, 124, 125, 126, 127, 128, 129, 130, 131, 132, 133, 134, 135, 136],
+    [124, 137, 138, 121, 128, 139, 140, 125, 132, 141, 142, 129, 136, 143, 144, 133],
+    [133, 134, 135, 136, 145, 146, 147, 148, 149, 150, 151, 152, 69, 153, 154, 155],
+    [136, 143, 144, 133, 148, 156, 157, 145, 152, 158, 159, 149, 155, 160, 161, 69],
+    // Spout
+    [162, 163, 164, 165, 166, 167, 168, 169, 170, 171, 172, 173, 174, 175, 176, 177],
+    [165, 178, 179, 162, 169, 180, 181, 166, 173, 182, 183, 170, 177, 184, 185, 174],
+    [174, 175, 176, 177, 186, 187, 188, 189, 190, 191, 192, 193, 194, 195, 196, 197],
+    [177, 184, 185, 174, 189, 198, 199, 186, 193, 200, 201, 190, 197, 202, 203, 194],
+    // Lid
+    [204, 204, 204, 204, 207, 208, 209, 210, 211, 211, 211, 211, 212, 213, 214, 215],
+    [204, 204, 204, 204, 210, 217, 218, 219, 211, 211, 211, 211, 215, 220, 221, 222],
+    [204, 204, 204, 204, 219, 224, 225, 226, 211, 211, 211, 211, 222, 227, 228, 229],
+    [204, 204, 204, 204, 226, 230, 231, 207, 211, 211, 211, 211, 229, 232, 233, 212],
+    [212, 213, 214, 215, 234, 235, 236, 237, 238, 239, 240, 241, 242, 243, 244, 245],
+    [215, 220, 221, 222, 237, 246, 247, 248, 241, 249, 250, 251, 245, 252, 253, 254],
+    [222, 227, 228, 229, 248, 255, 256, 257, 251, 258, 259, 260, 254, 261, 262, 263],
+    [229, 232, 233, 212, 257, 264, 265, 234, 260, 266, 267, 238, 263, 268, 269, 242],
+    // Bottom
+    [270, 270, 270, 270, 279, 280, 281, 282, 275, 276, 277, 278, 271, 272, 273, 274],
+    [270, 270, 270, 270, 282, 289, 290, 291, 278, 286, 287, 288, 274, 283, 284, 285],
+    [270, 270, 270, 270, 291, 298, 299, 300, 288, 295, 296, 297, 285, 292, 293, 294],
+    [270, 270, 270, 270, 300, 305, 306, 279, 297, 303, 304, 275, 294, 301, 302, 271],
+];
+
+#[rustfmt::skip]
+const TEAPOT_VERTICES: [[f64; 3]; 306] = [
+    [1.4, 0.0, 2.4], [1.4, -0.784, 2.4], [0.784, -1.4, 2.4], [0.0, -1.4, 2.4],
+    [1.3375, 0.0, 2.53125], [1.3375, -0.749, 2.53125], [0.749, -1.3375, 2.53125], [0.0, -1.3375, 2.53125],
+    [1.4375, 0.0, 2.53125], [1.4375, -0.805, 2.53125], [0.805, -1.4375, 2.53125], [0.0, -1.4375, 2.53125],
+    [1.5, 0.0, 2.4], [1.5, -0.84, 2.4], [0.84, -1.5, 2.4], [0.0, -1.5, 2.4],
+    [-0.784, -1.4, 2.4], [-1.4, -0.784, 2.4], [-1.4, 0.0, 2.4],
+    [-0.749, -1.3375, 2.53125], [-1.3375, -0.749, 2.53125], [-1.3375, 0.0, 2.53125],
+    [-0.805, -1.4375, 2.53125], [-1.4375, -0.805, 2.53125], [-1.4375, 0.0, 2.53125],
+    [-0.84, -1.5, 2.4], [-1.5, -0.84, 2.4], [-1.5, 0.0, 2.4],
+    [-1.4, 0.784, 2.4], [-0.784, 1.4, 2.4], [0.0, 1.4, 2.4],
+    [-1.3375, 0.749, 2.53125], [-0.749, 1.3375, 2.53125], [0.0, 1.3375, 2.53125],
+    [-1.4375, 0.805, 2.53125], [-0.805, 1.4375, 2.53125], [0.0, 1.4375, 2.53125],
+    [-1.5, 0.84, 2.4], [-0.84, 1.5, 2.4], [0.0, 1.5, 2.4],
+    [0.784, 1.4, 2.4], [1.4, 0.784, 2.4],
+    [0.749, 1.3375, 2.53125], [1.3375, 0.749, 2.53125],
+    [0.805, 1.4375, 2.53125], [1.4375, 0.805, 2.53125],
+    [0.84, 1.5, 2.4], [1.5, 0.84, 2.4],
+    [1.75, 0.0, 1.875], [1.75, -0.98, 1.875], [0.98, -1.75, 1.875], [0.0, -1.75, 1.875],
+    [2.0, 0.0, 1.35], [2.0, -1.12, 1.35], [1.12, -2.0, 1.35], [0.0, -2.0, 1.35],
+    [2.0, 0.0, 0.9], [2.0, -1.12, 0.9], [1.12, -2.0, 0.9], [0.0, -2.0, 0.9],
+    [-0.98, -1.75, 1.875], [-1.75, -0.98, 1.875], [-1.75, 0.0, 1.875],
+    [-1.12, -2.0, 1.35], [-2.0, -1.12, 1.35], [-2.0, 0.0, 1.35],
+    [-1.12, -2.0, 0.9], [-2.0, -1.12, 0.9], [-2.0, 0.0, 0.9],
+    [-1.75, 0.98, 1.875], [-0.98, 1.75, 1.875], [0.0, 1.75, 1.875],
+    [-2.0, 1.12, 1.35], [-1.12, 2.0, 1.35], [0.0, 2.0, 1.35],
+    [-2.0, 1.12, 0.9], [-1.12, 2.0, 0.9], [0.0, 2.0, 0.9],
+    [0.98, 1.75, 1.875], [1.75, 0.98, 1.875],
+    [1.12, 2.0, 1.35], [2.0, 1.12, 1.35],
+    [1.12, 2.0, 0.9], [2.0, 1.12, 0.9],
+    [2.0, 0.0, 0.45], [2.0, -1.12, 0.45], [1.12, -2.0, 0.45], [0.0, -2.0, 0.45],
+    [1.5, 0.0, 0.225], [1.5, -0.84, 0.225], [0.84, -1.5, 0.225], [0.0, -1.5, 0.225],
+    [1.5, 0.0, 0.15], [1.5, -0.84, 0.15], [0.84, -1.5, 0.15], [0.0, -1.5, 0.15],
+    [-1.12, -2.0, 0.45], [-2.0, -1.12, 0.45], [-2.0, 0.0, 0.45],
+    [-0.84, -1.5, 0.225], [-1.5, -0.84, 0.225], [-1.5, 0.0, 0.225],
+    [-0.84, -1.5, 0.15], [-1.5, -0.84, 0.15], [-1.5, 0.0, 0.15],
+    [-2.0, 1.12, 0.45], [-1.12, 2.0, 0.45], [0.0, 2.0, 0.45],
+    [-1.5, 0.84, 0.225], [-0.84, 1.5, 0.225], [0.0, 1.5, 0.225],
+    [-1.5, 0.84, 0.15], [-0.84, 1.5, 0.15], [0.0, 1.5, 0.15],
+    [1.12, 2.0, 0.45], [2.0, 1.12, 0.45],
+    [0.84, 1.5, 0.225], [1.5, 0.84, 0.225],
+    [0.84, 1.5, 0.15], [1.5, 0.84, 0.15],
+    [-1.6, 0.0, 2.025], [-1.6, -0.3, 2.025], [-1.5, -0.3, 2.25], [-1.5, 0.0, 2.25],
+    [-2.3, 0.0, 2.025], [-2.3, -0.3, 2.025], [-2.5, -0.3, 2.25], [-2.5, 0.0, 2.25],
+    [-2.7, 0.0, 2.025], [-2.7, -0.3, 2.025], [-3.0, -0.3, 2.25], [-3.0, 0.0, 2.25],
+    [-2.7, 0.0, 1.8], [-2.7, -0.3, 1.8], [-3.0, -0.3, 1.8], [-3.0, 0.0, 1.8],
+    [-1.5, 0.3, 2.25], [-1.6, 0.3, 2.025],
+    [-2.5, 0.3, 2.25], [-2.3, 0.3, 2.025],
+    [-3.0, 0.3, 2.25], [-2.7, 0.3, 2.025],
+    [-3.0, 0.3, 1.8], [-2.7, 0.3, 1.8],
+    [-2.7, 0.0, 1.575], [-2.7, -0.3, 1.575], [-3.0, -0.3, 1.35], [-3.0, 0.0, 1.35],
+    [-2.5, 0.0, 1.125], [-2.5, -0.3, 1.125], [-2.65, -0.3, 0.9375], [-2.65, 0.0, 0.9375],
+    [-2.0, -0.3, 0.9], [-1.9, -0.3, 0.6], [-1.9, 0.0, 0.6],
+    [-3.0, 0.3, 1.35], [-2.7, 0.3, 1.575],
+    [-2.65, 0.3, 0.9375], [-2.5, 0.3, 1.125],
+    [-1.9, 0.3, 0.6], [-2.0, 0.3, 0.9],
+    [1.7, 0.0, 1.425], [1.7, -0.66, 1.425], [1.7, -0.66, 0.6], [1.7, 0.0, 0.6],
+    [2.6, 0.0, 1.425], [2.6, -0.66, 1.425], [3.1, -0.66, 0.825], [3.1, 0.0, 0.825],
+    [2.3, 0.0, 2.1], [2.3, -0.25, 2.1], [2.4, -0.25, 2.025], [2.4, 0.0, 2.025],
+    [2.7, 0.0, 2.4], [2.7, -0.25, 2.4], [3.3, -0.25, 2.4], [3.3, 0.0, 2.4],
+    [1.7, 0.66, 0.6], [1.7, 0.66, 1.425],
+    [3.1, 0.66, 0.825], [2.6, 0.66, 1.425],
+    [2.4, 0.25, 2.025], [2.3, 0.25, 2.1],
+    [3.3, 0.25, 2.4], [2.7, 0.25, 2.4],
+    [2.8, 0.0, 2.475], [2.8, -0.25, 2.475], [3.525, -0.25, 2.49375], [3.525, 0.0, 2.49375],
+    [2.9, 0.0, 2.475], [2.9, -0.15, 2.475], [3.45, -0.15, 2.5125], [3.45, 0.0, 2.5125],
+    [2.8, 0.0, 2.4], [2.8, -0.15, 2.4], [3.2, -0.15, 2.4], [3.2, 0.0, 2.4],
+    [3.525, 0.25, 2.49375], [2.8, 0.25, 2.475],
+    [3.45, 0.15, 2.5125], [2.9, 0.15, 2.475],
+    [3.2, 0.15, 2.4], [2.8, 0.15, 2.4],
+    [0.0, 0.0, 3.15], [0.0, -0.002, 3.15], [0.002, 0.0, 3.15],
+    [0.8, 0.0, 3.15], [0.8, -0.45, 3.15], [0.45, -0.8, 3.15], [0.0, -0.8, 3.15],
+    [0.0, 0.0, 2.85],
+    [0.2, 0.0, 2.7], [0.2, -0.112, 2.7], [0.112, -0.2, 2.7], [0.0, -0.2, 2.7],
+    [-0.002, 0.0, 3.15], [-0.45, -0.8, 3.15], [-0.8, -0.45, 3.15], [-0.8, 0.0, 3.15],
+    [-0.112, -0.2, 2.7], [-0.2, -0.112, 2.7], [-0.2, 0.0, 2.7],
+    [0.0, 0.002, 3.15], [-0.8, 0.45, 3.15], [-0.45, 0.8, 3.15], [0.0, 0.8, 3.15],
+    [-0.2, 0.112, 2.7], [-0.112, 0.2, 2.7], [0.0, 0.2, 2.7],
+    [0.45, 0.8, 3.15], [0.8, 0.45, 3.15],
+    [0.112, 0.2, 2.7], [0.2, 0.112, 2.7],
+    [0.4, 0.0, 2.55], [0.4, -0.224, 2.55], [0.224, -0.4, 2.55], [0.0, -0.4, 2.55],
+    [1.3, 0.0, 2.55], [1.3, -0.728, 2.55], [0.728, -1.3, 2.55], [0.0, -1.3, 2.55],
+    [1.3, 0.0, 2.4], [1.3, -0.728, 2.4], [0.728, -1.3, 2.4], [0.0, -1.3, 2.4],
+    [-0.224, -0.4, 2.55], [-0.4, -0.224, 2.55], [-0.4, 0.0, 2.55],
+    [-0.728, -1.3, 2.55], [-1.3, -0.728, 2.55], [-1.3, 0.0, 2.55],
+    [-0.728, -1.3, 2.4], [-1.3, -0.728, 2.4], [-1.3, 0.0, 2.4],
+    [-0.4, 0.224, 2.55], [-0.224, 0.4, 2.55], [0.0, 0.4, 2.55],
+    [-1.3, 0.728, 2.55], [-0.728, 1.3, 2.55], [0.0, 1.3, 2.55],
+    [-1.3, 0.728, 2.4], [-0.728, 1.3, 2.4], [0.0, 1.3, 2.4],
+    [0.224, 0.4, 2.55], [0.4, 0.224, 2.55],
+    [0.728, 1.3, 2.55], [1.3, 0.728, 2.55],
+    [0.728, 1.3, 2.4], [1.3, 0.728, 2.4],
+    [0.0, 0.0, 0.0], [1.5, 0.0, 0.15], [1.5, 0.84, 0.15], [0.84, 1.5, 0.15], [0.0, 1.5, 0.15],
+    [1.5, 0.0, 0.075], [1.5, 0.84, 0.075], [0.84, 1.5, 0.075], [0.0, 1.5, 0.075],
+    [1.425, 0.0, 0.0], [1.425, 0.798, 0.0], [0.798, 1.425, 0.0], [0.0, 1.425, 0.0],
+    [-0.84, 1.5, 0.15], [-1.5, 0.84, 0.15], [-1.5, 0.0, 0.15],
+    [-0.84, 1.5, 0.075], [-1.5, 0.84, 0.075], [-1.5, 0.0, 0.075],
+    [-0.798, 1.425, 0.0], [-1.425, 0.798, 0.0], [-1.425, 0.0, 0.0],
+    [-1.5, -0.84, 0.15], [-0.84, -1.5, 0.15], [0.0, -1.5, 0.15],
+    [-1.5, -0.84, 0.075], [-0.84, -1.5, 0.075], [0.0, -1.5, 0.075],
+    [-1.425, -0.798, 0.0], [-0.798, -1.425, 0.0], [0.0, -1.425, 0.0],
+    [0.84, -1.5, 0.15], [1.5, -0.84, 0.15],
+    [0.84, -1.5, 0.075], [1.5, -0.84, 0.075],
+    [0.798, -1.425, 0.0], [1.425, -0.798, 0.0],
+];
@@ -0,0 +1,90 @@
+use crate::objs::Vec3d;
+
+// Calls f(x, y, weights) for every pixel of a width x height target whose centre lies inside the
+// screen space triangle p. The barycentric weights are perspective correct: projected points carry
+// view depth in w (1 for orthographic projections), and weights divided by it interpolate in 3D.
+pub fn rasterize<F: FnMut(i64, i64, [f64; 3])>(p: &[Vec3d], width: usize, height: usize, mut f: F) {
+    rasterize_samples(p, width, height, &[(0.5, 0.5)], |x, y, _, weights| f(x, y, weights));
+}
+
+// Like rasterize, but tests coverage at each of the offsets within a pixel and calls f once for every
+// pixel with any covered, passing a bitmask of which. Weights are for the pixel centre when all
+// samples are covered, otherwise for the first covered sample, so they never reach past the triangle.
+pub fn rasterize_samples<F: FnMut(i64, i64, u32, [f64; 3])>(p: &[Vec3d], width: usize, height: usize, offsets: &[(f64, f64)], mut f: F) {
+    let (p0, p1, p2) = (p[0], p[1], p[2]);
+    let area = (p1.x - p0.x)*(p2.y - p0.y) - (p2.x - p0.x)*(p1.y - p0.y);
+    if area == 0.0 || width == 0 || height == 0 {
+        return;
+    }
+
+    let min_x = p0.x.min(p1.x).min(p2.x).floor().max(0.0) as i64;
+    let max_x = p0.x.max(p1.x).max(p2.x).ceil().min(width as f64 - 1.0) as i64;
+    let min_y = p0.y.min(p1.y).min(p2.y).floor().max(0.0) as i64;
+    let max_y = p0.y.max(p1.y).max(p2.y).ceil().min(height as f64 - 1.0) as i64;
+    let inv_w = [1.0/p0.w, 1.0/p1.w, 1.0/p2.w];
+    let full_mask = (1u32 << offsets.len()) - 1;
+
+    // Barycentric weights from signed sub-triangle areas; all share the sign of area when inside
+    let weights_at = |px: f64, py: f64| {
+        let w0 = ((p1.x - px)*(p2.y - py) - (p2.x - px)*(p1.y - py)) / area;
+        let w1 = ((p2.x - px)*(p0.y - py) - (p0.x - px)*(p2.y - py)) / area;
+        [w0, w1, 1.0 - w0 - w1]
+    };
+
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let mut mask = 0;
+            let mut first = None;
+            for (s, (ox, oy)) in offsets.iter().enumerate() {
+                let w = weights_at(x as f64 + ox, y as f64 + oy);
+                if w.iter().all(|w| *w >= 0.0) {
+                    mask |= 1 << s;
+                    first = first.or(Some(w));
+                }
+            }
+            let w = match first {
+                Some(_) if mask == full_mask && offsets.len() > 1 => weights_at(x as f64 + 0.5, y as f64 + 0.5),
+                Some(w) => w,
+                None => continue,
+            };
+            let (q0, q1, q2) = (w[0]*inv_w[0], w[1]*inv_w[1], w[2]*inv_w[2]);
+            let q = q0 + q1 + q2;
+            f(x, y, mask, [q0/q, q1/q, q2/q]);
+        }
+    }
+}
+
+// Xiaolin Wu's line widened to width pixels, calling f(x, y, coverage) for every pixel it touches.
+// Each step along the major axis covers the line's span across the minor axis, with the partly
+// covered pixels at either side and at the ends weighted by how much of them the line covers.
+// Pixel (x, y) is centred on (x + 0.5, y + 0.5), as in rasterize.
+pub fn smooth_line<F: FnMut(i64, i64, f64)>(from: (f64, f64), to: (f64, f64), width: f64, mut f: F) {
+    let ((mut x0, mut y0), (mut x1, mut y1)) = ((from.0 - 0.5, from.1 - 0.5), (to.0 - 0.5, to.1 - 0.5));
+    let steep = (y1 - y0).abs() > (x1 - x0).abs();
+    if steep {
+        (x0, y0, x1, y1) = (y0, x0, y1, x1);
+    }
+    if x0 > x1 {
+        (x0, y0, x1, y1) = (x1, y1, x0, y0);
+    }
+    let gradient = if x1 - x0 > 0.0 { (y1 - y0)/(x1 - x0) } else { 0.0 };
+    // Width across the line becomes a longer span across the minor axis as the line tilts
+    let half_span = 0.5*width.max(0.0)*(1.0 + gradient*gradient).sqrt();
+
+    for x in x0.floor() as i64..=x1.ceil() as i64 {
+        // Coverage along the line, partial at the two ends
+        let x_cover = ((x as f64 + 0.5).min(x1 + 0.5) - (x as f64 - 0.5).max(x0 - 0.5)).clamp(0.0, 1.0);
+        if x_cover <= 0.0 {
+            continue;
+        }
+        let y = y0 + gradient*(x as f64 - x0);
+        let (top, bottom) = (y - half_span, y + half_span);
+        for k in (top + 0.5).floor() as i64..=(bottom + 0.5).floor() as i64 {
+            let y_cover = (bottom.min(k as f64 + 0.5) - top.max(k as f64 - 0.5)).clamp(0.0, 1.0);
+            if y_cover > 0.0 {
+                let (px, py) = if steep { (k, x) } else { (x, k) };
+                f(px, py, x_cover*y_cover);
+            }
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use eframe::egui;
+use std::sync::Arc;
+
+use crate::animation::{ Animation, Target };
+use crate::bounds::{ Aabb, BoundingSphere };
+use crate::lod::Lod;
+use crate::objs::{ Matrix4x4, Mesh, Vec3d };
+use crate::morph;
+use crate::skin::{ self, Skin };
+
+pub struct Texture {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<egui::Color32>,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, pixels: Vec<egui::Color32>) -> Self {
+        Self { width, height, pixels }
+    }
+
+    // Nearest texel lookup with repeat wrapping; v runs down the image as in glTF
+    pub fn sample(&self, u: f64, v: f64) -> egui::Color32 {
+        if self.width == 0 || self.height == 0 {
+            return egui::Color32::WHITE;
+        }
+        let x = ((u - u.floor())*self.width as f64) as usize;
+        let y = ((v - v.floor())*self.height as f64) as usize;
+        self.pixels[y.min(self.height - 1)*self.width + x.min(self.width - 1)]
+    }
+}
+
+// Whether a material's alpha makes it see-through, as glTF's alphaMode; alpha is ignored when opaque
+#[derive(Copy, Clone, PartialEq, Default)]
+pub enum AlphaMode {
+    #[default]
+    Opaque,
+    Blend,
+}
+
+#[derive(Clone)]
+pub struct Material {
+    pub name: String,
+    // Linear RGBA multiplier applied to vertex colors and the texture; alpha is the opacity
+    pub base_color: [f64; 4],
+    pub base_color_texture: Option<Arc<Texture>>,
+    pub alpha_mode: AlphaMode,
+}
+
+impl Material {
+    pub fn new(name: String) -> Self {
+        Self { name, base_color: [1.0; 4], base_color_texture: None, alpha_mode: AlphaMode::Opaque }
+    }
+
+    // Opacity surfaces with this material are blended with, or None when they are drawn opaque
+    pub fn opacity(&self) -> Option<f64> {
+        (self.alpha_mode == AlphaMode::Blend).then_some(self.base_color[3].clamp(0.0, 1.0))
+    }
+}
+
+pub struct Node {
+    pub name: String,
+    // Transform relative to the parent node
+    pub transform: Matrix4x4,
+    // Indices into Scene::meshes drawn with this node's transform
+    pub meshes: Vec<usize>,
+    pub children: Vec<usize>,
+    // Index into Scene::skins deforming this node's meshes. Skinned meshes are placed by their
+    // joints, so the node's own transform doesn't apply to them.
+    pub skin: Option<usize>,
+}
+
+impl Node {
+    pub fn new(name: String) -> Self {
+        Self { name, transform: Matrix4x4::identity(), meshes: Vec::new(), children: Vec::new(), skin: None }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum LightKind {
+    // Parallel rays, like sunlight
+    Directional,
+    // Cone of light from a point; cone_angle is the half angle in radians where it fades out
+    Spot { position: Vec3d, cone_angle: f64 },
+}
+
+#[derive(Copy, Clone)]
+pub struct Light {
+    pub kind: LightKind,
+    // Unit direction the light travels in
+    pub direction: Vec3d,
+    pub intensity: f64,
+    pub casts_shadows: bool,
+}
+
+impl Light {
+    pub fn directional(direction: Vec3d, intensity: f64) -> Self {
+        let mut direction = direction;
+        direction.normalize();
+        Self { kind: LightKind::Directional, direction, intensity, casts_shadows: true }
+    }
+
+    pub fn spot(position: Vec3d, direction: Vec3d, cone_angle: f64, intensity: f64) -> Self {
+        let mut direction = direction;
+        direction.normalize();
+        Self { kind: LightKind::Spot { position, cone_angle }, direction, intensity, casts_shadows: true }
+    }
+
+    // The light as seen through an affine transform. The direction is one the light travels in,
+    // not a normal, so it follows the matrix itself and is renormalised after any scaling.
+    pub fn transformed(&self, mat: &Matrix4x4) -> Self {
+        let mut direction = mat.mul_mat_dir(&self.direction);
+        direction.normalize();
+        let kind = match self.kind {
+            LightKind::Directional => LightKind::Directional,
+            LightKind::Spot { position, cone_angle } => LightKind::Spot { position: mat.mul_mat_vec(&position), cone_angle },
+        };
+        Self { kind, direction, ..*self }
+    }
+}
+
+// Meshes and materials plus the node tree that places them
+pub struct Scene {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    pub nodes: Vec<Node>,
+    pub roots: Vec<usize>,
+    // In the same space as the root nodes
+    pub lights: Vec<Light>,
+    pub animations: Vec<Animation>,
+    pub skins: Vec<Skin>,
+    // Simplified versions of meshes to draw when they are small on screen, see add_lods
+    pub lods: Vec<Lod>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self { meshes: Vec::new(), materials: Vec::new(), nodes: Vec::new(), roots: Vec::new(), lights: Vec::new(), animations: Vec::new(), skins: Vec::new(), lods: Vec::new() }
+    }
+
+    // Adds a mesh under a new root node with an identity transform, returning the node index
+    pub fn add_mesh(&mut self, name: String, mesh: Mesh) -> usize {
+        self.meshes.push(mesh);
+        let mut node = Node::new(name);
+        node.meshes.push(self.meshes.len() - 1);
+        self.nodes.push(node);
+        self.roots.push(self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    // Moves everything from other into self, shifting its indices past the existing contents
+    pub fn append(&mut self, other: Scene) {
+        let (mesh_base, material_base, node_base, skin_base) = (self.meshes.len(), self.materials.len(), self.nodes.len(), self.skins.len());
+        for mut mesh in other.meshes {
+            mesh.material = mesh.material.map(|m| m + material_base);
+            self.meshes.push(mesh);
+        }
+        self.materials.extend(other.materials);
+        for mut node in other.nodes {
+            node.meshes.iter_mut().for_each(|m| *m += mesh_base);
+            node.children.iter_mut().for_each(|c| *c += node_base);
+            node.skin = node.skin.map(|s| s + skin_base);
+            self.nodes.push(node);
+        }
+        self.roots.extend(other.roots.iter().map(|r| r + node_base));
+        self.lights.extend(other.lights);
+        for mut animation in other.animations {
+            for channel in animation.channels.iter_mut() {
+                if let Target::Node(n) = &mut channel.target {
+                    *n += node_base;
+                }
+            }
+            self.animations.push(animation);
+        }
+        for mut skin in other.skins {
+            skin.joints.iter_mut().for_each(|j| *j += node_base);
+            self.skins.push(skin);
+        }
+        for mut lod in other.lods {
+            lod.mesh += mesh_base;
+            lod.levels.iter_mut().for_each(|l| l.mesh += mesh_base);
+            self.lods.push(lod);
+        }
+    }
+
+    // World transform of every node, identity for nodes no root reaches
+    pub fn node_worlds(&self) -> Vec<Matrix4x4> {
+        let mut worlds = vec![Matrix4x4::identity(); self.nodes.len()];
+        let mut stack: Vec<(usize, Matrix4x4)> = self.roots.iter().map(|r| (*r, Matrix4x4::identity())).collect();
+        while let Some((node_index, parent)) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let world = node.transform.mul_mat_mat(&parent);
+            for c in node.children.iter() {
+                stack.push((*c, world.clone()));
+            }
+            worlds[node_index] = world;
+        }
+        worlds
+    }
+
+    // Reshapes every deformable mesh: morph targets by their current weights, then skins to follow
+    // their joints as they are posed now
+    pub fn pose_meshes(&mut self) {
+        self.meshes.iter_mut().filter(|m| m.is_deformable()).for_each(morph::morph_mesh);
+        if self.skins.is_empty() {
+            return;
+        }
+        let worlds = self.node_worlds();
+        for node in self.nodes.iter() {
+            let joint_matrices = match node.skin.and_then(|s| self.skins.get(s)) {
+                Some(skin) => skin.joint_matrices(&worlds),
+                None => continue,
+            };
+            for m in node.meshes.iter() {
+                skin::skin_mesh(&mut self.meshes[*m], &joint_matrices);
+            }
+        }
+    }
+
+    // Indices of the meshes some node draws, in order and once each. Simplified levels of detail
+    // are drawn in place of their full mesh rather than by a node, so they are never among them.
+    pub fn drawn_meshes(&self) -> Vec<usize> {
+        let mut drawn: Vec<usize> = self.nodes.iter().flat_map(|n| n.meshes.iter().cloned()).collect();
+        drawn.sort_unstable();
+        drawn.dedup();
+        drawn
+    }
+
+    // Every (mesh index, world transform) pair reachable from the roots
+    pub fn world_meshes(&self) -> Vec<(usize, Matrix4x4)> {
+        let mut out = Vec::with_capacity(self.meshes.len());
+        let mut stack: Vec<(usize, Matrix4x4)> = self.roots.iter().map(|r| (*r, Matrix4x4::identity())).collect();
+        while let Some((node_index, parent)) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let world = node.transform.mul_mat_mat(&parent);
+            for m in node.meshes.iter() {
+                let skinned = node.skin.is_some() && self.meshes[*m].is_skinned();
+                out.push((*m, if skinned { Matrix4x4::identity() } else { world.clone() }));
+            }
+            for c in node.children.iter() {
+                stack.push((*c, world.clone()));
+            }
+        }
+        out
+    }
+
+    // World space box around every placed mesh
+    pub fn aabb(&self) -> Aabb {
+        self.world_meshes().iter().fold(Aabb::empty(), |acc, (m, mat)| acc.union(&self.meshes[*m].aabb().transformed(mat)))
+    }
+
+    // World space sphere around every placed vertex
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        let points: Vec<_> = self.world_meshes().iter()
+            .flat_map(|(m, mat)| self.meshes[*m].positions.iter().map(move |p| mat.mul_mat_vec(p)))
+            .collect();
+        BoundingSphere::from_points(&points)
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,131 @@
+use crate::bounds::BoundingSphere;
+use crate::objs::{ Matrix4x4, Mesh, Vec3d };
+use crate::raster;
+use crate::scene::{ Light, LightKind };
+
+// Depth of the surfaces nearest a light, seen from the light through the same projection and
+// rasterizer the main pass uses. Everything here works in the main pass's view space.
+pub struct ShadowMap {
+    pub size: usize,
+    // Light space depth per texel, infinite where nothing was drawn
+    pub depth: Vec<f64>,
+    // View space to light space, with the light at the origin looking down +z
+    pub mat_light: Matrix4x4,
+    // Light space to clip space: perspective for spot lights, orthographic for directional ones
+    pub mat_proj: Matrix4x4,
+    near: f64,
+    // View space width of a texel, per unit of depth for perspective maps
+    texel: f64,
+    perspective: bool,
+}
+
+impl ShadowMap {
+    // Fits the light's view to the sphere holding every caster and receiver. Spot lights that
+    // can't fit a frustum (cone too wide) cast no shadows.
+    pub fn new(light: &Light, bounds: &BoundingSphere, size: usize) -> Option<Self> {
+        let radius = bounds.radius.max(1e-6);
+        let dir = light.direction;
+        let up = if dir.y.abs() > 0.9 { Vec3d::new(1.0, 0.0, 0.0) } else { Vec3d::new(0.0, 1.0, 0.0) };
+        let look_from = |eye: Vec3d| {
+            let mut mat_point_at = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+            mat_point_at.make_point_at(&eye, &(eye + dir), &up);
+            mat_point_at.quick_inverse()
+        };
+
+        let mut mat_proj = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        let (mat_light, near, texel, perspective) = match light.kind {
+            LightKind::Directional => {
+                // Back off along the light until the whole sphere is in front of it
+                let eye = bounds.center - dir*(2.0*radius);
+                mat_proj.make_orthographic(radius, radius, radius, 3.0*radius);
+                (look_from(eye), radius, 2.0*radius/size as f64, false)
+            }
+            LightKind::Spot { position, cone_angle } => {
+                let fov = (2.0*cone_angle).to_degrees();
+                if fov <= 0.0 || fov >= 170.0 {
+                    return None;
+                }
+                let to_center = bounds.center - position;
+                let distance = to_center.dot(&to_center).sqrt();
+                let near = (distance - radius).max(radius*0.01);
+                let far = (distance + radius).max(near*2.0);
+                mat_proj.make_projection(fov, 1.0, near, far);
+                (look_from(position), near, 2.0/mat_proj.m[1][1]/size as f64, true)
+            }
+        };
+        Some(Self { size, depth: vec![f64::INFINITY; size*size], mat_light, mat_proj, near, texel, perspective })
+    }
+
+    // Draws a mesh's depth, with mat taking it to view space. Both faces are drawn so closed
+    // meshes shadow themselves however the light meets them.
+    pub fn draw_mesh(&mut self, mesh: &Mesh, mat: &Matrix4x4) {
+        let mat_light = mat.mul_mat_mat(&self.mat_light);
+        let light_positions: Vec<Vec3d> = mesh.positions.iter().map(|p| mat_light.mul_mat_vec(p)).collect();
+        let near_p = Vec3d::new(0.0, 0.0, self.near);
+        let near_n = Vec3d::new(0.0, 0.0, 1.0);
+
+        for i in 0..mesh.indices.len() {
+            let tri = mesh.assemble_tri(i, &light_positions, &[]);
+            if tri.p.iter().all(|p| p.z >= self.near) {
+                self.draw_tri(&tri.p);
+                continue;
+            }
+            for clipped in tri.triangle_clip_against_plane(&near_p, &near_n) {
+                self.draw_tri(&clipped.p);
+            }
+        }
+    }
+
+    fn draw_tri(&mut self, light_points: &[Vec3d]) {
+        let half = 0.5*self.size as f64;
+        let screen: Vec<Vec3d> = light_points.iter().map(|p| {
+            let mut projected = self.mat_proj.mul_mat_vec(p);
+            projected.x = (projected.x + 1.0)*half;
+            projected.y = (projected.y + 1.0)*half;
+            projected
+        }).collect();
+        let size = self.size;
+        let depth = &mut self.depth;
+        raster::rasterize(&screen, size, size, |x, y, w| {
+            let z = light_points[0].z*w[0] + light_points[1].z*w[1] + light_points[2].z*w[2];
+            let texel = &mut depth[y as usize*size + x as usize];
+            if z < *texel {
+                *texel = z;
+            }
+        });
+    }
+
+    // Fraction of the (2 pcf_radius + 1)^2 texels around the view space point p that see it from the
+    // light. The depth bias grows with a texel's footprint, the filter's reach and how steeply the light
+    // grazes the surface, given as the cosine n_dot_l, so lit faces don't shadow themselves.
+    pub fn lit_fraction(&self, p: &Vec3d, n_dot_l: f64, pcf_radius: usize) -> f64 {
+        let light_point = self.mat_light.mul_mat_vec(p);
+        if light_point.z < self.near {
+            return 1.0;
+        }
+        let projected = self.mat_proj.mul_mat_vec(&light_point);
+        let half = 0.5*self.size as f64;
+        let (u, v) = ((projected.x + 1.0)*half, (projected.y + 1.0)*half);
+
+        let texel = if self.perspective { self.texel*light_point.z } else { self.texel };
+        let n_dot_l = n_dot_l.clamp(0.05, 1.0);
+        let slope = (1.0 - n_dot_l*n_dot_l).sqrt()/n_dot_l;
+        // Neighbouring texels sample the surface further away, where a sloped face's depth has moved on
+        let radius = pcf_radius as i64;
+        let reach = 1.0 + 1.5*radius as f64;
+        let depth = light_point.z - texel*(1.0 + slope.min(4.0)*reach);
+
+        let (cx, cy) = (u.floor() as i64, v.floor() as i64);
+        let mut lit = 0;
+        for y in cy - radius..=cy + radius {
+            for x in cx - radius..=cx + radius {
+                // Outside the map nothing was drawn, so nothing is in the way
+                let in_map = x >= 0 && y >= 0 && x < self.size as i64 && y < self.size as i64;
+                if !in_map || depth <= self.depth[y as usize*self.size + x as usize] {
+                    lit += 1;
+                }
+            }
+        }
+        lit as f64/((2*radius + 1)*(2*radius + 1)) as f64
+    }
+}
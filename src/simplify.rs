@@ -0,0 +1,286 @@
+use std::cmp::Ordering;
+use std::collections::{ BinaryHeap, HashMap, HashSet };
+
+use crate::objs::{ lerp_color, Mesh, Vec2d, Vec3d };
+
+// Boundary edges are held in place by planes through them this many times as stiff as the
+// surface itself, so open borders don't shrink away
+const BOUNDARY_WEIGHT: f64 = 1000.0;
+
+// Symmetric 4x4 matrix summing squared distances to a set of planes, stored as its upper triangle:
+// a², ab, ac, ad, b², bc, bd, c², cd, d² for planes ax + by + cz + d = 0
+#[derive(Copy, Clone, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(n: &Vec3d, d: f64, weight: f64) -> Self {
+        let (a, b, c) = (n.x, n.y, n.z);
+        Self([a*a, a*b, a*c, a*d, b*b, b*c, b*d, c*c, c*d, d*d].map(|q| q*weight))
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for k in 0..10 {
+            self.0[k] += other.0[k];
+        }
+    }
+
+    // Sum of squared distances from p to the planes, by their weights
+    fn error(&self, p: &Vec3d) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        q[0]*x*x + 2.0*q[1]*x*y + 2.0*q[2]*x*z + 2.0*q[3]*x
+            + q[4]*y*y + 2.0*q[5]*y*z + 2.0*q[6]*y
+            + q[7]*z*z + 2.0*q[8]*z + q[9]
+    }
+
+    // The point closest to all the planes, if they pin one down
+    fn minimum(&self) -> Option<Vec3d> {
+        let q = &self.0;
+        let det = q[0]*(q[4]*q[7] - q[5]*q[5]) - q[1]*(q[1]*q[7] - q[5]*q[2]) + q[2]*(q[1]*q[5] - q[4]*q[2]);
+        let scale = (q[0] + q[4] + q[7]).powi(3);
+        if scale <= 0.0 || det.abs() <= 1e-10*scale {
+            return None;
+        }
+        // Cramer's rule on the upper 3x3 against -(ad, bd, cd)
+        let (r0, r1, r2) = (-q[3], -q[6], -q[8]);
+        let x = (r0*(q[4]*q[7] - q[5]*q[5]) - q[1]*(r1*q[7] - q[5]*r2) + q[2]*(r1*q[5] - q[4]*r2))/det;
+        let y = (q[0]*(r1*q[7] - r2*q[5]) - r0*(q[1]*q[7] - q[5]*q[2]) + q[2]*(q[1]*r2 - r1*q[2]))/det;
+        let z = (q[0]*(q[4]*r2 - q[5]*r1) - q[1]*(q[1]*r2 - r1*q[2]) + r0*(q[1]*q[5] - q[4]*q[2]))/det;
+        Some(Vec3d::new(x, y, z))
+    }
+}
+
+// A possible collapse of edge (a, b), valid while neither end has changed since it was costed
+struct Candidate {
+    cost: f64,
+    a: usize,
+    b: usize,
+    stamps: (usize, usize),
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed so the heap gives the cheapest collapse first. Equal costs, common on flat areas, are
+// broken by the edge so the result doesn't depend on the order candidates were pushed in.
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then_with(|| (other.a, other.b, other.stamps).cmp(&(self.a, self.b, self.stamps)))
+    }
+}
+
+impl Mesh {
+    // Reduces the mesh to at most target_triangles triangles by collapsing edges, cheapest first,
+    // where the cost of moving a vertex is its summed squared distance to the planes of the faces
+    // that met there originally (Garland and Heckbert's quadric error metric). Collapses that would
+    // fold a face over are skipped, so it can stop short of the target. Vertices split on texture
+    // or normal seams stay where they are so the seams don't open up. Deformed meshes are
+    // simplified in their rest pose.
+    pub fn simplify(&mut self, target_triangles: usize) {
+        if self.indices.len() <= target_triangles {
+            return;
+        }
+        if !self.rest_positions.is_empty() {
+            self.positions = self.rest_positions.clone();
+            self.normals = self.rest_normals.clone();
+        }
+        let vertex_count = self.positions.len();
+
+        // Vertices sharing a position with another are on a seam
+        let mut at_position: HashMap<[u64; 3], usize> = HashMap::new();
+        for p in self.positions.iter() {
+            *at_position.entry([p.x.to_bits(), p.y.to_bits(), p.z.to_bits()]).or_default() += 1;
+        }
+        let pinned: Vec<bool> = self.positions.iter().map(|p| at_position[&[p.x.to_bits(), p.y.to_bits(), p.z.to_bits()]] > 1).collect();
+
+        let mut quadrics = vec![Quadric::default(); vertex_count];
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (f, face) in self.indices.iter().enumerate() {
+            let [a, b, c] = face.map(|v| self.positions[v]);
+            let mut n = (b - a).cross(&(c - a));
+            // Weighted by area so big faces count for more than slivers
+            let area = 0.5*n.dot(&n).sqrt();
+            n.normalize();
+            let plane = Quadric::plane(&n, -n.dot(&a), area);
+            for v in face.iter() {
+                quadrics[*v].add(&plane);
+            }
+            for k in 0..3 {
+                let (a, b) = (face[k], face[(k + 1) % 3]);
+                edge_faces.entry((a.min(b), a.max(b))).or_default().push(f);
+            }
+        }
+        // Edges are visited in a fixed order since the quadric sums depend on it, if only by rounding
+        let mut edges: Vec<(usize, usize)> = edge_faces.keys().cloned().collect();
+        edges.sort_unstable();
+        for (a, b) in edges.iter().filter(|e| edge_faces[*e].len() == 1) {
+            let faces = &edge_faces[&(*a, *b)];
+            let face = self.indices[faces[0]];
+            let [p0, p1, p2] = face.map(|v| self.positions[v]);
+            let edge = self.positions[*b] - self.positions[*a];
+            let mut n = edge.cross(&(p1 - p0).cross(&(p2 - p0)));
+            n.normalize();
+            let plane = Quadric::plane(&n, -n.dot(&self.positions[*a]), BOUNDARY_WEIGHT*edge.dot(&edge));
+            quadrics[*a].add(&plane);
+            quadrics[*b].add(&plane);
+        }
+
+        let mut vertex_faces: Vec<Vec<usize>> = vec![Vec::new(); vertex_count];
+        for (f, face) in self.indices.iter().enumerate() {
+            face.iter().for_each(|v| vertex_faces[*v].push(f));
+        }
+        let mut face_alive = vec![true; self.indices.len()];
+        let mut vertex_alive = vec![true; vertex_count];
+        let mut stamps = vec![0; vertex_count];
+        let mut heap = BinaryHeap::new();
+        for (a, b) in edges.iter() {
+            if let Some(candidate) = self.candidate(*a, *b, &quadrics, &pinned, &stamps) {
+                heap.push(candidate);
+            }
+        }
+
+        let mut triangles = self.indices.len();
+        while triangles > target_triangles {
+            let Some(Candidate { a, b, stamps: (stamp_a, stamp_b), .. }) = heap.pop() else { break };
+            if !vertex_alive[a] || !vertex_alive[b] || stamps[a] != stamp_a || stamps[b] != stamp_b {
+                continue;
+            }
+            // b goes into a; the pinned end, if any, is always a
+            let (keep, gone) = if pinned[b] { (b, a) } else { (a, b) };
+            let target = self.collapse_point(keep, gone, &quadrics, &pinned);
+            if self.folds_over(keep, gone, &target, &vertex_faces, &face_alive) {
+                continue;
+            }
+
+            self.merge_attributes(keep, gone, &target);
+            for f in std::mem::take(&mut vertex_faces[gone]) {
+                if !face_alive[f] {
+                    continue;
+                }
+                if self.indices[f].contains(&keep) {
+                    face_alive[f] = false;
+                    triangles -= 1;
+                }
+                else {
+                    self.indices[f].iter_mut().filter(|v| **v == gone).for_each(|v| *v = keep);
+                    vertex_faces[keep].push(f);
+                }
+            }
+            vertex_faces[keep].retain(|f| face_alive[*f]);
+            vertex_alive[gone] = false;
+            let merged = quadrics[gone];
+            quadrics[keep].add(&merged);
+            stamps[keep] += 1;
+
+            let neighbours: HashSet<usize> = vertex_faces[keep].iter().flat_map(|f| self.indices[*f]).filter(|v| *v != keep).collect();
+            for n in neighbours {
+                if let Some(candidate) = self.candidate(keep, n, &quadrics, &pinned, &stamps) {
+                    heap.push(candidate);
+                }
+            }
+        }
+
+        // Only the surviving faces and the vertices they use are kept
+        self.polygons.clear();
+        self.indices = self.indices.iter().zip(face_alive.iter()).filter(|(_, alive)| **alive).map(|(f, _)| *f).collect();
+        let mut remap = vec![usize::MAX; vertex_count];
+        let mut keep = Vec::new();
+        for v in self.indices.iter().flatten() {
+            if remap[*v] == usize::MAX {
+                remap[*v] = keep.len();
+                keep.push(*v);
+            }
+        }
+        self.keep_vertices(&keep);
+        self.indices.iter_mut().for_each(|f| *f = f.map(|v| remap[v]));
+        for n in self.normals.iter_mut() {
+            n.normalize();
+        }
+    }
+
+    fn candidate(&self, a: usize, b: usize, quadrics: &[Quadric], pinned: &[bool], stamps: &[usize]) -> Option<Candidate> {
+        if pinned[a] && pinned[b] {
+            return None;
+        }
+        let (keep, gone) = if pinned[b] { (b, a) } else { (a, b) };
+        let p = self.collapse_point(keep, gone, quadrics, pinned);
+        let mut q = quadrics[keep];
+        q.add(&quadrics[gone]);
+        Some(Candidate { cost: q.error(&p).max(0.0), a, b, stamps: (stamps[a], stamps[b]) })
+    }
+
+    // Where the merged vertex goes: the pinned end if there is one, otherwise the best of the
+    // quadric's minimum, either end and the midpoint
+    fn collapse_point(&self, keep: usize, gone: usize, quadrics: &[Quadric], pinned: &[bool]) -> Vec3d {
+        let (pa, pb) = (self.positions[keep], self.positions[gone]);
+        if pinned[keep] {
+            return pa;
+        }
+        let mut q = quadrics[keep];
+        q.add(&quadrics[gone]);
+        let mut options = vec![pa, pb, (pa + pb)*0.5];
+        // Far from the edge means the planes were nearly parallel and the minimum is unreliable
+        if let Some(p) = q.minimum().filter(|p| { let d = *p - pa; let e = pb - pa; d.dot(&d) <= 4.0*e.dot(&e) }) {
+            options.push(p);
+        }
+        options.into_iter().min_by(|p, r| q.error(p).total_cmp(&q.error(r))).unwrap()
+    }
+
+    // Whether moving keep and gone to p would turn any face around them that survives the collapse
+    // past edge on or leave it with no area
+    fn folds_over(&self, keep: usize, gone: usize, p: &Vec3d, vertex_faces: &[Vec<usize>], face_alive: &[bool]) -> bool {
+        vertex_faces[keep].iter().chain(vertex_faces[gone].iter())
+            .filter(|f| face_alive[**f] && !(self.indices[**f].contains(&keep) && self.indices[**f].contains(&gone)))
+            .any(|f| {
+                let face = self.indices[*f];
+                let before = face.map(|v| self.positions[v]);
+                let after = face.map(|v| if v == keep || v == gone { *p } else { self.positions[v] });
+                let n_before = (before[1] - before[0]).cross(&(before[2] - before[0]));
+                let n_after = (after[1] - after[0]).cross(&(after[2] - after[0]));
+                n_after.dot(&n_before) <= 1e-3*n_before.dot(&n_before)
+            })
+    }
+
+    // Moves keep to p, with its other attributes blended from the two ends by how far along the edge p is
+    fn merge_attributes(&mut self, keep: usize, gone: usize, p: &Vec3d) {
+        let (pa, pb) = (self.positions[keep], self.positions[gone]);
+        let edge = pb - pa;
+        let t = if edge.dot(&edge) > 0.0 { ((*p - pa).dot(&edge)/edge.dot(&edge)).clamp(0.0, 1.0) } else { 0.0 };
+        let lerp = |a: Vec3d, b: Vec3d| a + (b - a)*t;
+        self.positions[keep] = *p;
+        if !self.normals.is_empty() {
+            self.normals[keep] = lerp(self.normals[keep], self.normals[gone]);
+        }
+        if !self.uvs.is_empty() {
+            let (a, b) = (self.uvs[keep], self.uvs[gone]);
+            self.uvs[keep] = Vec2d::new(a.u + (b.u - a.u)*t, a.v + (b.v - a.v)*t);
+        }
+        if !self.colors.is_empty() {
+            self.colors[keep] = lerp_color(self.colors[keep], self.colors[gone], t);
+        }
+        // Joint influences can't be blended slot by slot, so the nearer end's are taken whole
+        if self.is_skinned() && t > 0.5 {
+            self.joints[keep] = self.joints[gone];
+            self.weights[keep] = self.weights[gone];
+        }
+        for target in self.morph_targets.iter_mut() {
+            if target.positions.len() == self.positions.len() {
+                target.positions[keep] = lerp(target.positions[keep], target.positions[gone]);
+            }
+            if target.normals.len() == self.positions.len() {
+                target.normals[keep] = lerp(target.normals[keep], target.normals[gone]);
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::animation::{ axis_angle, Animation, Channel, Interpolation, Property, Target, Track };
+use crate::objs::{ Matrix4x4, Mesh, Vec3d };
+use crate::scene::{ Node, Scene };
+
+// Joints of a skeleton, which are ordinary scene nodes, and where each sat when the mesh was bound to it
+pub struct Skin {
+    pub name: String,
+    // Node indices; a mesh's joint attributes index into this list
+    pub joints: Vec<usize>,
+    // Per joint, takes the mesh from its rest space into the joint's space at bind time
+    pub inverse_binds: Vec<Matrix4x4>,
+}
+
+impl Skin {
+    pub fn new(name: String, joints: Vec<usize>) -> Self {
+        let inverse_binds = vec![Matrix4x4::identity(); joints.len()];
+        Self { name, joints, inverse_binds }
+    }
+
+    // Per joint, takes a rest pose vertex to the scene's root space as the joint is posed now,
+    // given every node's world transform
+    pub fn joint_matrices(&self, node_worlds: &[Matrix4x4]) -> Vec<Matrix4x4> {
+        self.joints.iter().zip(self.inverse_binds.iter()).map(|(j, inverse_bind)| inverse_bind.mul_mat_mat(&node_worlds[*j])).collect()
+    }
+}
+
+// Linear blend skinning: moves every vertex by its joints' matrices mixed by weight. Vertices are
+// moved on from where they are, so each frame starts from morph::morph_mesh's rest pose. Normals
+// are blended through each joint's normal matrix, so scaled joints keep them upright.
+pub fn skin_mesh(mesh: &mut Mesh, joint_matrices: &[Matrix4x4]) {
+    if !mesh.is_skinned() {
+        return;
+    }
+    let normal_matrices: Vec<Matrix4x4> = joint_matrices.iter().map(|m| m.normal_matrix()).collect();
+    let has_normals = mesh.normals.len() == mesh.positions.len();
+    for v in 0..mesh.positions.len() {
+        let (from, joints, weights) = (mesh.positions[v], mesh.joints[v], mesh.weights[v]);
+        let mut p = Vec3d::new(0.0, 0.0, 0.0);
+        let mut n = Vec3d::new(0.0, 0.0, 0.0);
+        let mut total = 0.0;
+        for (j, w) in joints.iter().zip(weights.iter()).filter(|(j, w)| **w != 0.0 && **j < joint_matrices.len()) {
+            p = p + joint_matrices[*j].mul_mat_vec(&from)*(*w);
+            if has_normals {
+                let mut joint_n = normal_matrices[*j].mul_mat_dir(&mesh.normals[v]);
+                joint_n.normalize();
+                n = n + joint_n*(*w);
+            }
+            total += w;
+        }
+        // Vertices nothing holds stay where they were; the rest are renormalised in case the
+        // weights don't quite add up to one
+        if total <= 0.0 {
+            continue;
+        }
+        mesh.positions[v] = p*(1.0/total);
+        if has_normals {
+            n.normalize();
+            mesh.normals[v] = n;
+        }
+    }
+}
+
+// A capped cylinder along y bound to a chain of bones running up its middle, with a "Bend"
+// animation that sways the chain from side to side. Each vertex is shared between the two bones
+// nearest it so the bends are smooth.
+pub fn skinned_cylinder(radius: f64, height: f64, segments: usize, bones: usize) -> Scene {
+    let bones = bones.max(1);
+    let h = height/2.0;
+    let bone_length = height/bones as f64;
+    let mut mesh = Mesh::cylinder(radius, height, segments, 4*bones);
+    (mesh.joints, mesh.weights) = mesh.positions.iter().map(|p| {
+        // Bone i starts at -h + i*bone_length; weights shift between neighbours across each bone's middle
+        let s = ((p.y + h)/bone_length - 0.5).clamp(0.0, (bones - 1) as f64);
+        let lower = (s.floor() as usize).min(bones - 1);
+        let upper = (lower + 1).min(bones - 1);
+        let t = s - lower as f64;
+        ([lower, upper, 0, 0], [1.0 - t, t, 0.0, 0.0])
+    }).unzip();
+
+    let mut scene = Scene::new();
+    let mut skin = Skin::new("Spine".to_string(), Vec::new());
+    for i in 0..bones {
+        let mut node = Node::new(format!("Bone {}", i));
+        let offset = if i == 0 { -h } else { bone_length };
+        node.transform.make_translation(0.0, offset, 0.0);
+        if i + 1 < bones {
+            node.children.push(i + 1);
+        }
+        scene.nodes.push(node);
+        let mut inverse_bind = Matrix4x4::identity();
+        inverse_bind.make_translation(0.0, h - i as f64*bone_length, 0.0);
+        skin.joints.push(i);
+        skin.inverse_binds.push(inverse_bind);
+    }
+    scene.roots.push(0);
+    scene.skins.push(skin);
+    let mesh_node = scene.add_mesh("Skinned cylinder".to_string(), mesh);
+    scene.nodes[mesh_node].skin = Some(0);
+
+    let mut bend = Animation::new("Bend".to_string());
+    let swing = 0.6/bones as f64;
+    let times = vec![0.0, 1.0, 2.0, 3.0, 4.0];
+    for joint in 1..bones {
+        let values = [0.0, swing, 0.0, -swing, 0.0].iter().map(|a| axis_angle([0.0, 0.0, 1.0], *a)).collect();
+        bend.channels.push(Channel { target: Target::Node(joint), property: Property::Rotation(Track::new(times.clone(), values, Interpolation::Cubic)) });
+    }
+    scene.animations.push(bend);
+    scene
+}
@@ -0,0 +1,61 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// Counters and stage timings for a single rendered frame
+#[derive(Copy, Clone, Default)]
+pub struct RenderStats {
+    pub tris_submitted: u64,
+    // Meshes and triangles skipped because their bounds were outside the view frustum
+    pub meshes_frustum_culled: u64,
+    pub tris_frustum_culled: u64,
+    // Meshes drawn with one of their simplified levels of detail
+    pub meshes_lod_reduced: u64,
+    pub tris_culled: u64,
+    pub tris_clipped: u64,
+    pub tris_rasterized: u64,
+    pub pixels_written: u64,
+    pub transform_time: Duration,
+    pub shadow_time: Duration,
+    pub clip_time: Duration,
+    pub sort_time: Duration,
+    pub raster_time: Duration,
+    pub resolve_time: Duration,
+    pub post_time: Duration,
+    pub frame_time: Duration,
+}
+
+impl RenderStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// Rolling window of engine frame times (in ms) for the overlay graph
+pub struct FrameHistory {
+    pub frame_times: VecDeque<f64>,
+    capacity: usize,
+}
+
+impl FrameHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self { frame_times: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    pub fn push(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == self.capacity {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time.as_secs_f64()*1000.0);
+    }
+
+    pub fn average(&self) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        self.frame_times.iter().sum::<f64>() / self.frame_times.len() as f64
+    }
+
+    pub fn max(&self) -> f64 {
+        self.frame_times.iter().cloned().fold(0.0, f64::max)
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use eframe::egui;
+
+use crate::objs::{ Mesh, Vec2d, Vec3d };
+use crate::scene::Scene;
+
+// Which old vertices a new vertex is made of, and by how much
+type Stencil = Vec<(usize, f64)>;
+// The vertices at either end, smallest first
+type Edge = (usize, usize);
+
+#[derive(Copy, Clone, PartialEq, Default)]
+pub enum SubdivisionScheme {
+    // Splits every triangle into four; for triangle meshes
+    #[default]
+    Loop,
+    // Splits every face into quads, one per corner; for quad meshes, though any faces will do
+    CatmullClark,
+}
+
+impl SubdivisionScheme {
+    pub const ALL: [SubdivisionScheme; 2] = [SubdivisionScheme::Loop, SubdivisionScheme::CatmullClark];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SubdivisionScheme::Loop => "Loop",
+            SubdivisionScheme::CatmullClark => "Catmull-Clark",
+        }
+    }
+}
+
+// Per vertex, what the edges around it say about where it sits: its neighbours across any edge,
+// those across sharp edges (boundaries, or edges shared by more than two faces), and how many
+// faces use it
+struct Neighbourhood {
+    neighbours: Vec<Vec<usize>>,
+    sharp: Vec<Vec<usize>>,
+    face_counts: Vec<usize>,
+}
+
+impl Neighbourhood {
+    fn new(vertex_count: usize, faces: &[Vec<usize>], edge_faces: &HashMap<Edge, Vec<usize>>) -> Self {
+        let mut neighbours = vec![Vec::new(); vertex_count];
+        let mut sharp = vec![Vec::new(); vertex_count];
+        for ((a, b), around) in edge_faces.iter() {
+            neighbours[*a].push(*b);
+            neighbours[*b].push(*a);
+            if around.len() != 2 {
+                sharp[*a].push(*b);
+                sharp[*b].push(*a);
+            }
+        }
+        let mut face_counts = vec![0; vertex_count];
+        faces.iter().flatten().for_each(|v| face_counts[*v] += 1);
+        Self { neighbours, sharp, face_counts }
+    }
+
+    // Vertices on a sharp edge follow the curve along it, as the cubic B-spline through their
+    // neighbours there, so both schemes agree with each other and with the mesh across the edge.
+    // Corners, where a single face or more than two sharp edges meet, stay where they are, as do
+    // vertices no face uses.
+    fn sharp_stencil(&self, v: usize) -> Option<Stencil> {
+        if self.neighbours[v].is_empty() {
+            return Some(vec![(v, 1.0)]);
+        }
+        match self.sharp[v].as_slice() {
+            [] => None,
+            [a, b] if self.face_counts[v] > 1 => Some(vec![(v, 0.75), (*a, 0.125), (*b, 0.125)]),
+            _ => Some(vec![(v, 1.0)]),
+        }
+    }
+}
+
+// Faces by the edge between consecutive corners, smallest vertex first
+fn edge_faces(faces: &[Vec<usize>]) -> HashMap<Edge, Vec<usize>> {
+    let mut edges: HashMap<Edge, Vec<usize>> = HashMap::new();
+    for (f, face) in faces.iter().enumerate() {
+        for k in 0..face.len() {
+            let (a, b) = (face[k], face[(k + 1) % face.len()]);
+            edges.entry((a.min(b), a.max(b))).or_default().push(f);
+        }
+    }
+    edges
+}
+
+// The edges in a fixed order, each with the index of the vertex that will split it
+fn number_edges(edges: &HashMap<Edge, Vec<usize>>, first: usize) -> (Vec<Edge>, HashMap<Edge, usize>) {
+    let mut order: Vec<Edge> = edges.keys().cloned().collect();
+    order.sort_unstable();
+    let numbers = order.iter().enumerate().map(|(i, e)| (*e, first + i)).collect();
+    (order, numbers)
+}
+
+impl Mesh {
+    pub fn subdivide(&mut self, scheme: SubdivisionScheme, levels: usize) {
+        match scheme {
+            SubdivisionScheme::Loop => self.subdivide_loop(levels),
+            SubdivisionScheme::CatmullClark => self.subdivide_catmull_clark(levels),
+        }
+    }
+
+    // Loop subdivision: each level puts a vertex on every edge and splits every triangle into four,
+    // then moves every vertex towards a weighted average of its neighbours, converging on a smooth
+    // surface. Boundaries are smoothed as curves and their corners are kept.
+    pub fn subdivide_loop(&mut self, levels: usize) {
+        for _ in 0..levels {
+            let faces: Vec<Vec<usize>> = self.indices.iter().map(|f| f.to_vec()).collect();
+            let edges = edge_faces(&faces);
+            let around = Neighbourhood::new(self.positions.len(), &faces, &edges);
+
+            let mut stencils: Vec<Stencil> = (0..self.positions.len()).map(|v| around.sharp_stencil(v).unwrap_or_else(|| {
+                let n = around.neighbours[v].len();
+                let beta = if n == 3 { 3.0/16.0 } else { 3.0/(8.0*n as f64) };
+                let mut stencil = vec![(v, 1.0 - n as f64*beta)];
+                stencil.extend(around.neighbours[v].iter().map(|u| (*u, beta)));
+                stencil
+            })).collect();
+            let (order, edge_vertex) = number_edges(&edges, stencils.len());
+            for (a, b) in order.iter() {
+                let across = &edges[&(*a, *b)];
+                if across.len() != 2 {
+                    stencils.push(vec![(*a, 0.5), (*b, 0.5)]);
+                    continue;
+                }
+                // Weighted towards the edge's own ends, with a little of the corner opposite on each side
+                let opposite = |f: usize| faces[f].iter().cloned().find(|v| v != a && v != b).unwrap();
+                stencils.push(vec![(*a, 0.375), (*b, 0.375), (opposite(across[0]), 0.125), (opposite(across[1]), 0.125)]);
+            }
+
+            let split = |a: usize, b: usize| edge_vertex[&(a.min(b), a.max(b))];
+            let triangles = self.indices.iter().flat_map(|[a, b, c]| {
+                let (ab, bc, ca) = (split(*a, *b), split(*b, *c), split(*c, *a));
+                [vec![*a, ab, ca], vec![ab, *b, bc], vec![ca, bc, *c], vec![ab, bc, ca]]
+            }).collect();
+            self.apply_stencils(&stencils, triangles);
+        }
+    }
+
+    // Catmull-Clark subdivision: each level puts a vertex in the middle of every face and on every
+    // edge and splits each face into one quad per corner, so after the first level the mesh is all
+    // quads. Works from the mesh's polygons when it has them, its triangles otherwise. Boundaries
+    // are smoothed as curves and their corners are kept.
+    pub fn subdivide_catmull_clark(&mut self, levels: usize) {
+        for _ in 0..levels {
+            let faces: Vec<Vec<usize>> = if self.polygons.is_empty() { self.indices.iter().map(|f| f.to_vec()).collect() } else { self.polygons.clone() };
+            let edges = edge_faces(&faces);
+            let around = Neighbourhood::new(self.positions.len(), &faces, &edges);
+            let vertex_count = self.positions.len();
+
+            let face_points: Vec<Stencil> = faces.iter().map(|f| f.iter().map(|v| (*v, 1.0/f.len() as f64)).collect()).collect();
+            let mut vertex_faces = vec![Vec::new(); vertex_count];
+            for (f, face) in faces.iter().enumerate() {
+                face.iter().for_each(|v| vertex_faces[*v].push(f));
+            }
+
+            // Interior vertices go to (F + 2R + (n - 3)v)/n, F being the average of the face points
+            // around them and R of the midpoints of their n edges
+            let mut stencils: Vec<Stencil> = (0..vertex_count).map(|v| around.sharp_stencil(v).unwrap_or_else(|| {
+                let n = around.neighbours[v].len() as f64;
+                let m = vertex_faces[v].len() as f64;
+                let mut stencil = vec![(v, (n - 3.0)/n + 1.0/n)];
+                stencil.extend(around.neighbours[v].iter().map(|u| (*u, 1.0/(n*n))));
+                for f in vertex_faces[v].iter() {
+                    stencil.extend(face_points[*f].iter().map(|(u, w)| (*u, w/(m*n))));
+                }
+                stencil
+            })).collect();
+            let face_base = stencils.len();
+            stencils.extend(face_points.iter().cloned());
+            let (order, edge_vertex) = number_edges(&edges, stencils.len());
+            for (a, b) in order.iter() {
+                let across = &edges[&(*a, *b)];
+                if across.len() != 2 {
+                    stencils.push(vec![(*a, 0.5), (*b, 0.5)]);
+                    continue;
+                }
+                // The average of the edge's ends and the face points either side
+                let mut stencil = vec![(*a, 0.25), (*b, 0.25)];
+                for f in across.iter() {
+                    stencil.extend(face_points[*f].iter().map(|(u, w)| (*u, w*0.25)));
+                }
+                stencils.push(stencil);
+            }
+
+            let split = |a: usize, b: usize| edge_vertex[&(a.min(b), a.max(b))];
+            let quads = faces.iter().enumerate().flat_map(|(f, face)| {
+                let k = face.len();
+                (0..k).map(move |i| {
+                    let (previous, corner, next) = (face[(i + k - 1) % k], face[i], face[(i + 1) % k]);
+                    vec![corner, split(corner, next), face_base + f, split(previous, corner)]
+                }).collect::<Vec<_>>()
+            }).collect();
+            self.apply_stencils(&stencils, quads);
+        }
+    }
+
+    // Rebuilds the vertices from stencils over the old ones and sets the new faces. Joint
+    // influences can't be mixed, so each new vertex takes those of the old one it leans on most.
+    // Normals are recomputed, smooth everywhere, since a subdivided surface has no creases.
+    fn apply_stencils(&mut self, stencils: &[Stencil], faces: Vec<Vec<usize>>) {
+        if !self.rest_positions.is_empty() {
+            self.positions = self.rest_positions.clone();
+            self.normals = self.rest_normals.clone();
+        }
+        let mix = |list: &[Vec3d], stencil: &Stencil| stencil.iter().fold(Vec3d::new(0.0, 0.0, 0.0), |acc, (v, w)| acc + list[*v]*(*w));
+        let had_normals = !self.normals.is_empty();
+        let old_count = self.positions.len();
+        self.positions = stencils.iter().map(|s| mix(&self.positions, s)).collect();
+        if !self.uvs.is_empty() {
+            self.uvs = stencils.iter().map(|s| s.iter().fold(Vec2d::new(0.0, 0.0), |acc, (v, w)| Vec2d::new(acc.u + self.uvs[*v].u*w, acc.v + self.uvs[*v].v*w))).collect();
+        }
+        if !self.colors.is_empty() {
+            self.colors = stencils.iter().map(|s| {
+                let channel = |c: usize| s.iter().map(|(v, w)| self.colors[*v].to_array()[c] as f64*w).sum::<f64>().round().clamp(0.0, 255.0) as u8;
+                egui::Color32::from_rgba_premultiplied(channel(0), channel(1), channel(2), channel(3))
+            }).collect();
+        }
+        if self.is_skinned() {
+            let heaviest = |s: &Stencil| s.iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap().0;
+            (self.joints, self.weights) = stencils.iter().map(|s| (self.joints[heaviest(s)], self.weights[heaviest(s)])).unzip();
+        }
+        // Targets may move only the normals, or only the positions
+        for target in self.morph_targets.iter_mut() {
+            if target.positions.len() == old_count {
+                target.positions = stencils.iter().map(|s| mix(&target.positions, s)).collect();
+            }
+            if target.normals.len() == old_count {
+                target.normals = stencils.iter().map(|s| mix(&target.normals, s)).collect();
+            }
+        }
+        self.normals.clear();
+        self.rest_positions.clear();
+        self.rest_normals.clear();
+        self.set_polygons(faces);
+        if had_normals {
+            self.compute_smooth_normals(180.0);
+        }
+    }
+}
+
+impl Scene {
+    // Subdivides every mesh a node draws. Their simplified levels of detail no longer match, so
+    // they are dropped.
+    pub fn subdivide(&mut self, scheme: SubdivisionScheme, levels: usize) {
+        let drawn = self.drawn_meshes();
+        for m in drawn.iter() {
+            self.meshes[*m].subdivide(scheme, levels);
+        }
+        self.lods.retain(|l| !drawn.contains(&l.mesh));
+    }
+}
@@ -0,0 +1,29 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::objs::{ Mesh, Vec3d };
+
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+// Opens path for writing and hands a buffered writer to write, flushing it afterwards
+pub(crate) fn save_with<P: AsRef<Path>>(path: P, write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write(&mut out)?;
+    out.flush()
+}
+
+// Unit normal of triangle i from its winding, or zero for a degenerate triangle
+pub(crate) fn face_normal(mesh: &Mesh, i: usize) -> Vec3d {
+    let [a, b, c] = mesh.indices[i];
+    let n = (mesh.positions[b] - mesh.positions[a]).cross(&(mesh.positions[c] - mesh.positions[a]));
+    let len = n.dot(&n).sqrt();
+    if len > 0.0 {
+        Vec3d::new(n.x/len, n.y/len, n.z/len)
+    }
+    else {
+        Vec3d::new(0.0, 0.0, 0.0)
+    }
+}
@@ -0,0 +1,39 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::objs::Mesh;
+use crate::writers::save_with;
+
+pub fn save_obj<P: AsRef<Path>>(mesh: &Mesh, path: P) -> io::Result<()> {
+    save_with(path, |out| write_obj(mesh, out))
+}
+
+// Attribute lists are written in vertex order, so every face corner uses the same
+// index for its position, texture coordinate and normal
+pub fn write_obj<W: Write>(mesh: &Mesh, out: &mut W) -> io::Result<()> {
+    writeln!(out, "# {} vertices, {} faces", mesh.positions.len(), mesh.indices.len())?;
+    for (i, p) in mesh.positions.iter().enumerate() {
+        match mesh.colors.get(i) {
+            // Vertex colors use the common "v x y z r g b" extension
+            Some(c) => writeln!(out, "v {} {} {} {} {} {}", p.x, p.y, p.z, c.r() as f64/255.0, c.g() as f64/255.0, c.b() as f64/255.0)?,
+            None => writeln!(out, "v {} {} {}", p.x, p.y, p.z)?,
+        }
+    }
+    for uv in mesh.uvs.iter() {
+        writeln!(out, "vt {} {}", uv.u, uv.v)?;
+    }
+    for n in mesh.normals.iter() {
+        writeln!(out, "vn {} {} {}", n.x, n.y, n.z)?;
+    }
+
+    let corner = |i: usize| match (mesh.uvs.is_empty(), mesh.normals.is_empty()) {
+        (true, true) => format!("{}", i + 1),
+        (false, true) => format!("{0}/{0}", i + 1),
+        (true, false) => format!("{0}//{0}", i + 1),
+        (false, false) => format!("{0}/{0}/{0}", i + 1),
+    };
+    for [a, b, c] in mesh.indices.iter() {
+        writeln!(out, "f {} {} {}", corner(*a), corner(*b), corner(*c))?;
+    }
+    Ok(())
+}
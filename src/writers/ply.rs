@@ -0,0 +1,131 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::objs::Mesh;
+use crate::writers::save_with;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+pub fn save_ply<P: AsRef<Path>>(mesh: &Mesh, path: P, format: PlyFormat) -> io::Result<()> {
+    save_with(path, |out| write_ply(mesh, out, format))
+}
+
+// Positions, normals and texture coordinates are written as doubles so nothing is lost
+// on a round trip; colors are RGBA bytes and faces are triangle lists
+pub fn write_ply<W: Write>(mesh: &Mesh, out: &mut W, format: PlyFormat) -> io::Result<()> {
+    let (has_normals, has_colors, has_uvs) = (!mesh.normals.is_empty(), !mesh.colors.is_empty(), !mesh.uvs.is_empty());
+
+    writeln!(out, "ply")?;
+    writeln!(out, "format {} 1.0", match format {
+        PlyFormat::Ascii => "ascii",
+        PlyFormat::BinaryLittleEndian => "binary_little_endian",
+        PlyFormat::BinaryBigEndian => "binary_big_endian",
+    })?;
+    writeln!(out, "element vertex {}", mesh.positions.len())?;
+    for name in ["x", "y", "z"] {
+        writeln!(out, "property double {}", name)?;
+    }
+    if has_normals {
+        for name in ["nx", "ny", "nz"] {
+            writeln!(out, "property double {}", name)?;
+        }
+    }
+    if has_colors {
+        for name in ["red", "green", "blue", "alpha"] {
+            writeln!(out, "property uchar {}", name)?;
+        }
+    }
+    if has_uvs {
+        for name in ["s", "t"] {
+            writeln!(out, "property double {}", name)?;
+        }
+    }
+    writeln!(out, "element face {}", mesh.indices.len())?;
+    writeln!(out, "property list uchar uint vertex_indices")?;
+    writeln!(out, "end_header")?;
+
+    let mut body = BodyWriter { format, out, mid_line: false };
+    for (i, p) in mesh.positions.iter().enumerate() {
+        let mut floats = vec![p.x, p.y, p.z];
+        if has_normals {
+            let n = mesh.normals[i];
+            floats.extend([n.x, n.y, n.z]);
+        }
+        for f in floats {
+            body.double(f)?;
+        }
+        if has_colors {
+            for c in mesh.colors[i].to_srgba_unmultiplied() {
+                body.uchar(c)?;
+            }
+        }
+        if has_uvs {
+            body.double(mesh.uvs[i].u)?;
+            body.double(mesh.uvs[i].v)?;
+        }
+        body.end_line()?;
+    }
+    for idx in mesh.indices.iter() {
+        body.uchar(3)?;
+        for v in idx.iter() {
+            let v = u32::try_from(*v).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "vertex index does not fit in a PLY uint"))?;
+            body.uint(v)?;
+        }
+        body.end_line()?;
+    }
+    Ok(())
+}
+
+// Writes scalars as space separated text or packed bytes depending on the format
+struct BodyWriter<'a, W: Write> {
+    format: PlyFormat,
+    out: &'a mut W,
+    // Whether the current ASCII line already has a value on it
+    mid_line: bool,
+}
+
+impl<'a, W: Write> BodyWriter<'a, W> {
+    fn text(&mut self, value: String) -> io::Result<()> {
+        let sep = if self.mid_line { " " } else { "" };
+        self.mid_line = true;
+        write!(self.out, "{}{}", sep, value)
+    }
+
+    fn double(&mut self, value: f64) -> io::Result<()> {
+        match self.format {
+            PlyFormat::Ascii => self.text(value.to_string()),
+            PlyFormat::BinaryLittleEndian => self.out.write_all(&value.to_le_bytes()),
+            PlyFormat::BinaryBigEndian => self.out.write_all(&value.to_be_bytes()),
+        }
+    }
+
+    fn uint(&mut self, value: u32) -> io::Result<()> {
+        match self.format {
+            PlyFormat::Ascii => self.text(value.to_string()),
+            PlyFormat::BinaryLittleEndian => self.out.write_all(&value.to_le_bytes()),
+            PlyFormat::BinaryBigEndian => self.out.write_all(&value.to_be_bytes()),
+        }
+    }
+
+    fn uchar(&mut self, value: u8) -> io::Result<()> {
+        match self.format {
+            PlyFormat::Ascii => self.text(value.to_string()),
+            _ => self.out.write_all(&[value]),
+        }
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        match self.format {
+            PlyFormat::Ascii => {
+                self.mid_line = false;
+                writeln!(self.out)
+            }
+            _ => Ok(()),
+        }
+    }
+}
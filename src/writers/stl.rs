@@ -0,0 +1,65 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::objs::Mesh;
+use crate::writers::{ face_normal, save_with };
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum StlFormat {
+    Ascii,
+    Binary,
+}
+
+pub fn save_stl<P: AsRef<Path>>(mesh: &Mesh, path: P, format: StlFormat) -> io::Result<()> {
+    save_with(path, |out| write_stl(mesh, out, format))
+}
+
+// STL only stores positions, each facet carrying the normal of its winding
+pub fn write_stl<W: Write>(mesh: &Mesh, out: &mut W, format: StlFormat) -> io::Result<()> {
+    match format {
+        StlFormat::Ascii => write_ascii(mesh, out),
+        StlFormat::Binary => write_binary(mesh, out),
+    }
+}
+
+fn write_ascii<W: Write>(mesh: &Mesh, out: &mut W) -> io::Result<()> {
+    writeln!(out, "solid mesh")?;
+    for (i, idx) in mesh.indices.iter().enumerate() {
+        let n = face_normal(mesh, i);
+        writeln!(out, "  facet normal {} {} {}", n.x, n.y, n.z)?;
+        writeln!(out, "    outer loop")?;
+        for v in idx.iter() {
+            let p = mesh.positions[*v];
+            writeln!(out, "      vertex {} {} {}", p.x, p.y, p.z)?;
+        }
+        writeln!(out, "    endloop")?;
+        writeln!(out, "  endfacet")?;
+    }
+    writeln!(out, "endsolid mesh")
+}
+
+fn write_binary<W: Write>(mesh: &Mesh, out: &mut W) -> io::Result<()> {
+    let count = u32::try_from(mesh.indices.len()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many triangles for binary STL"))?;
+
+    // The header must not start with "solid" or readers may take the file for ASCII
+    let mut header = [b' '; 80];
+    header[..10].copy_from_slice(b"binary STL");
+    out.write_all(&header)?;
+    out.write_all(&count.to_le_bytes())?;
+
+    let mut facet = [0u8; 50];
+    for (i, idx) in mesh.indices.iter().enumerate() {
+        let n = face_normal(mesh, i);
+        let mut values = vec![n.x, n.y, n.z];
+        for v in idx.iter() {
+            let p = mesh.positions[*v];
+            values.extend([p.x, p.y, p.z]);
+        }
+        for (k, value) in values.iter().enumerate() {
+            facet[k*4..k*4 + 4].copy_from_slice(&(*value as f32).to_le_bytes());
+        }
+        // Trailing two bytes are the attribute byte count, left at zero
+        out.write_all(&facet)?;
+    }
+    Ok(())
+}
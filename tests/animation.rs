@@ -0,0 +1,223 @@
+mod common;
+
+use std::f64::consts::FRAC_PI_2;
+
+use r3de::animation::{ axis_angle, Animation, Clock, Interpolation, Property, Target, Track, Trs };
+use r3de::loaders::gltf::parse_gltf;
+use r3de::objs::Matrix4x4;
+use common::{ base64, close };
+
+fn ramp(interpolation: Interpolation) -> Track<1> {
+    Track::new(vec![0.0, 1.0, 2.0], vec![[0.0], [2.0], [0.0]], interpolation)
+}
+
+#[test]
+fn tracks_pass_through_their_keys() {
+    for interpolation in [Interpolation::Step, Interpolation::Linear, Interpolation::Cubic] {
+        let track = ramp(interpolation);
+        for (t, v) in [(0.0, 0.0), (1.0, 2.0), (2.0, 0.0), (-1.0, 0.0), (3.0, 0.0)] {
+            assert!(close(track.sample(t)[0], v), "{} at {}", track.sample(t)[0], t);
+        }
+    }
+}
+
+#[test]
+fn interpolation_modes_differ_between_keys() {
+    assert!(close(ramp(Interpolation::Step).sample(0.5)[0], 0.0));
+    assert!(close(ramp(Interpolation::Linear).sample(0.5)[0], 1.0));
+    // The slope at the middle key is flat, so the curve rises faster early on than a straight line
+    let cubic = ramp(Interpolation::Cubic).sample(0.5)[0];
+    assert!(cubic > 1.0 && cubic < 2.0, "{}", cubic);
+}
+
+#[test]
+fn rotations_slerp_at_constant_speed() {
+    let track = Track::new(vec![0.0, 1.0], vec![axis_angle([0.0, 1.0, 0.0], 0.0), axis_angle([0.0, 1.0, 0.0], FRAC_PI_2)], Interpolation::Linear);
+    for u in [0.25, 0.5, 0.75] {
+        let q = track.sample_rotation(u);
+        let angle = 2.0*q[1].atan2(q[3]);
+        assert!(close(angle, u*FRAC_PI_2), "{} at {}", angle, u);
+    }
+}
+
+#[test]
+fn clock_wraps_when_looping_and_stops_otherwise() {
+    let mut clock = Clock::new();
+    clock.advance(2.5, 2.0);
+    assert!(close(clock.time, 0.5));
+    clock.speed = -1.0;
+    clock.advance(1.0, 2.0);
+    assert!(close(clock.time, 1.5));
+
+    clock.looping = false;
+    clock.speed = 2.0;
+    clock.advance(1.0, 2.0);
+    assert!(close(clock.time, 2.0) && !clock.playing);
+    clock.advance(1.0, 2.0);
+    assert!(close(clock.time, 2.0));
+
+    // Playing a finished clip again starts it over from whichever end it is played from
+    clock.play(2.0);
+    assert!(clock.playing && clock.time == 0.0);
+    clock.advance(0.5, 2.0);
+    assert!(close(clock.time, 1.0));
+    clock.speed = -1.0;
+    clock.advance(2.0, 2.0);
+    assert!(clock.time == 0.0 && !clock.playing);
+    clock.play(2.0);
+    assert!(clock.playing && clock.time == 2.0);
+    // but not when there's time left to play
+    clock.pause();
+    clock.seek(1.0);
+    clock.play(2.0);
+    assert!(clock.time == 1.0);
+}
+
+#[test]
+fn clock_at_zero_speed_holds_still_without_stopping() {
+    let mut clock = Clock::new();
+    clock.looping = false;
+    clock.speed = 0.0;
+    clock.advance(1.0, 2.0);
+    assert!(clock.playing && clock.time == 0.0);
+    clock.seek(2.0);
+    clock.advance(1.0, 2.0);
+    assert!(clock.playing && clock.time == 2.0);
+    // and picks up again once it is given a speed
+    clock.seek(0.0);
+    clock.speed = 1.0;
+    clock.advance(0.5, 2.0);
+    assert!(clock.playing && close(clock.time, 0.5));
+}
+
+#[test]
+fn trs_survives_a_trip_through_a_matrix() {
+    let trs = Trs { translation: [1.0, -2.0, 3.0], rotation: axis_angle([0.6, 0.0, 0.8], 2.5), scale: [2.0, 0.5, 1.5] };
+    let back = Trs::from_matrix(&trs.to_matrix());
+    let sign = if back.rotation[3]*trs.rotation[3] < 0.0 { -1.0 } else { 1.0 };
+    for k in 0..3 {
+        assert!(close(back.translation[k], trs.translation[k]));
+        assert!(close(back.scale[k], trs.scale[k]));
+    }
+    for k in 0..4 {
+        assert!(close(back.rotation[k]*sign, trs.rotation[k]));
+    }
+    let identity = Trs::from_matrix(&Matrix4x4::identity());
+    assert!(close(identity.rotation[3], 1.0) && close(identity.scale[0], 1.0));
+}
+
+#[test]
+fn turntable_goes_all_the_way_round() {
+    let turntable = Animation::turntable(12.0);
+    assert!(close(turntable.duration(), 12.0));
+    let rest = Trs::identity();
+    let half = turntable.pose(Target::Scene, &rest, 6.0).to_matrix();
+    assert!(close(half.m[0][0], -1.0) && close(half.m[2][2], -1.0));
+    let whole = turntable.pose(Target::Scene, &rest, 12.0).to_matrix();
+    assert!(close(whole.m[0][0], 1.0) && close(whole.m[2][2], 1.0));
+}
+
+#[test]
+fn gltf_animations_are_loaded() {
+    let floats = [0.0f32, 2.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.70710677, 0.0, 0.70710677];
+    let bytes: Vec<u8> = floats.iter().flat_map(|f| f.to_le_bytes()).collect();
+    let doc = format!(r#"{{
+        "asset": {{ "version": "2.0" }},
+        "nodes": [{{ "name": "spinner", "translation": [0, 1, 0] }}],
+        "buffers": [{{ "byteLength": 40, "uri": "data:application/octet-stream;base64,{}" }}],
+        "bufferViews": [{{ "buffer": 0, "byteLength": 8 }}, {{ "buffer": 0, "byteOffset": 8, "byteLength": 32 }}],
+        "accessors": [
+            {{ "bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR" }},
+            {{ "bufferView": 1, "componentType": 5126, "count": 2, "type": "VEC4" }}
+        ],
+        "animations": [{{
+            "name": "spin",
+            "samplers": [{{ "input": 0, "output": 1, "interpolation": "LINEAR" }}],
+            "channels": [{{ "sampler": 0, "target": {{ "node": 0, "path": "rotation" }} }}]
+        }}]
+    }}"#, base64(&bytes));
+    let scene = parse_gltf(doc.as_bytes(), None).unwrap();
+
+    assert_eq!(scene.animations.len(), 1);
+    let animation = &scene.animations[0];
+    assert_eq!(animation.name, "spin");
+    assert!(close(animation.duration(), 2.0));
+    assert!(matches!(animation.channels[0].property, Property::Rotation(_)));
+
+    // Halfway through the node has turned 45 degrees about y and kept its translation
+    let rest = Trs::from_matrix(&scene.nodes[0].transform);
+    let pose = animation.pose(Target::Node(0), &rest, 1.0);
+    let angle = 2.0*pose.rotation[1].atan2(pose.rotation[3]);
+    assert!((angle - FRAC_PI_2/2.0).abs() < 1e-6, "{}", angle);
+    assert!(close(pose.translation[1], 1.0));
+}
+
+#[test]
+fn gltf_animation_with_too_few_values_is_an_error() {
+    let floats = [0.0f32, 1.0, 0.0, 0.0, 0.0];
+    let bytes: Vec<u8> = floats.iter().flat_map(|f| f.to_le_bytes()).collect();
+    let doc = format!(r#"{{
+        "asset": {{ "version": "2.0" }},
+        "nodes": [{{}}],
+        "buffers": [{{ "byteLength": 20, "uri": "data:application/octet-stream;base64,{}" }}],
+        "bufferViews": [{{ "buffer": 0, "byteLength": 8 }}, {{ "buffer": 0, "byteOffset": 8, "byteLength": 12 }}],
+        "accessors": [
+            {{ "bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR" }},
+            {{ "bufferView": 1, "componentType": 5126, "count": 1, "type": "VEC3" }}
+        ],
+        "animations": [{{
+            "samplers": [{{ "input": 0, "output": 1 }}],
+            "channels": [{{ "sampler": 0, "target": {{ "node": 0, "path": "translation" }} }}]
+        }}]
+    }}"#, base64(&bytes));
+    assert!(parse_gltf(doc.as_bytes(), None).is_err());
+}
+
+#[test]
+fn gltf_morph_targets_and_weight_channels_are_loaded() {
+    // One triangle with a target raising it by 1, keyed from weight 0 to 1 over a second
+    let floats = [
+        0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+        0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0,
+        0.0, 1.0,
+        0.0, 1.0,
+    ];
+    let bytes: Vec<u8> = floats.iter().flat_map(|f| f.to_le_bytes()).collect();
+    let doc = format!(r#"{{
+        "asset": {{ "version": "2.0" }},
+        "meshes": [{{
+            "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "targets": [{{ "POSITION": 1 }}] }}],
+            "weights": [0.25],
+            "extras": {{ "targetNames": ["Raise"] }}
+        }}],
+        "nodes": [{{ "mesh": 0 }}],
+        "buffers": [{{ "byteLength": 88, "uri": "data:application/octet-stream;base64,{}" }}],
+        "bufferViews": [
+            {{ "buffer": 0, "byteLength": 36 }}, {{ "buffer": 0, "byteOffset": 36, "byteLength": 36 }},
+            {{ "buffer": 0, "byteOffset": 72, "byteLength": 8 }}, {{ "buffer": 0, "byteOffset": 80, "byteLength": 8 }}
+        ],
+        "accessors": [
+            {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }},
+            {{ "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" }},
+            {{ "bufferView": 2, "componentType": 5126, "count": 2, "type": "SCALAR" }},
+            {{ "bufferView": 3, "componentType": 5126, "count": 2, "type": "SCALAR" }}
+        ],
+        "animations": [{{
+            "samplers": [{{ "input": 2, "output": 3 }}],
+            "channels": [{{ "sampler": 0, "target": {{ "node": 0, "path": "weights" }} }}]
+        }}]
+    }}"#, base64(&bytes));
+    let mut scene = parse_gltf(doc.as_bytes(), None).unwrap();
+
+    let mesh = &scene.meshes[0];
+    assert_eq!(mesh.morph_targets.len(), 1);
+    assert_eq!(mesh.morph_targets[0].name, "Raise");
+    assert!(close(mesh.morph_weights[0], 0.25));
+
+    let animation = &scene.animations[0];
+    assert!(matches!(animation.channels[0].property, Property::Weight(0, _)));
+    animation.morph_weights(Target::Node(0), &mut scene.meshes[0].morph_weights, 0.5);
+    assert!(close(scene.meshes[0].morph_weights[0], 0.5));
+    scene.pose_meshes();
+    assert!(scene.meshes[0].positions.iter().all(|p| close(p.y, 0.5)));
+}
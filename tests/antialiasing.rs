@@ -0,0 +1,75 @@
+use eframe::egui::Color32;
+
+use r3de::color::ToneMapping;
+use r3de::framebuffer::{ AaMode, Framebuffer };
+use r3de::objs::Vec3d;
+use r3de::raster;
+
+// Right triangle whose hypotenuse runs corner to corner through a 4 x 4 output, in the
+// coordinates of a target scale times larger
+fn diagonal_half(scale: usize) -> Vec<Vec3d> {
+    let s = 4.0*scale as f64;
+    vec![Vec3d::new(0.0, 0.0, 1.0), Vec3d::new(s, 0.0, 1.0), Vec3d::new(0.0, s, 1.0)]
+}
+
+fn draw(mode: AaMode) -> Vec<Color32> {
+    let mut target = Framebuffer::new([4, 4], mode);
+    let (width, height) = (target.width, target.height);
+    let mut covered = Vec::new();
+    raster::rasterize_samples(&diagonal_half(mode.scale()), width, height, mode.sample_offsets(), |x, y, mask, _| covered.push((x, y, mask)));
+    for (x, y, mask) in covered {
+        target.put(x, y, mask, [1.0; 3]);
+    }
+    let mut out = vec![Color32::BLACK; 16];
+    target.resolve(&mut out, ToneMapping::Clamp, 0.0);
+    out
+}
+
+#[test]
+fn aliased_edges_are_all_or_nothing() {
+    assert!(draw(AaMode::Off).iter().all(|c| c.r() == 0 || c.r() == 255));
+}
+
+#[test]
+fn every_mode_blends_pixels_on_the_edge() {
+    for mode in [AaMode::Ssaa2x, AaMode::Ssaa4x, AaMode::Msaa4x] {
+        let out = draw(mode);
+        // Pixel (3, 0) is cut by the diagonal, (0, 0) is inside and (3, 3) outside
+        assert!(out[3].r() > 0 && out[3].r() < 255, "{}", mode.label());
+        assert_eq!(out[0].r(), 255, "{}", mode.label());
+        assert_eq!(out[15].r(), 0, "{}", mode.label());
+    }
+}
+
+#[test]
+fn multisampling_shades_partial_pixels_inside_the_triangle() {
+    let mut weights = Vec::new();
+    raster::rasterize_samples(&diagonal_half(1), 4, 4, AaMode::Msaa4x.sample_offsets(), |_, _, _, w| weights.push(w));
+    assert!(weights.iter().flatten().all(|w| *w >= 0.0));
+}
+
+fn line_coverage(from: (f64, f64), to: (f64, f64), width: f64) -> Vec<(i64, i64, f64)> {
+    let mut out = Vec::new();
+    raster::smooth_line(from, to, width, |x, y, alpha| out.push((x, y, alpha)));
+    out
+}
+
+#[test]
+fn smooth_line_coverage_adds_up_to_its_area() {
+    for (from, to, width) in [((1.5, 2.5), (9.5, 2.5), 1.0), ((1.5, 2.3), (9.5, 2.3), 2.0), ((2.0, 1.0), (3.0, 9.0), 1.5)] {
+        let total: f64 = line_coverage(from, to, width).iter().map(|(_, _, a)| a).sum();
+        let length = ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+        // Ends reach half a pixel past each point along the major axis
+        let major = (to.0 - from.0).abs().max((to.1 - from.1).abs());
+        let expected = width*length*(major + 1.0)/major;
+        assert!((total - expected).abs() < 1e-9, "{} != {}", total, expected);
+    }
+}
+
+#[test]
+fn smooth_line_between_pixel_rows_splits_its_weight() {
+    // A horizontal line along y = 3.0 lies on the border between rows 2 and 3
+    let coverage = line_coverage((1.5, 3.0), (5.5, 3.0), 1.0);
+    assert!(coverage.iter().all(|(_, y, a)| (*y == 2 || *y == 3) && (*a - 0.5).abs() < 1e-9));
+    assert_eq!(coverage.len(), 10);
+}
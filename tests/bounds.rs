@@ -0,0 +1,60 @@
+use r3de::bounds::BoundingSphere;
+use r3de::objs::{ Matrix4x4, Mesh, Vec3d };
+
+fn contains(sphere: &BoundingSphere, points: &[Vec3d]) -> bool {
+    points.iter().all(|p| {
+        let d = *p - sphere.center;
+        d.dot(&d).sqrt() <= sphere.radius*(1.0 + 1e-9)
+    })
+}
+
+// Points scattered through a lopsided box by a fixed linear congruential generator
+fn scattered(count: usize) -> Vec<Vec3d> {
+    let mut seed = 12345u64;
+    let mut next = || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 11) as f64/(1u64 << 53) as f64
+    };
+    (0..count).map(|_| Vec3d::new(next()*10.0 - 2.0, next()*3.0, next()*0.5 + 7.0)).collect()
+}
+
+#[test]
+fn ritter_sphere_contains_every_vertex() {
+    let teapot = Mesh::teapot(3.0, 8);
+    let sphere = teapot.bounding_sphere();
+    assert!(contains(&sphere, &teapot.positions));
+
+    let points = scattered(2000);
+    let sphere = BoundingSphere::from_points(&points);
+    assert!(contains(&sphere, &points));
+    // Not much looser than the box around the points
+    let aabb = Mesh::new(points.clone(), Vec::new()).aabb();
+    assert!(sphere.radius <= aabb.bounding_sphere().radius);
+
+    let single = BoundingSphere::from_points(&[Vec3d::new(1.0, 2.0, 3.0)]);
+    assert_eq!((single.center.x, single.center.y, single.center.z, single.radius), (1.0, 2.0, 3.0, 0.0));
+    assert_eq!(BoundingSphere::from_points(&[]).radius, 0.0);
+}
+
+#[test]
+fn framed_sphere_just_fits_the_narrower_field_of_view() {
+    for aspect in [0.5, 1.0, 1.6] {
+        let mut mat_proj = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+        mat_proj.make_projection(70.0, aspect, 0.1, 1000.0);
+        let sphere = BoundingSphere { center: Vec3d::new(0.0, 0.0, 0.0), radius: 2.0 };
+        let distance = sphere.fit_distance(&mat_proj);
+
+        // Sample the sphere's surface in front of the camera and see how far out it projects
+        let mut widest: f64 = 0.0;
+        for i in 0..=200 {
+            for j in 0..200 {
+                let (theta, phi) = (std::f64::consts::PI*i as f64/200.0, std::f64::consts::TAU*j as f64/200.0);
+                let p = Vec3d::new(theta.sin()*phi.cos()*2.0, theta.sin()*phi.sin()*2.0, theta.cos()*2.0 + distance);
+                let projected = mat_proj.mul_mat_vec(&p);
+                widest = widest.max(projected.x.abs()).max(projected.y.abs());
+            }
+        }
+        assert!(widest <= 1.0 + 1e-9, "aspect {}: {}", aspect, widest);
+        assert!(widest > 0.999, "aspect {}: {}", aspect, widest);
+    }
+}
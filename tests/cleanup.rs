@@ -0,0 +1,132 @@
+mod common;
+
+use r3de::animation::{ Target, Trs };
+use r3de::loaders::stl::load_stl;
+use r3de::objs::{ DisplayBuffers, Mesh, Vec3d };
+use r3de::skin::skinned_cylinder;
+use r3de::writers::stl::{ save_stl, StlFormat };
+use common::{ new_engine, signed_volume };
+
+// Each face of the cube as its own four vertices, the way STL and many OBJ exports store it
+fn unwelded_cube() -> Mesh {
+    let cube = Mesh::cube(2.0, 1);
+    let positions = cube.indices.iter().flatten().map(|v| cube.positions[*v]).collect();
+    let indices = (0..cube.indices.len()).map(|f| [3*f, 3*f + 1, 3*f + 2]).collect();
+    Mesh::new(positions, indices)
+}
+
+#[test]
+fn welding_merges_coincident_vertices() {
+    let mut mesh = unwelded_cube();
+    assert_eq!(mesh.positions.len(), 36);
+    // Nudged by less than the tolerance, so it still merges
+    mesh.positions[0].x += 1e-9;
+    assert_eq!(mesh.weld(1e-6), 28);
+    assert_eq!(mesh.positions.len(), 8);
+    assert_eq!(mesh.indices.len(), 12);
+    assert!((signed_volume(&mesh) - 8.0).abs() < 1e-6);
+}
+
+#[test]
+fn degenerate_and_repeated_faces_are_removed() {
+    let positions = vec![Vec3d::new(0.0, 0.0, 0.0), Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(2.0, 0.0, 0.0)];
+    let mut mesh = Mesh::new(positions, vec![[0, 1, 2], [1, 2, 0], [0, 1, 1], [0, 1, 3], [0, 2, 1]]);
+    assert_eq!(mesh.remove_degenerate_faces(), 2);
+    assert_eq!(mesh.remove_duplicate_faces(), 1);
+    // The reversed face is the back of the same triangle, so it stays
+    assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 1]]);
+}
+
+#[test]
+fn flipped_faces_are_turned_to_match_and_face_out() {
+    let mut mesh = Mesh::icosphere(1.0, 1);
+    mesh.normals.clear();
+    let volume = signed_volume(&mesh);
+    for f in (0..mesh.indices.len()).step_by(3) {
+        mesh.indices[f].swap(1, 2);
+    }
+    assert!(mesh.orient_consistently() > 0);
+    assert!((signed_volume(&mesh) - volume).abs() < 1e-9);
+
+    // A sphere turned wholly inside out is turned back
+    mesh.indices.iter_mut().for_each(|f| f.swap(1, 2));
+    assert_eq!(mesh.orient_consistently(), mesh.indices.len());
+    assert!((signed_volume(&mesh) - volume).abs() < 1e-9);
+}
+
+#[test]
+fn smooth_normals_keep_creases_and_never_go_nan() {
+    let mut mesh = unwelded_cube();
+    mesh.weld(1e-6);
+    // A zero area sliver of the kind that used to put NaN in the normals
+    mesh.indices.push([0, 0, 1]);
+    mesh.compute_smooth_normals(60.0);
+    assert!(mesh.normals.iter().all(|n| n.x.is_finite() && n.y.is_finite() && n.z.is_finite()));
+    // Every cube corner meets three faces at right angles, so it splits into three
+    assert_eq!(mesh.positions.len(), 24);
+    for [a, b, c] in mesh.indices.iter().take(12) {
+        let face = (mesh.positions[*b] - mesh.positions[*a]).cross(&(mesh.positions[*c] - mesh.positions[*a]));
+        assert!(face.dot(&mesh.normals[*a]) > 0.0 && (mesh.normals[*a].dot(&mesh.normals[*a]) - 1.0).abs() < 1e-9);
+    }
+
+    // Past 90 degrees the corners smooth into one normal each, pointing out along the diagonal
+    let mut mesh = unwelded_cube();
+    mesh.weld(1e-6);
+    mesh.compute_smooth_normals(100.0);
+    assert_eq!(mesh.positions.len(), 8);
+    for (p, n) in mesh.positions.iter().zip(mesh.normals.iter()) {
+        assert!((p.dot(n)/p.dot(p).sqrt() - 1.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn loaded_models_are_only_cleaned_up_when_asked() {
+    let path = std::env::temp_dir().join(format!("r3de_cleanup_{}.stl", std::process::id()));
+    save_stl(&unwelded_cube(), &path, StlFormat::Binary).unwrap();
+    let buffers = DisplayBuffers::new([16, 16]);
+    let mut engine = new_engine(&buffers);
+    let loaded = engine.load_model(path.to_str().unwrap());
+    let parsed = load_stl(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    loaded.unwrap();
+
+    // The scene gets the mesh just as the loader read it
+    let mesh = &engine.scene.meshes[engine.scene.nodes[0].meshes[0]];
+    assert!(mesh.positions.len() > 24);
+    assert_eq!((mesh.positions.len(), mesh.indices.len()), (parsed.positions.len(), parsed.indices.len()));
+    // Facet normals keep the faces' corners apart, but the two triangles of each face share theirs
+    engine.scene.clean_up();
+    let mesh = &engine.scene.meshes[engine.scene.nodes[0].meshes[0]];
+    assert_eq!((mesh.positions.len(), mesh.indices.len()), (24, 12));
+}
+
+#[test]
+fn clean_up_makes_the_winding_consistent() {
+    let mut mesh = unwelded_cube();
+    mesh.indices[3].swap(1, 2);
+    mesh.indices[8].swap(1, 2);
+    mesh.clean_up();
+    assert!((signed_volume(&mesh) - 8.0).abs() < 1e-6);
+}
+
+#[test]
+fn posed_meshes_are_cleaned_up_in_their_rest_pose() {
+    let mut rest = skinned_cylinder(0.5, 4.0, 16, 3);
+    rest.meshes[0].clean_up();
+
+    // Cleaned up partway through the bend, then posed again
+    let mut scene = skinned_cylinder(0.5, 4.0, 16, 3);
+    for joint in 1..3 {
+        let rest = Trs::from_matrix(&scene.nodes[joint].transform);
+        scene.nodes[joint].transform = scene.animations[0].pose(Target::Node(joint), &rest, 1.0).to_matrix();
+    }
+    scene.pose_meshes();
+    let posed = scene.meshes[0].positions.clone();
+    let same = |a: &[Vec3d], b: &[Vec3d]| a.len() == b.len() && a.iter().zip(b.iter()).all(|(p, q)| (*p - *q).dot(&(*p - *q)) < 1e-18);
+    scene.meshes[0].clean_up();
+    assert!(same(&scene.meshes[0].positions, &rest.meshes[0].positions));
+    // Posing again deforms the rest pose once, landing where it was before
+    scene.pose_meshes();
+    assert!(same(&scene.meshes[0].rest_positions, &rest.meshes[0].positions));
+    assert!(same(&scene.meshes[0].positions, &posed));
+}
@@ -0,0 +1,49 @@
+use eframe::egui::Color32;
+
+use r3de::color::{ self, ToneMapping };
+use r3de::framebuffer::{ AaMode, Framebuffer };
+
+#[test]
+fn srgb_bytes_survive_a_trip_through_linear() {
+    for v in 0..=255u8 {
+        let c = Color32::from_rgb(v, v/2, 255 - v);
+        assert_eq!(color::to_srgb(color::to_linear(c)), c);
+    }
+    // Mid grey in sRGB is about a fifth of full intensity in linear light
+    assert!((color::to_linear(Color32::from_gray(128))[0] - 0.2158).abs() < 1e-4);
+    assert_eq!(color::to_srgb([0.5; 3]), Color32::from_gray(188));
+}
+
+#[test]
+fn tone_mapping_keeps_hdr_colors_in_range_and_in_order() {
+    for mode in ToneMapping::ALL {
+        let mapped: Vec<f64> = [0.0, 0.1, 0.5, 1.0, 4.0, 100.0].iter().map(|v| mode.apply([*v; 3])[0]).collect();
+        assert_eq!(mapped[0], 0.0, "{}", mode.label());
+        assert!(mapped.windows(2).all(|w| w[0] <= w[1]), "{}: {:?}", mode.label(), mapped);
+        assert!(mapped.iter().all(|v| *v <= 1.0), "{}", mode.label());
+    }
+    assert_eq!(ToneMapping::Clamp.apply([4.0, 0.5, -1.0]), [1.0, 0.5, 0.0]);
+    assert_eq!(ToneMapping::Reinhard.apply([1.0; 3]), [0.5; 3]);
+    // The filmic curve rolls off towards white instead of cutting off
+    assert!(ToneMapping::Aces.apply([4.0; 3])[0] > 0.95);
+}
+
+#[test]
+fn resolve_averages_in_linear_light_then_exposes() {
+    let mut target = Framebuffer::new([1, 1], AaMode::Ssaa2x);
+    target.put(0, 0, 1, [1.0; 3]);
+    target.put(1, 0, 1, [1.0; 3]);
+    let mut out = [Color32::BLACK];
+    // Half covered by white is half intensity, which sRGB shows much brighter than byte 128
+    target.resolve(&mut out, ToneMapping::Clamp, 0.0);
+    assert_eq!(out[0], Color32::from_gray(188));
+    target.resolve(&mut out, ToneMapping::Clamp, 1.0);
+    assert_eq!(out[0], Color32::WHITE);
+
+    // Light past 1 is kept until it is resolved
+    target.clear([3.0; 3]);
+    target.resolve(&mut out, ToneMapping::Clamp, -1.0);
+    assert_eq!(out[0], Color32::WHITE);
+    target.resolve(&mut out, ToneMapping::Reinhard, 0.0);
+    assert_eq!(out[0], color::to_srgb([0.75; 3]));
+}
@@ -0,0 +1,40 @@
+// Helpers shared by the integration tests. Each test file only uses some of them.
+#![allow(dead_code)]
+
+use std::sync::{ Arc, Mutex };
+
+use r3de::engine::Engine;
+use r3de::objs::{ DisplayBuffers, GUIState, Matrix4x4, Mesh };
+
+pub fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+// For embedding buffers in glTF documents as data URIs
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let word = chunk.iter().enumerate().fold(0u32, |acc, (i, b)| acc | (*b as u32) << (16 - 8*i));
+        for i in 0..4 {
+            out.push(if i <= chunk.len() { ALPHABET[(word >> (18 - 6*i) & 63) as usize] as char } else { '=' });
+        }
+    }
+    out
+}
+
+// Volume enclosed by the mesh, positive when its faces wind outwards
+pub fn signed_volume(mesh: &Mesh) -> f64 {
+    mesh.indices.iter().map(|[a, b, c]| mesh.positions[*a].dot(&mesh.positions[*b].cross(&mesh.positions[*c]))).sum::<f64>()/6.0
+}
+
+pub fn translation(x: f64, y: f64, z: f64) -> Matrix4x4 {
+    let mut mat = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+    mat.make_translation(x, y, z);
+    mat
+}
+
+// An engine drawing into buffers, with a GUI state of its own that nothing else looks at
+pub fn new_engine(buffers: &DisplayBuffers) -> Engine {
+    Engine::new(Arc::new(Mutex::new(GUIState::new())), buffers)
+}
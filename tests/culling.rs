@@ -0,0 +1,56 @@
+mod common;
+
+use r3de::bounds::{ Aabb, Containment, Frustum };
+use r3de::objs::{ Matrix4x4, Mesh, Vec3d };
+use common::translation;
+
+// 90 degree square view, so the side planes are |x| <= z and |y| <= z
+fn frustum() -> Frustum {
+    let mut mat_proj = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+    mat_proj.make_projection(90.0, 1.0, 0.1, 1000.0);
+    Frustum::from_projection(&mat_proj, 0.1)
+}
+
+fn unit_box() -> Aabb {
+    Aabb::from_points(&[Vec3d::new(-0.5, -0.5, -0.5), Vec3d::new(0.5, 0.5, 0.5)])
+}
+
+#[test]
+fn boxes_are_classified_against_the_frustum() {
+    let frustum = frustum();
+    let classify = |x: f64, y: f64, z: f64| frustum.classify_aabb(&unit_box(), &translation(x, y, z));
+    assert!(classify(0.0, 0.0, 5.0) == Containment::Inside);
+    // Behind the camera, past each side, and straddling the near plane and a side
+    assert!(classify(0.0, 0.0, -5.0) == Containment::Outside);
+    assert!(classify(20.0, 0.0, 5.0) == Containment::Outside);
+    assert!(classify(0.0, -20.0, 5.0) == Containment::Outside);
+    assert!(classify(0.0, 0.0, 0.2) == Containment::Intersecting);
+    assert!(classify(5.0, 0.0, 5.0) == Containment::Intersecting);
+    assert!(frustum.classify_aabb(&Aabb::empty(), &Matrix4x4::identity()) == Containment::Outside);
+}
+
+#[test]
+fn bvh_keeps_only_triangles_that_can_be_seen() {
+    // A 60 wide strip of floor 10 in front of the camera, of which only the middle third is in view
+    let mut mesh = Mesh::plane(60.0, 2.0, 60, 2);
+    mesh.build_bvh();
+    let bvh = mesh.bvh.as_ref().unwrap();
+    let frustum = frustum();
+    let visible = |mat: &Matrix4x4| {
+        let mut out = Vec::new();
+        bvh.visible_tris(&frustum, mat, &mut out);
+        out.sort_unstable();
+        out
+    };
+    let centre_x = |t: usize| mesh.indices[t].iter().map(|v| mesh.positions[*v].x).sum::<f64>()/3.0;
+
+    let all: Vec<usize> = (0..mesh.indices.len()).collect();
+    assert_eq!(visible(&translation(0.0, 0.0, 100.0)), all);
+    assert!(visible(&translation(0.0, 0.0, -10.0)).is_empty());
+
+    let seen = visible(&translation(0.0, 0.0, 10.0));
+    assert!(!seen.is_empty() && seen.len() < all.len() / 2, "{}", seen.len());
+    // Everything in view is kept, and nothing far out to the sides is
+    assert!(all.iter().filter(|t| centre_x(**t).abs() < 8.0).all(|t| seen.contains(t)));
+    assert!(seen.iter().all(|t| centre_x(*t).abs() < 20.0));
+}
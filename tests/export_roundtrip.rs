@@ -0,0 +1,115 @@
+use eframe::egui::Color32;
+
+use r3de::loaders::{ obj, ply, stl };
+use r3de::objs::{ Mesh, Vec2d, Vec3d };
+use r3de::writers;
+use r3de::writers::ply::PlyFormat;
+use r3de::writers::stl::StlFormat;
+
+// Unit cube with four unshared vertices per face, carrying every optional attribute
+fn attributed_cube() -> Mesh {
+    let faces = [
+        ([0.0, 0.0, -1.0], [[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0], [1.0, 0.0, 0.0]]),
+        ([0.0, 0.0, 1.0], [[1.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0], [0.0, 0.0, 1.0]]),
+        ([-1.0, 0.0, 0.0], [[0.0, 0.0, 1.0], [0.0, 1.0, 1.0], [0.0, 1.0, 0.0], [0.0, 0.0, 0.0]]),
+        ([1.0, 0.0, 0.0], [[1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [1.0, 1.0, 1.0], [1.0, 0.0, 1.0]]),
+        ([0.0, 1.0, 0.0], [[0.0, 1.0, 0.0], [0.0, 1.0, 1.0], [1.0, 1.0, 1.0], [1.0, 1.0, 0.0]]),
+        ([0.0, -1.0, 0.0], [[1.0, 0.0, 1.0], [0.0, 0.0, 1.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0]]),
+    ];
+    let corner_uvs = [[0.0, 1.0], [0.0, 0.0], [1.0, 0.0], [1.0, 1.0]];
+
+    let mut mesh = Mesh::new(Vec::new(), Vec::new());
+    for (f, (n, corners)) in faces.iter().enumerate() {
+        let base = mesh.positions.len();
+        for (k, c) in corners.iter().enumerate() {
+            mesh.positions.push(Vec3d::new(c[0]*2.0 - 0.5, c[1]*1.25, c[2] - 0.1));
+            mesh.normals.push(Vec3d::new(n[0], n[1], n[2]));
+            mesh.uvs.push(Vec2d::new(corner_uvs[k][0], corner_uvs[k][1]));
+            mesh.colors.push(Color32::from_rgb((f*40) as u8, (k*60) as u8, 200));
+        }
+        mesh.indices.push([base, base + 1, base + 2]);
+        mesh.indices.push([base, base + 2, base + 3]);
+    }
+    mesh
+}
+
+fn close(a: f64, b: f64, eps: f64) -> bool {
+    (a - b).abs() <= eps
+}
+
+fn assert_positions_eq(a: &[Vec3d], b: &[Vec3d], eps: f64) {
+    assert_eq!(a.len(), b.len());
+    for (p, q) in a.iter().zip(b.iter()) {
+        assert!(close(p.x, q.x, eps) && close(p.y, q.y, eps) && close(p.z, q.z, eps), "({}, {}, {}) != ({}, {}, {})", p.x, p.y, p.z, q.x, q.y, q.z);
+    }
+}
+
+fn assert_same_mesh(original: &Mesh, loaded: &Mesh) {
+    assert_eq!(original.indices, loaded.indices);
+    assert_positions_eq(&original.positions, &loaded.positions, 0.0);
+    assert_positions_eq(&original.normals, &loaded.normals, 0.0);
+    assert_eq!(original.uvs.len(), loaded.uvs.len());
+    for (a, b) in original.uvs.iter().zip(loaded.uvs.iter()) {
+        assert_eq!((a.u, a.v), (b.u, b.v));
+    }
+    assert_eq!(original.colors, loaded.colors);
+}
+
+#[test]
+fn obj_round_trip_keeps_all_attributes() {
+    let mesh = attributed_cube();
+    let mut data = Vec::new();
+    writers::obj::write_obj(&mesh, &mut data).unwrap();
+    let loaded = obj::parse_obj(data.as_slice()).unwrap();
+    assert_same_mesh(&mesh, &loaded);
+}
+
+#[test]
+fn obj_round_trip_positions_only() {
+    let mesh = Mesh::new(
+        vec![Vec3d::new(0.0, 0.0, 0.0), Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(0.0, 0.0, 1.0)],
+        vec![[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]],
+    );
+    let mut data = Vec::new();
+    writers::obj::write_obj(&mesh, &mut data).unwrap();
+    let loaded = obj::parse_obj(data.as_slice()).unwrap();
+    assert_same_mesh(&mesh, &loaded);
+}
+
+#[test]
+fn ply_round_trip_in_every_format() {
+    let mesh = attributed_cube();
+    for format in [PlyFormat::Ascii, PlyFormat::BinaryLittleEndian, PlyFormat::BinaryBigEndian] {
+        let mut data = Vec::new();
+        writers::ply::write_ply(&mesh, &mut data, format).unwrap();
+        let loaded = ply::parse_ply(&data).unwrap();
+        assert_same_mesh(&mesh, &loaded);
+    }
+}
+
+#[test]
+fn stl_round_trip_in_both_formats() {
+    let mesh = attributed_cube();
+    for (format, eps) in [(StlFormat::Ascii, 0.0), (StlFormat::Binary, 1e-6)] {
+        let mut data = Vec::new();
+        writers::stl::write_stl(&mesh, &mut data, format).unwrap();
+        let loaded = stl::parse_stl(&data).unwrap();
+
+        // STL has no shared vertices, so compare triangle by triangle
+        let (expected, found) = (mesh.to_tris(), loaded.to_tris());
+        assert_eq!(expected.len(), found.len());
+        for (a, b) in expected.iter().zip(found.iter()) {
+            assert_positions_eq(&a.p, &b.p, eps);
+            // Facet normals come from the winding, which matches the cube's face normals
+            assert_positions_eq(&a.n, &b.n, eps);
+        }
+    }
+}
+
+#[test]
+fn binary_stl_is_not_mistaken_for_ascii() {
+    let mut data = Vec::new();
+    writers::stl::write_stl(&attributed_cube(), &mut data, StlFormat::Binary).unwrap();
+    assert!(!data.starts_with(b"solid"));
+    assert_eq!(data.len(), 84 + 12*50);
+}
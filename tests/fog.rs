@@ -0,0 +1,45 @@
+mod common;
+
+use eframe::egui::Color32;
+
+use r3de::color;
+use r3de::fog::{ Fog, FogMode };
+use common::close;
+
+fn fog(mode: FogMode) -> Fog {
+    Fog { mode, color: Color32::WHITE, start: 10.0, end: 20.0, density: 0.1, per_vertex: false }
+}
+
+#[test]
+fn visibility_falls_off_with_depth() {
+    assert!([0.0, 10.0, 1000.0].iter().all(|z| fog(FogMode::Off).visibility(*z) == 1.0));
+
+    let linear = fog(FogMode::Linear);
+    assert_eq!(linear.visibility(5.0), 1.0);
+    assert!(close(linear.visibility(15.0), 0.5));
+    assert_eq!(linear.visibility(25.0), 0.0);
+
+    assert!(close(fog(FogMode::Exponential).visibility(10.0), (-1.0f64).exp()));
+    assert!(close(fog(FogMode::ExponentialSquared).visibility(20.0), (-4.0f64).exp()));
+    // Squared fog is clearer up close and thicker far away
+    let (exp, exp2) = (fog(FogMode::Exponential), fog(FogMode::ExponentialSquared));
+    assert!(exp2.visibility(5.0) > exp.visibility(5.0) && exp2.visibility(20.0) < exp.visibility(20.0));
+    for mode in FogMode::ALL {
+        let f = fog(mode);
+        assert!((0..100).all(|z| f.visibility(z as f64) >= f.visibility(z as f64 + 1.0)), "{}", mode.label());
+    }
+}
+
+#[test]
+fn fogged_colors_fade_into_the_background() {
+    let f = fog(FogMode::Linear);
+    assert_eq!(f.background(), [1.0; 3]);
+    assert_eq!(fog(FogMode::Off).background(), [0.0; 3]);
+    assert_eq!(f.apply([0.2, 0.4, 0.0], 1.0), [0.2, 0.4, 0.0]);
+    assert_eq!(f.apply([0.2, 0.4, 0.0], 0.0), f.background());
+    let half = f.apply([0.0; 3], 0.5);
+    assert!(half.iter().all(|v| close(*v, 0.5)));
+
+    let grey = Fog { color: Color32::from_gray(128), ..f };
+    assert_eq!(color::to_srgb(grey.apply([5.0; 3], 0.0)), Color32::from_gray(128));
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use eframe::egui::Color32;
+
+use r3de::loaders::gltf::parse_gltf;
+use r3de::loaders::json;
+use r3de::loaders::LoadError;
+use r3de::objs::Vec3d;
+use r3de::scene::{ AlphaMode, Scene };
+use common::base64;
+
+// One triangle's buffer: positions, normals, uvs and RGBA colors as floats, then u16 indices
+fn triangle_bytes() -> Vec<u8> {
+    let floats: [f32; 36] = [
+        0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0,
+        0.0, 0.0, 1.0, 0.0, 0.0, 1.0,
+        1.0, 0.0, 0.0, 1.0, 0.0, 0.5, 0.0, 0.5, 0.0, 0.0, 1.0, 0.25,
+    ];
+    let mut bytes: Vec<u8> = floats.iter().flat_map(|f| f.to_le_bytes()).collect();
+    bytes.extend([0u16, 1, 2].iter().flat_map(|i| i.to_le_bytes()));
+    bytes
+}
+
+// A document around the triangle buffer, with the accessors (in order POSITION, NORMAL,
+// TEXCOORD_0, COLOR_0 and indices) and any further top level members given
+fn document(accessors: &str, rest: &str) -> String {
+    let bytes = triangle_bytes();
+    format!(r#"{{
+        "asset": {{ "version": "2.0" }},
+        "buffers": [{{ "byteLength": {}, "uri": "data:application/octet-stream;base64,{}" }}],
+        "bufferViews": [
+            {{ "buffer": 0, "byteLength": 36 }},
+            {{ "buffer": 0, "byteOffset": 36, "byteLength": 36 }},
+            {{ "buffer": 0, "byteOffset": 72, "byteLength": 24 }},
+            {{ "buffer": 0, "byteOffset": 96, "byteLength": 48 }},
+            {{ "buffer": 0, "byteOffset": 144, "byteLength": 6 }}
+        ],
+        "accessors": [{}],
+        "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2, "COLOR_0": 3 }}, "indices": 4, "material": 0 }}] }}]
+        {}
+    }}"#, bytes.len(), base64(&bytes), accessors, rest)
+}
+
+const ACCESSORS: &str = r#"
+    { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+    { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" },
+    { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2" },
+    { "bufferView": 3, "componentType": 5126, "count": 3, "type": "VEC4" },
+    { "bufferView": 4, "componentType": 5123, "count": 3, "type": "SCALAR" }
+"#;
+
+fn load(accessors: &str, rest: &str) -> Result<Scene, LoadError> {
+    parse_gltf(document(accessors, rest).as_bytes(), None)
+}
+
+// The triangle's accessors with the one at index replaced
+fn accessors_with(index: usize, accessor: &str) -> String {
+    let mut list: Vec<&str> = ACCESSORS.trim().split(",\n").collect();
+    list[index] = accessor;
+    list.join(",\n")
+}
+
+fn parse_message(result: Result<Scene, LoadError>) -> String {
+    match result {
+        Err(LoadError::Parse { message, .. }) => message,
+        Err(e) => panic!("expected a parse error, got {}", e),
+        Ok(_) => panic!("expected a parse error"),
+    }
+}
+
+#[test]
+fn triangle_mesh_is_loaded_with_its_attributes() {
+    let scene = load(ACCESSORS, "").unwrap();
+    assert_eq!(scene.meshes.len(), 1);
+    let mesh = &scene.meshes[0];
+    assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+    assert_eq!(mesh.positions[1].x, 1.0);
+    assert_eq!(mesh.positions[2].y, 1.0);
+    assert!(mesh.normals.iter().all(|n| n.z == 1.0));
+    assert_eq!((mesh.uvs[1].u, mesh.uvs[2].v), (1.0, 1.0));
+    // Colors are stored as sRGB, so linear 0.5 becomes 188; alpha is kept linear
+    assert_eq!(mesh.colors[0], Color32::from_rgba_unmultiplied(255, 0, 0, 255));
+    assert_eq!(mesh.colors[1], Color32::from_rgba_unmultiplied(0, 188, 0, 128));
+    assert_eq!(mesh.colors[2].a(), 64);
+    // Without nodes the mesh still gets a root to be drawn from
+    assert_eq!(scene.roots, vec![0]);
+    assert_eq!(scene.nodes[0].meshes, vec![0]);
+}
+
+#[test]
+fn materials_are_loaded_and_assigned() {
+    let scene = load(ACCESSORS, r#", "materials": [{ "name": "glass", "alphaMode": "BLEND", "pbrMetallicRoughness": { "baseColorFactor": [0.2, 0.4, 0.6, 0.5] } }]"#).unwrap();
+    let material = &scene.materials[0];
+    assert_eq!(material.name, "glass");
+    assert_eq!(material.base_color, [0.2, 0.4, 0.6, 0.5]);
+    assert!(material.alpha_mode == AlphaMode::Blend);
+    assert_eq!(material.opacity(), Some(0.5));
+    assert_eq!(scene.meshes[0].material, Some(0));
+
+    // A primitive pointing at a material that isn't there gets none
+    let scene = load(ACCESSORS, "").unwrap();
+    assert!(scene.materials.is_empty());
+    assert_eq!(scene.meshes[0].material, None);
+}
+
+#[test]
+fn node_transforms_compose_down_the_hierarchy() {
+    let nodes = r#", "nodes": [
+        { "name": "parent", "translation": [0, 2, 0], "scale": [2, 2, 2], "children": [1] },
+        { "name": "child", "translation": [1, 0, 0], "mesh": 0 },
+        { "name": "moved", "matrix": [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 5, 6, 7, 1] }
+    ]"#;
+    let scene = load(ACCESSORS, nodes).unwrap();
+    assert_eq!(scene.roots, vec![0, 2]);
+    assert_eq!(scene.nodes[0].children, vec![1]);
+    assert_eq!(scene.nodes[1].meshes, vec![0]);
+
+    let worlds = scene.node_worlds();
+    let origin = Vec3d::new(0.0, 0.0, 0.0);
+    let child = worlds[1].mul_mat_vec(&origin);
+    assert_eq!((child.x, child.y, child.z), (2.0, 2.0, 0.0));
+    let moved = worlds[2].mul_mat_vec(&origin);
+    assert_eq!((moved.x, moved.y, moved.z), (5.0, 6.0, 7.0));
+}
+
+#[test]
+fn attributes_with_the_wrong_component_count_are_rejected() {
+    let wrong = [
+        (0, r#"{ "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2" }"#, "POSITION"),
+        (1, r#"{ "bufferView": 3, "componentType": 5126, "count": 3, "type": "VEC4" }"#, "NORMAL"),
+        (2, r#"{ "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" }"#, "TEXCOORD_0"),
+        (3, r#"{ "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2" }"#, "COLOR_0"),
+    ];
+    for (index, accessor, name) in wrong {
+        let message = parse_message(load(&accessors_with(index, accessor), ""));
+        assert!(message.starts_with(name), "{}", message);
+    }
+    // Both RGB and RGBA colors are fine
+    let rgb = accessors_with(3, r#"{ "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" }"#);
+    assert!(load(&rgb, "").unwrap().meshes[0].colors.iter().all(|c| c.a() == 255));
+}
+
+#[test]
+fn accessors_past_the_end_of_their_view_are_truncated() {
+    let long = accessors_with(0, r#"{ "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3" }"#);
+    assert!(matches!(load(&long, ""), Err(LoadError::Truncated { expected: 48, found: 36 })));
+    let offset = accessors_with(0, r#"{ "bufferView": 0, "byteOffset": 4, "componentType": 5126, "count": 3, "type": "VEC3" }"#);
+    assert!(matches!(load(&offset, ""), Err(LoadError::Truncated { expected: 40, found: 36 })));
+    // Huge counts are refused up front instead of being allocated
+    let huge = accessors_with(0, r#"{ "bufferView": 0, "componentType": 5126, "count": 1000000000000, "type": "VEC3" }"#);
+    assert!(matches!(load(&huge, ""), Err(LoadError::Truncated { found: 36, .. })));
+    let unbacked = accessors_with(0, r#"{ "componentType": 5126, "count": 1000000000000, "type": "VEC3" }"#);
+    assert!(matches!(load(&unbacked, ""), Err(LoadError::Unsupported(_))));
+
+    let strided = document(ACCESSORS, "").replacen(r#"{ "buffer": 0, "byteLength": 36 }"#, r#"{ "buffer": 0, "byteLength": 36, "byteStride": 4 }"#, 1);
+    assert!(parse_message(parse_gltf(strided.as_bytes(), None)).contains("byteStride"));
+
+    let short = document(ACCESSORS, "").replacen(r#""byteLength": 150"#, r#""byteLength": 160"#, 1);
+    assert!(matches!(parse_gltf(short.as_bytes(), None), Err(LoadError::Truncated { expected: 160, found: 150 })));
+}
+
+#[test]
+fn indices_and_references_out_of_range_are_bad_indices() {
+    let bytes = triangle_bytes();
+    let doc = document(ACCESSORS, "").replace(&base64(&bytes), &base64(&[&bytes[..144], &[0, 0, 1, 0, 3, 0]].concat()));
+    assert!(matches!(parse_gltf(doc.as_bytes(), None), Err(LoadError::BadIndex { index: 3, .. })));
+
+    assert!(matches!(load(ACCESSORS, r#", "nodes": [{ "mesh": 4 }]"#), Err(LoadError::BadIndex { index: 4, .. })));
+    assert!(matches!(load(ACCESSORS, r#", "nodes": [{ "skin": 0 }]"#), Err(LoadError::BadIndex { index: 0, .. })));
+    let missing = document(ACCESSORS, "").replacen(r#""POSITION": 0"#, r#""POSITION": 9"#, 1);
+    assert!(matches!(parse_gltf(missing.as_bytes(), None), Err(LoadError::BadIndex { index: 9, .. })));
+
+    assert!(parse_message(load(ACCESSORS, r#", "nodes": [{ "children": [2] }, { "children": [2] }, {}]"#)).contains("more than one parent"));
+}
+
+#[test]
+fn unsupported_files_are_refused() {
+    let old = document(ACCESSORS, "").replacen(r#""version": "2.0""#, r#""version": "1.0""#, 1);
+    assert!(matches!(parse_gltf(old.as_bytes(), None), Err(LoadError::Unsupported(_))));
+    let remote = document(ACCESSORS, "").replacen("data:application/octet-stream;base64,", "https://example.com/", 1);
+    assert!(matches!(parse_gltf(remote.as_bytes(), None), Err(LoadError::Unsupported(_))));
+    let sparse = accessors_with(0, r#"{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "sparse": {} }"#);
+    assert!(matches!(load(&sparse, ""), Err(LoadError::Unsupported(_))));
+
+    // Binary containers are checked before anything in them is read
+    let mut glb = b"glTF".to_vec();
+    glb.extend_from_slice(&1u32.to_le_bytes());
+    glb.extend_from_slice(&12u32.to_le_bytes());
+    assert!(matches!(parse_gltf(&glb, None), Err(LoadError::Unsupported(_))));
+    glb[4] = 2;
+    glb[8] = 100;
+    assert!(matches!(parse_gltf(&glb, None), Err(LoadError::Truncated { expected: 100, found: 12 })));
+    assert!(matches!(parse_gltf(b"glTF", None), Err(LoadError::Truncated { expected: 8, found: 4 })));
+}
+
+#[test]
+fn malformed_json_is_a_parse_error() {
+    assert!(matches!(json::parse("{\n\"a\": [1, 2,\n}"), Err(LoadError::Parse { line: 3, .. })));
+    assert!(matches!(json::parse("{} extra"), Err(LoadError::Parse { line: 1, .. })));
+    assert!(matches!(json::parse(r#"{"a": "unterminated}"#), Err(LoadError::Parse { .. })));
+
+    // Deep nesting is an error rather than a stack overflow
+    let deep = "[".repeat(200_000);
+    assert!(matches!(json::parse(&deep), Err(LoadError::Parse { .. })));
+    let fine = format!("{}{}", "[".repeat(100), "]".repeat(100));
+    assert!(json::parse(&fine).is_ok());
+}
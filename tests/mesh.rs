@@ -0,0 +1,101 @@
+use eframe::egui::Color32;
+
+use r3de::objs::{ Matrix4x4, Mesh, Tri, Vec2d, Vec3d };
+
+fn xyz(v: &Vec3d) -> [f64; 3] {
+    [v.x, v.y, v.z]
+}
+
+fn uv(t: &Vec2d) -> [f64; 2] {
+    [t.u, t.v]
+}
+
+// Unit square in z = 0 split along its diagonal, with every attribute following the corner
+fn square_tris() -> Vec<Tri> {
+    let corners = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+    let colors = [Color32::RED, Color32::GREEN, Color32::BLUE, Color32::WHITE];
+    let tri = |c: [usize; 3]| {
+        let mut t = Tri::with_normals(c.iter().map(|k| Vec3d::new(corners[*k][0], corners[*k][1], 0.0)).collect(), vec![Vec3d::new(0.0, 0.0, 1.0); 3]);
+        t.col = c.iter().map(|k| colors[*k]).collect();
+        t.uv = c.iter().map(|k| Vec2d::new(corners[*k][0], corners[*k][1])).collect();
+        t
+    };
+    vec![tri([0, 1, 2]), tri([0, 2, 3])]
+}
+
+#[test]
+fn matching_corners_share_a_vertex() {
+    let mesh = Mesh::from_tris(&square_tris());
+    assert_eq!(mesh.positions.len(), 4);
+    assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3]]);
+    assert_eq!((mesh.normals.len(), mesh.colors.len(), mesh.uvs.len()), (4, 4, 4));
+}
+
+#[test]
+fn corners_differing_in_any_attribute_stay_apart() {
+    let mut tris = square_tris();
+    tris[1].uv[0] = Vec2d::new(0.5, 0.5);
+    assert_eq!(Mesh::from_tris(&tris).positions.len(), 5);
+
+    let mut tris = square_tris();
+    tris[1].col[1] = Color32::BLACK;
+    assert_eq!(Mesh::from_tris(&tris).positions.len(), 5);
+
+    // Normals only some triangles have are dropped, and no longer keep corners apart
+    let mut tris = square_tris();
+    tris[1].n.clear();
+    tris[0].n[0] = Vec3d::new(1.0, 0.0, 0.0);
+    let mesh = Mesh::from_tris(&tris);
+    assert_eq!(mesh.positions.len(), 4);
+    assert!(mesh.normals.is_empty());
+    assert_eq!(mesh.uvs.len(), 4);
+}
+
+#[test]
+fn triangles_survive_a_trip_through_the_vertex_buffer() {
+    let tris = square_tris();
+    let back = Mesh::from_tris(&tris).to_tris();
+    assert_eq!(back.len(), tris.len());
+    for (a, b) in tris.iter().zip(back.iter()) {
+        assert_eq!(a.p.iter().map(xyz).collect::<Vec<_>>(), b.p.iter().map(xyz).collect::<Vec<_>>());
+        assert_eq!(a.n.iter().map(xyz).collect::<Vec<_>>(), b.n.iter().map(xyz).collect::<Vec<_>>());
+        assert_eq!(a.col, b.col);
+        assert_eq!(a.uv.iter().map(uv).collect::<Vec<_>>(), b.uv.iter().map(uv).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn assembled_triangles_take_the_given_positions_and_normals() {
+    let mesh = Mesh::from_tris(&square_tris());
+    let moved: Vec<Vec3d> = mesh.positions.iter().map(|p| Vec3d::new(p.x + 10.0, p.y, p.z)).collect();
+    let tri = mesh.assemble_tri(1, &moved, &[]);
+    assert_eq!(tri.p.iter().map(xyz).collect::<Vec<_>>(), vec![[10.0, 0.0, 0.0], [11.0, 1.0, 0.0], [10.0, 1.0, 0.0]]);
+    assert!(tri.n.is_empty());
+    // Colors and uvs still come from the mesh
+    assert_eq!(tri.col, vec![Color32::RED, Color32::BLUE, Color32::WHITE]);
+    assert_eq!(uv(&tri.uv[1]), [1.0, 1.0]);
+}
+
+#[test]
+fn normals_stay_perpendicular_under_non_uniform_scales() {
+    let unit = |v: Vec3d| { let mut v = v; v.normalize(); v };
+    let scaled = |x: f64, y: f64, z: f64| { let mut mat = Matrix4x4::new(vec![vec![0.0; 4]; 4]); mat.make_scale(x, y, z); mat };
+
+    // The plane x + y = 0, stretched along x, tilts towards y
+    let mat = scaled(2.0, 1.0, 1.0);
+    let tangent = mat.mul_mat_dir(&Vec3d::new(1.0, -1.0, 0.0));
+    let n = unit(mat.normal_matrix().mul_mat_dir(&Vec3d::new(1.0, 1.0, 0.0)));
+    assert!(n.dot(&tangent).abs() < 1e-12);
+    let expected = unit(Vec3d::new(1.0, 2.0, 0.0));
+    assert!((n.dot(&expected) - 1.0).abs() < 1e-12, "{:?}", xyz(&n));
+    // while the upper 3x3 alone leans it the wrong way
+    assert!(mat.mul_mat_dir(&Vec3d::new(1.0, 1.0, 0.0)).dot(&tangent).abs() > 1.0);
+
+    // Mirroring flips the normal along with the surface, and rotations leave it as the matrix has it
+    let n = scaled(-1.0, 1.0, 1.0).normal_matrix().mul_mat_dir(&Vec3d::new(1.0, 0.0, 0.0));
+    assert_eq!(xyz(&n), [-1.0, 0.0, 0.0]);
+    let mut rotation = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+    rotation.make_rotation_z(0.7);
+    let (a, b) = (unit(rotation.normal_matrix().mul_mat_dir(&Vec3d::new(0.3, 0.4, 0.5))), unit(rotation.mul_mat_dir(&Vec3d::new(0.3, 0.4, 0.5))));
+    assert!((a.dot(&b) - 1.0).abs() < 1e-12);
+}
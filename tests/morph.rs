@@ -0,0 +1,58 @@
+use std::f64::consts::FRAC_PI_2;
+
+use r3de::animation::{ axis_angle, Trs };
+use r3de::morph::{ morph_mesh, MorphTarget };
+use r3de::objs::{ Mesh, Vec3d };
+use r3de::scene::Scene;
+use r3de::skin::skinned_cylinder;
+
+fn raised_plane() -> Mesh {
+    let mut mesh = Mesh::plane(2.0, 2.0, 2, 2);
+    let up = vec![Vec3d::new(0.0, 1.0, 0.0); mesh.positions.len()];
+    let out = mesh.positions.iter().map(|p| Vec3d::new(p.x, 0.0, p.z)).collect();
+    mesh.morph_targets = vec![MorphTarget::new("Raise".to_string(), up), MorphTarget::new("Spread".to_string(), out)];
+    mesh.morph_weights = vec![0.0, 0.0];
+    mesh
+}
+
+#[test]
+fn weights_mix_targets_into_the_rest_pose() {
+    let mut mesh = raised_plane();
+    let rest = mesh.positions.clone();
+    mesh.morph_weights = vec![0.5, 1.0];
+    morph_mesh(&mut mesh);
+    for (p, r) in mesh.positions.iter().zip(rest.iter()) {
+        assert!((p.y - (r.y + 0.5)).abs() < 1e-12 && (p.x - 2.0*r.x).abs() < 1e-12);
+    }
+    // Going back to zero weights gives back the rest pose, however often the mesh was morphed
+    mesh.morph_weights = vec![0.0, 0.0];
+    morph_mesh(&mut mesh);
+    assert!(mesh.positions.iter().zip(rest.iter()).all(|(p, r)| (*p - *r).dot(&(*p - *r)) < 1e-24));
+}
+
+#[test]
+fn scenes_morph_deformable_meshes_when_posed() {
+    let mut scene = Scene::new();
+    scene.add_mesh("plane".to_string(), raised_plane());
+    scene.meshes[0].morph_weights[0] = 1.0;
+    scene.pose_meshes();
+    assert!(scene.meshes[0].positions.iter().all(|p| (p.y - 1.0).abs() < 1e-12));
+}
+
+#[test]
+fn morphing_happens_before_skinning() {
+    // The upper of two bones turns a quarter about z around y = 0, carrying the top cap with it
+    let mut scene = skinned_cylinder(0.5, 4.0, 8, 2);
+    let top = scene.meshes[0].positions.iter().position(|p| p.y > 1.999 && p.x.abs() < 1e-9 && p.z.abs() < 1e-9).unwrap();
+    let count = scene.meshes[0].positions.len();
+    scene.meshes[0].morph_targets.push(MorphTarget::new("Lift".to_string(), vec![Vec3d::new(0.0, 1.0, 0.0); count]));
+    scene.meshes[0].morph_weights.push(1.0);
+    let mut pose = Trs::from_matrix(&scene.nodes[1].transform);
+    pose.rotation = axis_angle([0.0, 0.0, 1.0], FRAC_PI_2);
+    scene.nodes[1].transform = pose.to_matrix();
+    scene.pose_meshes();
+
+    // Lifted to y = 3 first and then turned, rather than turned and then lifted
+    let p = scene.meshes[0].positions[top];
+    assert!((p.x.abs() - 3.0).abs() < 1e-9 && p.y.abs() < 1e-9, "({}, {}, {})", p.x, p.y, p.z);
+}
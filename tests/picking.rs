@@ -0,0 +1,67 @@
+mod common;
+
+use r3de::bounds::Aabb;
+use r3de::objs::{ DisplayBuffers, Mesh, Vec3d };
+use r3de::picking::Ray;
+use common::{ new_engine, translation };
+
+fn v(x: f64, y: f64, z: f64) -> Vec3d {
+    Vec3d::new(x, y, z)
+}
+
+// Ray straight down -z through (x, y), against the triangle (0,0) (1,0) (0,1) in z = 0
+fn cast(x: f64, y: f64) -> Option<(f64, f64, f64)> {
+    Ray::new(v(x, y, 5.0), v(0.0, 0.0, -1.0)).intersect_triangle(&v(0.0, 0.0, 0.0), &v(1.0, 0.0, 0.0), &v(0.0, 1.0, 0.0))
+}
+
+#[test]
+fn rays_hit_triangles_inside_and_on_their_edges() {
+    let (t, u, w) = cast(0.25, 0.5).unwrap();
+    assert_eq!((t, u, w), (5.0, 0.25, 0.5));
+    assert!(cast(1.0, 1.0).is_none());
+    assert!(cast(-0.1, 0.5).is_none());
+    // Points on an edge and on a corner count as hits
+    assert!(cast(0.5, 0.5).is_some());
+    assert!(cast(0.0, 0.3).is_some());
+    assert!(cast(1.0, 0.0).is_some());
+    // Rays running along the triangle's plane never hit it
+    assert!(Ray::new(v(-1.0, 0.2, 0.0), v(1.0, 0.0, 0.0)).intersect_triangle(&v(0.0, 0.0, 0.0), &v(1.0, 0.0, 0.0), &v(0.0, 1.0, 0.0)).is_none());
+    // Hits behind the origin come back with a negative t for the caller to reject
+    assert_eq!(Ray::new(v(0.25, 0.25, -2.0), v(0.0, 0.0, -1.0)).intersect_triangle(&v(0.0, 0.0, 0.0), &v(1.0, 0.0, 0.0), &v(0.0, 1.0, 0.0)).unwrap().0, -2.0);
+}
+
+#[test]
+fn rays_enter_and_leave_boxes() {
+    let aabb = Aabb::from_points(&[v(-1.0, -1.0, -1.0), v(1.0, 1.0, 1.0)]);
+    assert_eq!(Ray::new(v(0.0, 0.0, -5.0), v(0.0, 0.0, 1.0)).intersect_aabb(&aabb), Some((4.0, 6.0)));
+    assert_eq!(Ray::new(v(0.0, 0.0, 0.0), v(0.0, 0.0, 2.0)).intersect_aabb(&aabb), Some((-0.5, 0.5)));
+    assert!(Ray::new(v(3.0, 0.0, -5.0), v(0.0, 0.0, 1.0)).intersect_aabb(&aabb).is_none());
+    assert!(Ray::new(v(0.0, 0.0, -5.0), v(1.0, 0.0, 1.0)).intersect_aabb(&aabb).is_none());
+    // Grazing the edge of the box is still a hit
+    assert_eq!(Ray::new(v(1.0, 1.0, -5.0), v(0.0, 0.0, 1.0)).intersect_aabb(&aabb), Some((4.0, 6.0)));
+}
+
+#[test]
+fn picking_takes_the_nearest_of_two_meshes() {
+    let buffers = DisplayBuffers::new([64, 64]);
+    let mut engine = new_engine(&buffers);
+    engine.settings.ground_plane = false;
+    // Two cubes one behind the other along the view axis; the scene's +z side faces the camera
+    let far = engine.scene.add_mesh("far".to_string(), Mesh::cube(1.0, 1));
+    let near = engine.scene.add_mesh("near".to_string(), Mesh::cube(1.0, 1));
+    engine.scene.nodes[near].transform = translation(0.0, 0.0, 2.0);
+    engine.frame_scene();
+    engine.render(0);
+
+    let hit = engine.pick(32.0, 32.0).unwrap();
+    assert_eq!(hit.mesh, engine.scene.nodes[near].meshes[0]);
+    assert!((hit.point.z - 2.5).abs() < 1e-9, "{}", hit.point.z);
+    // With the near cube moved aside the far one is found instead
+    engine.scene.nodes[near].transform = translation(10.0, 0.0, 2.0);
+    engine.render(0);
+    let hit = engine.pick(32.0, 32.0).unwrap();
+    assert_eq!(hit.mesh, engine.scene.nodes[far].meshes[0]);
+    assert!((hit.point.z - 0.5).abs() < 1e-9, "{}", hit.point.z);
+    // and the corner of the view shows nothing
+    assert!(engine.pick(0.5, 0.5).is_none());
+}
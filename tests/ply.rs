@@ -0,0 +1,67 @@
+use r3de::loaders::LoadError;
+use r3de::loaders::ply::parse_ply;
+
+const ASCII_HEADER: &str = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n";
+
+fn header_error(header: &str) -> (usize, String) {
+    match parse_ply(header.as_bytes()) {
+        Err(LoadError::Parse { line, message }) => (line, message),
+        Err(e) => panic!("expected a parse error, got {}", e),
+        Ok(_) => panic!("expected a parse error for {:?}", header),
+    }
+}
+
+#[test]
+fn malformed_headers_are_rejected() {
+    assert_eq!(header_error("plx\nformat ascii 1.0\nend_header\n").0, 1);
+    assert_eq!(header_error("ply\nformat ascii 1.0\nelement vertex 3\n").0, 4);
+    assert_eq!(header_error("ply\nformat ascii 1.0\nelement vertex lots\nend_header\n").0, 3);
+    assert_eq!(header_error("ply\nformat ascii 1.0\nproperty float x\nend_header\n").0, 3);
+    assert_eq!(header_error("ply\nformat ascii 1.0\nelement vertex 1\nproperty quad x\nend_header\n").0, 4);
+    assert_eq!(header_error("ply\nformat ascii 1.0\nvertices 3\nend_header\n").0, 3);
+    assert_eq!(header_error("ply\nelement vertex 0\nend_header\n"), (3, "header has no format line".to_string()));
+    assert!(matches!(parse_ply(b"ply\nformat binary_middle_endian 1.0\nend_header\n"), Err(LoadError::Unsupported(_))));
+    assert!(matches!(parse_ply(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nend_header\n0\n"), Err(LoadError::Parse { line: 0, .. })));
+}
+
+#[test]
+fn truncated_bodies_are_rejected() {
+    let full = format!("{}0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n", ASCII_HEADER);
+    assert_eq!(parse_ply(full.as_bytes()).unwrap().indices, vec![[0, 1, 2]]);
+
+    let short = format!("{}0 0 0\n1 0 0\n0 1\n", ASCII_HEADER);
+    assert!(matches!(parse_ply(short.as_bytes()), Err(LoadError::Parse { line: 12, .. })));
+    let bad = format!("{}0 0 0\n1 0 0\n0 one 0\n3 0 1 2\n", ASCII_HEADER);
+    assert!(matches!(parse_ply(bad.as_bytes()), Err(LoadError::Parse { line: 12, .. })));
+
+    // Binary bodies report how far short they fell
+    let mut data = ASCII_HEADER.replace("ascii", "binary_little_endian").into_bytes();
+    let header_len = data.len();
+    for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+        data.extend_from_slice(&v.to_le_bytes());
+    }
+    data.push(3);
+    for i in [0i32, 1, 2] {
+        data.extend_from_slice(&i.to_le_bytes());
+    }
+    assert_eq!(parse_ply(&data).unwrap().indices, vec![[0, 1, 2]]);
+    let full_len = data.len();
+    data.truncate(full_len - 2);
+    assert!(matches!(parse_ply(&data), Err(LoadError::Truncated { expected, found }) if expected == full_len && found == full_len - 2));
+    data.truncate(header_len + 10);
+    assert!(matches!(parse_ply(&data), Err(LoadError::Truncated { expected, .. }) if expected == header_len + 12));
+}
+
+#[test]
+fn faces_must_index_existing_vertices() {
+    let data = format!("{}0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n", ASCII_HEADER);
+    assert!(matches!(parse_ply(data.as_bytes()), Err(LoadError::BadIndex { line: 13, index: 3 })));
+
+    // Indices and list lengths have to be whole numbers, even where the header types them as floats
+    let float_header = ASCII_HEADER.replace("list uchar int", "list float float");
+    assert_eq!(parse_ply(format!("{}0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n", float_header).as_bytes()).unwrap().indices, vec![[0, 1, 2]]);
+    for face in ["3 0 1.5 2", "3 0 nan 2", "3 0 inf 2", "2.5 0 1 2"] {
+        let data = format!("{}0 0 0\n1 0 0\n0 1 0\n{}\n", float_header, face);
+        assert!(matches!(parse_ply(data.as_bytes()), Err(LoadError::Parse { line: 13, .. })), "{}", face);
+    }
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use std::sync::Arc;
+
+use eframe::egui::Color32;
+
+use r3de::color::{ Rgb, ToneMapping };
+use r3de::loaders::cube::parse_cube;
+use r3de::objs::{ DisplayBuffers, Mesh };
+use r3de::post::{ Lut, LutPreset, PostChain, PostPass };
+use common::new_engine;
+
+const SIZE: usize = 32;
+
+fn chain_with(passes: &[PostPass]) -> PostChain {
+    let mut chain = PostChain::new();
+    passes.iter().for_each(|p| chain.set_enabled(*p, true));
+    chain
+}
+
+fn grey(v: f64) -> Rgb {
+    [v; 3]
+}
+
+// Black on the left, white on the right, with the boundary stepping over one pixel every four rows
+fn staircase() -> Vec<Rgb> {
+    (0..SIZE*SIZE).map(|i| if i % SIZE > SIZE/2 + i/SIZE/4 { grey(1.0) } else { grey(0.0) }).collect()
+}
+
+fn close(a: Rgb, b: Rgb) -> bool {
+    (0..3).all(|k| (a[k] - b[k]).abs() < 1e-9)
+}
+
+#[test]
+fn nothing_changes_with_every_pass_off() {
+    let mut image = staircase();
+    PostChain::new().apply_linear(&mut image, SIZE, SIZE);
+    PostChain::new().apply_display(&mut image, SIZE, SIZE);
+    assert_eq!(image, staircase());
+}
+
+#[test]
+fn gamma_and_vignette_adjust_brightness() {
+    let mut image = vec![grey(0.25); SIZE*SIZE];
+    chain_with(&[PostPass::Gamma]).apply_display(&mut image, SIZE, SIZE);
+    // The default gamma of 1 leaves the image alone, the resolve being what encodes it as sRGB
+    assert!(close(image[0], grey(0.25)));
+    let mut chain = chain_with(&[PostPass::Gamma]);
+    chain.gamma = 2.0;
+    chain.apply_display(&mut image, SIZE, SIZE);
+    assert!(close(image[0], grey(0.5)));
+
+    let mut image = vec![grey(0.8); SIZE*SIZE];
+    chain_with(&[PostPass::Vignette]).apply_linear(&mut image, SIZE, SIZE);
+    assert!(close(image[SIZE/2*SIZE + SIZE/2], grey(0.8)));
+    assert!(image[0][0] < 0.5);
+}
+
+#[test]
+fn sobel_outlines_edges_and_fxaa_softens_them() {
+    let mut image = vec![grey(1.0); SIZE*SIZE];
+    image.iter_mut().skip(SIZE*SIZE/2).for_each(|c| *c = grey(0.5));
+    chain_with(&[PostPass::Sobel]).apply_display(&mut image, SIZE, SIZE);
+    // The rows either side of the step turn black, the rest stay as they were
+    assert_eq!(image[(SIZE/2 - 1)*SIZE + 5], grey(0.0));
+    assert_eq!(image[SIZE/2*SIZE + 5], grey(0.0));
+    assert_eq!(image[5], grey(1.0));
+    assert_eq!(image[(SIZE - 1)*SIZE + 5], grey(0.5));
+
+    let mut image = staircase();
+    chain_with(&[PostPass::Fxaa]).apply_display(&mut image, SIZE, SIZE);
+    let blended = image.iter().filter(|c| c[0] > 0.0 && c[0] < 1.0).count();
+    assert!(blended >= SIZE, "only {} pixels were blended", blended);
+    // Away from the edge the image is untouched
+    assert!(image.iter().enumerate().filter(|(i, _)| i % SIZE < 4 || i % SIZE > SIZE - 4).all(|(i, c)| *c == staircase()[i]));
+}
+
+#[test]
+fn bloom_spreads_light_from_bright_pixels_only() {
+    let mut image = vec![grey(0.5); SIZE*SIZE];
+    chain_with(&[PostPass::Bloom]).apply_linear(&mut image, SIZE, SIZE);
+    assert!(image.iter().all(|c| *c == grey(0.5)));
+
+    // A bright square in the middle glows onto the pixels around it
+    for y in 12..20 {
+        image[y*SIZE + 12..y*SIZE + 20].fill(grey(4.0));
+    }
+    chain_with(&[PostPass::Bloom]).apply_linear(&mut image, SIZE, SIZE);
+    assert!(image[16*SIZE + 21][0] > 0.6 && image[21*SIZE + 16][0] > 0.6);
+    assert_eq!(image[0], grey(0.5));
+}
+
+#[test]
+fn passes_run_in_the_chosen_order() {
+    // Graded to black then brightened by gamma stays black; the other way round it doesn't
+    let mut chain = chain_with(&[PostPass::Gamma, PostPass::ColorGrade]);
+    chain.gamma = 2.2;
+    chain.lut = Some(Arc::new(Lut::from_fn(33, |c| c.map(|v| (v - 0.5).max(0.0)))));
+    let position = |chain: &PostChain, pass: PostPass| chain.passes.iter().position(|(p, _)| *p == pass).unwrap();
+    let mut image = vec![grey(0.4); SIZE*SIZE];
+    chain.apply_display(&mut image, SIZE, SIZE);
+    assert_eq!(image[0], grey(0.0));
+
+    chain.move_pass(position(&chain, PostPass::Gamma), position(&chain, PostPass::ColorGrade));
+    assert!(position(&chain, PostPass::Gamma) < position(&chain, PostPass::ColorGrade));
+    let mut image = vec![grey(0.4); SIZE*SIZE];
+    chain.apply_display(&mut image, SIZE, SIZE);
+    assert!(image[0][0] > 0.0);
+}
+
+#[test]
+fn display_passes_wait_for_the_tone_mapped_frame() {
+    let linear: Vec<PostPass> = PostPass::ALL.iter().filter(|p| p.is_linear()).cloned().collect();
+    assert!(linear.contains(&PostPass::Bloom) && linear.contains(&PostPass::Vignette) && linear.len() == 2);
+
+    // A highlight far past white is left alone by FXAA and grading while the frame is still HDR
+    let mut chain = chain_with(&[PostPass::Fxaa, PostPass::ColorGrade, PostPass::Gamma, PostPass::Sobel]);
+    chain.lut = Some(Arc::new(LutPreset::Sepia.build()));
+    let hdr: Vec<Rgb> = staircase().iter().map(|c| c.map(|v| v*20.0)).collect();
+    let mut image = hdr.clone();
+    chain.apply_linear(&mut image, SIZE, SIZE);
+    assert_eq!(image, hdr);
+
+    // Once tone mapped it smooths the same as an edge up to plain white
+    let fxaa = chain_with(&[PostPass::Fxaa]);
+    let mut mapped: Vec<Rgb> = hdr.iter().map(|c| ToneMapping::Clamp.display(*c)).collect();
+    fxaa.apply_display(&mut mapped, SIZE, SIZE);
+    let mut white = staircase();
+    fxaa.apply_display(&mut white, SIZE, SIZE);
+    assert!(mapped.iter().zip(white.iter()).all(|(a, b)| close(*a, *b)));
+}
+
+#[test]
+fn bloom_runs_on_the_frame_before_tone_mapping() {
+    // A cube lit past white by the exposure, with bloom that only light brighter than white sets off
+    let render = |exposure: f64| {
+        let buffers = DisplayBuffers::new([SIZE, SIZE]);
+        let mut engine = new_engine(&buffers);
+        engine.settings.ground_plane = false;
+        engine.settings.tone_mapping = ToneMapping::Clamp;
+        engine.settings.exposure = exposure;
+        engine.post = chain_with(&[PostPass::Bloom]);
+        engine.post.bloom_threshold = 2.0;
+        engine.post.bloom_radius = 6;
+        engine.scene.add_mesh("cube".to_string(), Mesh::cube(0.5, 1));
+        engine.frame_sphere(&Mesh::cube(1.0, 1).bounding_sphere());
+        engine.render(0);
+        let pixels = buffers.bufs[0].lock().unwrap().clone();
+        pixels
+    };
+
+    // Clamped output never gets past the threshold, so any glow around the cube came from the HDR frame
+    let bright = render(4.0);
+    let glowing = bright.iter().filter(|c| **c != Color32::BLACK).count();
+    let plain = render(0.0);
+    let lit = plain.iter().filter(|c| **c != Color32::BLACK).count();
+    assert!(lit > 0);
+    assert!(glowing > lit + SIZE, "{} glowing pixels, {} lit", glowing, lit);
+}
+
+#[test]
+fn cube_luts_are_loaded_and_interpolated() {
+    let mut text = String::from("TITLE \"invert\"\nLUT_3D_SIZE 2\n");
+    for i in 0..8 {
+        let (r, g, b) = (i & 1, i >> 1 & 1, i >> 2 & 1);
+        text += &format!("{} {} {}\n", 1 - r, 1 - g, 1 - b);
+    }
+    let lut = parse_cube(text.as_bytes()).unwrap();
+    let c = lut.sample([0.25, 0.5, 1.0]);
+    assert!((c[0] - 0.75).abs() < 1e-9 && (c[1] - 0.5).abs() < 1e-9 && c[2].abs() < 1e-9);
+    let same = Lut::identity(5).sample([0.3, 0.6, 0.9]);
+    assert!((same[0] - 0.3).abs() < 1e-9 && (same[1] - 0.6).abs() < 1e-9 && (same[2] - 0.9).abs() < 1e-9);
+
+    assert!(parse_cube("LUT_3D_SIZE 2\n0 0 0\n".as_bytes()).is_err());
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use r3de::objs::{ Mesh, Vec3d };
+use common::signed_volume;
+
+// Fraction of triangles whose winding agrees with all three vertex normals
+fn consistent_fraction(mesh: &Mesh) -> f64 {
+    let agree = mesh.indices.iter().filter(|[a, b, c]| {
+        let (pa, pb, pc) = (mesh.positions[*a], mesh.positions[*b], mesh.positions[*c]);
+        let n = (pb - pa).cross(&(pc - pa));
+        [a, b, c].iter().all(|v| n.dot(&mesh.normals[**v]) > 0.0)
+    }).count();
+    agree as f64/mesh.indices.len() as f64
+}
+
+fn assert_well_formed(mesh: &Mesh) {
+    assert!(!mesh.indices.is_empty());
+    assert_eq!(mesh.normals.len(), mesh.positions.len());
+    assert!(mesh.indices.iter().flatten().all(|i| *i < mesh.positions.len()));
+    for n in mesh.normals.iter() {
+        assert!((n.dot(n) - 1.0).abs() < 1e-9, "normal is not unit length");
+    }
+}
+
+#[test]
+fn closed_primitives_wind_outwards() {
+    let pi = std::f64::consts::PI;
+    let cases = [
+        (Mesh::cube(2.0, 3), 8.0, 1e-9),
+        (Mesh::uv_sphere(1.0, 32, 16), 4.0/3.0*pi, 0.05),
+        (Mesh::icosphere(1.0, 3), 4.0/3.0*pi, 0.05),
+        (Mesh::cylinder(1.0, 2.0, 32, 2), 2.0*pi, 0.05),
+        (Mesh::cone(1.0, 3.0, 32, 2), pi, 0.05),
+        (Mesh::torus(2.0, 0.5, 32, 16), 2.0*pi*pi*2.0*0.25, 0.05),
+    ];
+    for (mesh, volume, tolerance) in cases.iter() {
+        assert_well_formed(mesh);
+        assert_eq!(consistent_fraction(mesh), 1.0);
+        let v = signed_volume(mesh);
+        assert!((v - volume).abs() <= volume*tolerance, "volume {} expected {}", v, volume);
+    }
+}
+
+#[test]
+fn plane_faces_up() {
+    let mesh = Mesh::plane(4.0, 2.0, 4, 2);
+    assert_well_formed(&mesh);
+    assert_eq!(mesh.indices.len(), 16);
+    assert_eq!(consistent_fraction(&mesh), 1.0);
+    assert!(mesh.normals.iter().all(|n| n.y == 1.0));
+}
+
+#[test]
+fn teapot_is_outward_facing_and_sized() {
+    let mesh = Mesh::teapot(3.0, 6);
+    assert_well_formed(&mesh);
+    assert!(consistent_fraction(&mesh) > 0.99);
+    assert!(signed_volume(&mesh) > 0.0);
+
+    let (min_y, max_y) = mesh.positions.iter().fold((f64::MAX, f64::MIN), |(lo, hi), p: &Vec3d| (lo.min(p.y), hi.max(p.y)));
+    assert!((max_y - min_y - 3.0).abs() < 1e-9);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use std::collections::HashSet;
+
+use eframe::egui::Color32;
+
+use r3de::objs::{ DisplayBuffers, Mesh, RenderMode };
+use common::new_engine;
+
+const SIZE: usize = 96;
+
+// Renders the mesh alone in the given mode, framed to fit the unit cube, and returns the
+// pixels anything was drawn into
+fn drawn_pixels(mesh: Mesh, mode: RenderMode) -> HashSet<(usize, usize)> {
+    let buffers = DisplayBuffers::new([SIZE, SIZE]);
+    let mut engine = new_engine(&buffers);
+    engine.settings.ground_plane = false;
+    engine.settings.point_size = 1.0;
+    let node = engine.scene.add_mesh("mesh".to_string(), mesh);
+    let mesh_index = engine.scene.nodes[node].meshes[0];
+    engine.scene.meshes[mesh_index].render_mode = mode;
+    engine.frame_sphere(&Mesh::cube(1.0, 1).bounding_sphere());
+    engine.render(0);
+
+    let pixels = buffers.bufs[0].lock().unwrap();
+    (0..SIZE*SIZE).filter(|i| pixels[*i] != Color32::BLACK).map(|i| (i % SIZE, i / SIZE)).collect()
+}
+
+// The cube's face towards the camera, which is its +z side
+fn front_face() -> Mesh {
+    let cube = Mesh::cube(1.0, 1);
+    let indices = cube.indices.iter().filter(|f| f.iter().all(|v| cube.positions[*v].z > 0.49)).cloned().collect();
+    Mesh::new(cube.positions.clone(), indices)
+}
+
+#[test]
+fn hidden_line_leaves_only_the_visible_edges() {
+    let hidden = drawn_pixels(Mesh::cube(1.0, 1), RenderMode::HiddenLine);
+    let wireframe = drawn_pixels(Mesh::cube(1.0, 1), RenderMode::Wireframe);
+    let front = drawn_pixels(front_face(), RenderMode::Wireframe);
+    assert!(!front.is_empty());
+
+    // Every edge pixel left belongs to the front face, and next to all of the front face's are left
+    assert!(hidden.is_subset(&front), "{:?}", hidden.difference(&front).collect::<Vec<_>>());
+    assert!(hidden.len()*100 >= front.len()*95, "{} of {}", hidden.len(), front.len());
+    // while the back of the cube shows through the plain wireframe
+    assert!(wireframe.difference(&front).count() > SIZE/2);
+}
+
+#[test]
+fn point_mode_draws_only_the_vertices() {
+    let points = drawn_pixels(Mesh::cube(1.0, 1), RenderMode::Points);
+    let wireframe = drawn_pixels(Mesh::cube(1.0, 1), RenderMode::Wireframe);
+    // Eight corners a pixel across, and each of them where the wireframe's edges meet
+    assert!(!points.is_empty() && points.len() <= 8*4, "{}", points.len());
+    let near_edge = |(x, y): (usize, usize)| (x.saturating_sub(1)..=x + 1).any(|x| (y.saturating_sub(1)..=y + 1).any(|y| wireframe.contains(&(x, y))));
+    assert!(points.iter().all(|p| near_edge(*p)));
+    // Nothing inside the faces
+    assert!(!points.contains(&(SIZE/2, SIZE/2)));
+    let clusters = points.iter().filter(|(x, y)| !points.contains(&(x.wrapping_sub(1), *y)) && !points.contains(&(*x, y.wrapping_sub(1)))).count();
+    assert!(clusters >= 8, "{}", clusters);
+}
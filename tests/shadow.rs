@@ -0,0 +1,65 @@
+use r3de::bounds::BoundingSphere;
+use r3de::objs::{ Matrix4x4, Mesh, Vec3d };
+use r3de::scene::Light;
+use r3de::shadow::ShadowMap;
+
+// A 2 x 2 square at height y = -1, lit from above (light travelling +y) over a floor at y = 0
+fn occluded_map(light: &Light) -> ShadowMap {
+    let square = Mesh::new(
+        vec![Vec3d::new(-1.0, -1.0, -1.0), Vec3d::new(1.0, -1.0, -1.0), Vec3d::new(1.0, -1.0, 1.0), Vec3d::new(-1.0, -1.0, 1.0)],
+        vec![[0, 1, 2], [0, 2, 3]],
+    );
+    let bounds = BoundingSphere { center: Vec3d::new(0.0, 0.0, 0.0), radius: 4.0 };
+    let mut map = ShadowMap::new(light, &bounds, 256).unwrap();
+    map.draw_mesh(&square, &Matrix4x4::identity());
+    map
+}
+
+#[test]
+fn directional_light_shadows_what_is_under_the_occluder() {
+    let map = occluded_map(&Light::directional(Vec3d::new(0.0, 1.0, 0.0), 1.0));
+    assert_eq!(map.lit_fraction(&Vec3d::new(0.0, 0.0, 0.0), 1.0, 1), 0.0);
+    assert_eq!(map.lit_fraction(&Vec3d::new(3.0, 0.0, 0.0), 1.0, 1), 1.0);
+    // The occluder doesn't shadow itself
+    assert_eq!(map.lit_fraction(&Vec3d::new(0.2, -1.0, 0.3), 1.0, 1), 1.0);
+}
+
+#[test]
+fn spot_light_shadows_what_is_under_the_occluder() {
+    let map = occluded_map(&Light::spot(Vec3d::new(0.0, -3.0, 0.0), Vec3d::new(0.0, 1.0, 0.0), 1.0, 1.0));
+    assert_eq!(map.lit_fraction(&Vec3d::new(0.0, 0.0, 0.0), 1.0, 1), 0.0);
+    assert_eq!(map.lit_fraction(&Vec3d::new(2.5, 0.0, 0.0), 1.0, 1), 1.0);
+    assert_eq!(map.lit_fraction(&Vec3d::new(0.2, -1.0, 0.3), 1.0, 1), 1.0);
+}
+
+#[test]
+fn pcf_softens_the_shadow_edge() {
+    let map = occluded_map(&Light::directional(Vec3d::new(0.0, 1.0, 0.0), 1.0));
+    // Right on the occluder's edge the filter sees texels on both sides
+    let edge = map.lit_fraction(&Vec3d::new(1.0, 0.0, 0.0), 1.0, 2);
+    assert!(edge > 0.0 && edge < 1.0, "{}", edge);
+}
+
+#[test]
+fn sloped_receivers_dont_shadow_themselves_under_pcf() {
+    let light = Light::directional(Vec3d::new(0.0, 1.0, 0.0), 1.0);
+    let bounds = BoundingSphere { center: Vec3d::new(0.0, 0.0, 0.0), radius: 4.0 };
+    for degrees in [30.0f64, 60.0, 75.0] {
+        // A 2 x 2 ramp through the origin, tilted about z so it meets the light at the given angle
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let at = |s: f64, t: f64| Vec3d::new(s*cos, -s*sin, t);
+        let ramp = Mesh::new(vec![at(-1.0, -1.0), at(1.0, -1.0), at(1.0, 1.0), at(-1.0, 1.0)], vec![[0, 1, 2], [0, 2, 3]]);
+        let mut map = ShadowMap::new(&light, &bounds, 256).unwrap();
+        map.draw_mesh(&ramp, &Matrix4x4::identity());
+
+        for pcf_radius in [1, 2, 3] {
+            for i in 0..=8 {
+                for j in 0..=8 {
+                    let p = at(-0.8 + 0.2*i as f64, -0.8 + 0.2*j as f64);
+                    let lit = map.lit_fraction(&p, cos, pcf_radius);
+                    assert_eq!(lit, 1.0, "{} degrees, radius {}, at ({}, {})", degrees, pcf_radius, p.x, p.z);
+                }
+            }
+        }
+    }
+}
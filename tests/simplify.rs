@@ -0,0 +1,76 @@
+mod common;
+
+use r3de::lod::screen_size;
+use r3de::bounds::BoundingSphere;
+use r3de::objs::{ DisplayBuffers, Matrix4x4, Mesh, Vec3d };
+use r3de::scene::Scene;
+use common::{ new_engine, signed_volume };
+
+fn sphere(subdivisions: usize) -> Mesh {
+    let mut mesh = Mesh::icosphere(1.0, subdivisions);
+    mesh.normals.clear();
+    mesh.uvs.clear();
+    mesh.weld(1e-9);
+    mesh
+}
+
+#[test]
+fn simplifying_reaches_the_target_and_keeps_the_shape() {
+    let mut mesh = sphere(4);
+    let volume = signed_volume(&mesh);
+    mesh.simplify(500);
+    assert!(mesh.indices.len() <= 500 && mesh.indices.len() > 400, "{}", mesh.indices.len());
+    assert!(mesh.indices.iter().flatten().all(|v| *v < mesh.positions.len()));
+    // Still a closed surface, still facing out, and every vertex still close to the sphere
+    assert!((signed_volume(&mesh) - volume).abs() < 0.05*volume);
+    assert!(mesh.positions.iter().all(|p| (p.dot(p).sqrt() - 1.0).abs() < 0.05));
+    assert_eq!(mesh.positions.len() + mesh.indices.len()/2, 2 + mesh.indices.len(), "Euler characteristic changed");
+}
+
+#[test]
+fn flat_grid_collapses_without_moving_its_border() {
+    let mut mesh = Mesh::plane(2.0, 2.0, 16, 16);
+    mesh.normals.clear();
+    mesh.uvs.clear();
+    mesh.simplify(2);
+    assert!(mesh.indices.len() <= 8, "{}", mesh.indices.len());
+    // A flat square can go all the way down without losing its outline or its area
+    let area: f64 = mesh.indices.iter().map(|[a, b, c]| 0.5*(mesh.positions[*b] - mesh.positions[*a]).cross(&(mesh.positions[*c] - mesh.positions[*a])).y.abs()).sum();
+    assert!((area - 4.0).abs() < 1e-9, "{}", area);
+    assert!(mesh.positions.iter().all(|p| p.y.abs() < 1e-12));
+}
+
+#[test]
+fn levels_are_picked_by_size_on_screen() {
+    let mut scene = Scene::new();
+    scene.add_mesh("sphere".to_string(), sphere(4));
+    assert_eq!(scene.add_lods(0, 3), 3);
+    let lod = scene.lod(0).unwrap();
+    let counts: Vec<usize> = lod.levels.iter().map(|l| scene.meshes[l.mesh].indices.len()).collect();
+    assert!(counts.windows(2).all(|w| w[1] < w[0]), "{:?}", counts);
+
+    let mut mat_proj = Matrix4x4::new(vec![vec![0.0; 4]; 4]);
+    mat_proj.make_projection(90.0, 1.0, 0.1, 1000.0);
+    let at = |distance: f64| BoundingSphere { center: Vec3d::new(0.0, 0.0, distance), radius: 1.0 };
+    assert_eq!(lod.select(screen_size(&at(0.5), &mat_proj)), 0);
+    assert_eq!(lod.select(screen_size(&at(1.5), &mat_proj)), 0);
+    assert_eq!(lod.select(screen_size(&at(3.0), &mat_proj)), lod.levels[1].mesh);
+    assert_eq!(lod.select(screen_size(&at(1000.0), &mat_proj)), lod.levels[3].mesh);
+}
+
+#[test]
+fn levels_are_only_made_on_request_and_for_large_meshes() {
+    let buffers = DisplayBuffers::new([16, 16]);
+    let mut engine = new_engine(&buffers);
+    engine.scene.add_mesh("small".to_string(), sphere(2));
+    engine.scene.add_mesh("large".to_string(), sphere(5));
+    assert!(engine.scene.lods.is_empty());
+
+    assert_eq!(engine.generate_lods(), 1);
+    assert!(engine.scene.lod(0).is_none() && engine.scene.lod(1).is_some());
+    // The levels are scene meshes, but none a node draws, so they stay out of the per-mesh lists
+    assert!(engine.scene.meshes.len() > 2);
+    assert_eq!(engine.scene.drawn_meshes(), vec![0, 1]);
+    // Meshes that already have levels keep them
+    assert_eq!(engine.generate_lods(), 0);
+}
@@ -0,0 +1,44 @@
+use std::f64::consts::FRAC_PI_2;
+
+use r3de::animation::{ axis_angle, Trs };
+use r3de::skin::skinned_cylinder;
+
+#[test]
+fn every_vertex_is_fully_weighted() {
+    let scene = skinned_cylinder(0.5, 4.0, 16, 3);
+    let mesh = &scene.meshes[0];
+    assert!(mesh.is_skinned());
+    for (joints, weights) in mesh.joints.iter().zip(mesh.weights.iter()) {
+        assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(joints.iter().all(|j| *j < scene.skins[0].joints.len()));
+    }
+}
+
+#[test]
+fn rest_pose_leaves_the_mesh_in_place() {
+    let mut scene = skinned_cylinder(0.5, 4.0, 16, 3);
+    let before = scene.meshes[0].positions.clone();
+    scene.pose_meshes();
+    for (a, b) in before.iter().zip(scene.meshes[0].positions.iter()) {
+        assert!((*a - *b).dot(&(*a - *b)) < 1e-18);
+    }
+}
+
+#[test]
+fn turning_a_joint_carries_its_vertices_round_it() {
+    // Two bones: the lower from y = -2 and the upper from y = 0, which the top cap follows alone
+    let mut scene = skinned_cylinder(0.5, 4.0, 16, 2);
+    let top = scene.meshes[0].positions.iter().position(|p| p.y > 1.999 && p.x.abs() < 1e-9 && p.z.abs() < 1e-9).unwrap();
+    let mut pose = Trs::from_matrix(&scene.nodes[1].transform);
+    pose.rotation = axis_angle([0.0, 0.0, 1.0], FRAC_PI_2);
+    scene.nodes[1].transform = pose.to_matrix();
+    scene.pose_meshes();
+
+    let mesh = &scene.meshes[0];
+    let p = mesh.positions[top];
+    assert!((p.x.abs() - 2.0).abs() < 1e-9 && p.y.abs() < 1e-9, "({}, {}, {})", p.x, p.y, p.z);
+    // The bottom follows the unmoved lower bone
+    assert!(mesh.positions.iter().zip(mesh.rest_positions.iter()).filter(|(_, r)| r.y < -1.999).all(|(p, r)| (*p - *r).dot(&(*p - *r)) < 1e-18));
+    // Normals stay unit length
+    assert!(mesh.normals.iter().all(|n| (n.dot(n) - 1.0).abs() < 1e-9));
+}
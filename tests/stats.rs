@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use r3de::stats::{ FrameHistory, RenderStats };
+
+#[test]
+fn render_stats_start_from_zero() {
+    let stats = RenderStats::new();
+    assert_eq!(stats.tris_submitted + stats.tris_culled + stats.tris_clipped + stats.tris_rasterized + stats.pixels_written, 0);
+    assert_eq!(stats.frame_time, Duration::ZERO);
+}
+
+#[test]
+fn frame_history_averages_over_its_window() {
+    let mut history = FrameHistory::new(3);
+    assert_eq!(history.average(), 0.0);
+    assert_eq!(history.max(), 0.0);
+
+    history.push(Duration::from_millis(10));
+    history.push(Duration::from_millis(20));
+    assert!((history.average() - 15.0).abs() < 1e-9);
+    assert!((history.max() - 20.0).abs() < 1e-9);
+
+    // Once full, the oldest frame drops out
+    history.push(Duration::from_millis(30));
+    history.push(Duration::from_millis(60));
+    assert_eq!(history.frame_times.len(), 3);
+    assert!((history.average() - 110.0/3.0).abs() < 1e-9);
+    assert!((history.max() - 60.0).abs() < 1e-9);
+}
@@ -0,0 +1,52 @@
+use r3de::loaders::LoadError;
+use r3de::loaders::stl::parse_stl;
+
+// Binary STL with the given facet count in its header followed by `facets` zeroed facets
+fn binary(count: u32, facets: usize) -> Vec<u8> {
+    let mut data = vec![0u8; 80];
+    data.extend_from_slice(&count.to_le_bytes());
+    data.resize(data.len() + facets*50, 0);
+    data
+}
+
+#[test]
+fn binary_files_shorter_than_their_header_are_truncated() {
+    assert!(matches!(parse_stl(&[0u8; 40]), Err(LoadError::Truncated { expected: 84, found: 40 })));
+
+    let mut data = binary(2, 2);
+    data.truncate(84 + 50 + 20);
+    assert!(matches!(parse_stl(&data), Err(LoadError::Truncated { expected: 184, found: 154 })));
+}
+
+#[test]
+fn facet_counts_past_the_end_of_the_file_are_rejected() {
+    assert!(matches!(parse_stl(&binary(5, 1)), Err(LoadError::Truncated { expected: 334, found: 134 })));
+    assert!(matches!(parse_stl(&binary(u32::MAX, 1)), Err(LoadError::Truncated { found: 134, .. })));
+
+    // Trailing bytes after the promised facets are ignored
+    let mut data = binary(1, 1);
+    data.extend_from_slice(&[0u8; 7]);
+    assert_eq!(parse_stl(&data).unwrap().indices.len(), 1);
+}
+
+fn ascii_error_line(text: &str) -> usize {
+    match parse_stl(text.as_bytes()) {
+        Err(LoadError::Parse { line, .. }) => line,
+        Err(e) => panic!("expected a parse error, got {}", e),
+        Ok(_) => panic!("expected a parse error for {:?}", text),
+    }
+}
+
+#[test]
+fn malformed_ascii_reports_the_offending_line() {
+    let facet = "solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid t\n";
+    assert_eq!(parse_stl(facet.as_bytes()).unwrap().indices.len(), 1);
+
+    assert_eq!(ascii_error_line("solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nendloop\nendfacet\n"), 7);
+    assert_eq!(ascii_error_line("solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 zero 0\n"), 4);
+    assert_eq!(ascii_error_line("solid t\nfacet normal 0 0\n"), 2);
+    assert_eq!(ascii_error_line("solid t\nfacet 0 0 1\n"), 2);
+    assert_eq!(ascii_error_line("solid t\nvertex 0 0 0\n"), 2);
+    assert_eq!(ascii_error_line("solid t\nfacets normal 0 0 1\n"), 2);
+    assert_eq!(ascii_error_line("solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\n"), 4);
+}
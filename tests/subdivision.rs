@@ -0,0 +1,118 @@
+mod common;
+
+use r3de::loaders::obj::parse_obj;
+use r3de::morph::MorphTarget;
+use r3de::objs::{ Mesh, Vec3d };
+use common::close;
+
+const CUBE: &str = "
+v -1 -1 -1
+v 1 -1 -1
+v 1 1 -1
+v -1 1 -1
+v -1 -1 1
+v 1 -1 1
+v 1 1 1
+v -1 1 1
+f 1 4 3 2
+f 5 6 7 8
+f 1 2 6 5
+f 2 3 7 6
+f 3 4 8 7
+f 4 1 5 8
+";
+
+// A 2 x 2 grid of quads in the y = 0 plane, open all round
+const GRID: &str = "
+v -1 0 -1
+v 0 0 -1
+v 1 0 -1
+v -1 0 0
+v 0 0 0
+v 1 0 0
+v -1 0 1
+v 0 0 1
+v 1 0 1
+f 1 4 5 2
+f 2 5 6 3
+f 4 7 8 5
+f 5 8 9 6
+";
+
+#[test]
+fn obj_polygons_are_kept_and_triangulated() {
+    let mesh = parse_obj("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv -1 0.5 0\nf 1 2 3 4 5\nf 1 2 3\n".as_bytes()).unwrap();
+    assert_eq!(mesh.polygons, vec![vec![0, 1, 2, 3, 4], vec![0, 1, 2]]);
+    assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3], [0, 3, 4], [0, 1, 2]]);
+
+    let triangles = parse_obj("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3\n".as_bytes()).unwrap();
+    assert!(triangles.polygons.is_empty());
+    assert!(parse_obj("v 0 0 0\nv 1 0 0\nf 1 2\n".as_bytes()).is_err());
+}
+
+#[test]
+fn catmull_clark_turns_a_cube_into_quads_and_rounds_its_corners() {
+    let mut mesh = parse_obj(CUBE.as_bytes()).unwrap();
+    mesh.subdivide_catmull_clark(1);
+    // 8 corners, 12 edge points and 6 face points, with four quads per face
+    assert_eq!(mesh.positions.len(), 26);
+    assert_eq!(mesh.polygons.len(), 24);
+    assert!(mesh.polygons.iter().all(|f| f.len() == 4));
+    assert_eq!(mesh.indices.len(), 48);
+    // A corner goes to (F + 2R)/3 with F = (1/3, 1/3, 1/3) and R = (2/3, 2/3, 2/3)
+    let corner = mesh.positions[6];
+    assert!(close(corner.x, 5.0/9.0) && close(corner.y, 5.0/9.0) && close(corner.z, 5.0/9.0));
+
+    mesh.subdivide_catmull_clark(2);
+    assert_eq!(mesh.polygons.len(), 24*16);
+    assert!(mesh.positions.iter().all(|p| p.x.abs() < 1.0 && p.y.abs() < 1.0 && p.z.abs() < 1.0));
+}
+
+#[test]
+fn boundaries_stay_on_their_outline() {
+    let mut mesh = parse_obj(GRID.as_bytes()).unwrap();
+    mesh.subdivide_catmull_clark(2);
+    assert_eq!(mesh.polygons.len(), 64);
+    assert!(mesh.positions.iter().all(|p| close(p.y, 0.0) && p.x.abs() <= 1.0 + 1e-9 && p.z.abs() <= 1.0 + 1e-9));
+    // The corners belong to a single face each, so they stay put
+    for (x, z) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+        assert!(mesh.positions.iter().any(|p| close(p.x, x) && close(p.z, z)));
+    }
+    // Boundary vertices slide along the edge they are on
+    let on_border = mesh.positions.iter().filter(|p| close(p.x.abs(), 1.0) || close(p.z.abs(), 1.0)).count();
+    assert_eq!(on_border, 4*8);
+}
+
+#[test]
+fn loop_subdivision_quadruples_triangles_and_smooths_the_surface() {
+    let mut mesh = Mesh::icosphere(1.0, 0);
+    mesh.uvs.clear();
+    mesh.weld(1e-9);
+    let (vertices, triangles) = (mesh.positions.len(), mesh.indices.len());
+    mesh.subdivide_loop(2);
+    assert_eq!(mesh.indices.len(), triangles*16);
+    // Closed, so each level adds one vertex per edge: V' = V + 3F/2
+    assert_eq!(mesh.positions.len(), vertices + 3*triangles/2 + 3*4*triangles/2);
+    assert_eq!(mesh.normals.len(), mesh.positions.len());
+    assert!(mesh.normals.iter().all(|n| close(n.dot(n), 1.0)));
+    // Loop surfaces shrink inside the control mesh but end up much rounder than it
+    let radii: Vec<f64> = mesh.positions.iter().map(|p| p.dot(p).sqrt()).collect();
+    let (min, max) = radii.iter().fold((f64::MAX, 0.0f64), |(lo, hi), r| (lo.min(*r), hi.max(*r)));
+    assert!(max <= 1.0 && max - min < 0.05, "{} to {}", min, max);
+}
+
+#[test]
+fn morph_targets_that_only_move_normals_are_subdivided() {
+    let mut mesh = parse_obj(CUBE.as_bytes()).unwrap();
+    mesh.compute_smooth_normals(180.0);
+    let mut target = MorphTarget::new("Normals".to_string(), Vec::new());
+    target.normals = vec![Vec3d::new(0.0, 1.0, 0.0); mesh.positions.len()];
+    mesh.morph_targets.push(target);
+    mesh.morph_weights.push(0.5);
+    mesh.subdivide_catmull_clark(1);
+
+    let target = &mesh.morph_targets[0];
+    assert!(target.positions.is_empty());
+    assert_eq!(target.normals.len(), mesh.positions.len());
+    assert!(target.normals.iter().all(|n| close(n.y, 1.0)));
+}
@@ -0,0 +1,73 @@
+use eframe::egui::Color32;
+
+use r3de::color::{ self, ToneMapping };
+use r3de::framebuffer::{ AaMode, Framebuffer };
+use r3de::scene::{ AlphaMode, Material };
+
+const RED: [f64; 3] = [1.0, 0.0, 0.0];
+const BLUE: [f64; 3] = [0.0, 0.0, 1.0];
+
+fn resolved(target: &Framebuffer) -> Color32 {
+    let mut out = [Color32::BLACK];
+    target.resolve(&mut out, ToneMapping::Clamp, 0.0);
+    out[0]
+}
+
+#[test]
+fn only_blended_materials_are_see_through() {
+    let mut material = Material::new("glass".to_string());
+    material.base_color[3] = 0.25;
+    assert_eq!(material.opacity(), None);
+    material.alpha_mode = AlphaMode::Blend;
+    assert_eq!(material.opacity(), Some(0.25));
+}
+
+#[test]
+fn surfaces_behind_what_is_drawn_are_rejected_per_sample() {
+    let mut target = Framebuffer::new([1, 1], AaMode::Msaa4x);
+    target.put_depth(0, 0, 0b0011, 5.0);
+    assert_eq!(target.depth_test(0, 0, 0b1111, 4.0), 0b1111);
+    assert_eq!(target.depth_test(0, 0, 0b1111, 6.0), 0b1100);
+    assert_eq!(target.depth_test(0, 0, 0b0011, 6.0), 0);
+    assert_eq!(target.depth_test(1, 0, 0b1111, 1.0), 0);
+}
+
+#[test]
+fn sorted_blending_depends_on_order() {
+    let mut target = Framebuffer::new([1, 1], AaMode::Off);
+    target.blend(0, 0, 1, RED, 0.5);
+    target.blend(0, 0, 1, BLUE, 0.5);
+    assert_eq!(resolved(&target), color::to_srgb([0.25, 0.0, 0.5]));
+
+    target.clear([0.0; 3]);
+    target.blend(0, 0, 1, BLUE, 0.5);
+    target.blend(0, 0, 1, RED, 0.5);
+    assert_eq!(resolved(&target), color::to_srgb([0.5, 0.0, 0.25]));
+}
+
+#[test]
+fn weighted_blending_is_order_independent() {
+    let draw = |layers: &[([f64; 3], f64)]| {
+        let mut target = Framebuffer::new([1, 1], AaMode::Off);
+        target.clear([0.0, 1.0, 0.0]);
+        for (color, z) in layers {
+            target.accumulate(0, 0, 1, *color, 0.5, *z);
+        }
+        target.composite_transparency();
+        resolved(&target)
+    };
+    let (a, b) = (draw(&[(RED, 4.0), (BLUE, 6.0)]), draw(&[(BLUE, 6.0), (RED, 4.0)]));
+    assert_eq!(a, b);
+    // A quarter of the green behind shows through, and the nearer red outweighs the blue
+    assert_eq!(a.g(), color::to_srgb([0.25; 3]).g());
+    assert!(a.r() > a.b() && a.b() > 0);
+
+    // A single layer is blended exactly
+    let mut target = Framebuffer::new([1, 1], AaMode::Off);
+    target.accumulate(0, 0, 1, RED, 0.25, 5.0);
+    target.composite_transparency();
+    assert_eq!(resolved(&target), color::to_srgb([0.25, 0.0, 0.0]));
+    // and what was accumulated is gone once composited
+    target.composite_transparency();
+    assert_eq!(resolved(&target), color::to_srgb([0.25, 0.0, 0.0]));
+}